@@ -130,6 +130,48 @@ pub mod config {
         }
     }
 
+    #[cfg(feature = "tracker")]
+    #[derive(Clone, Debug, Default, Deserialize)]
+    pub struct Tracker {
+        retention: Option<u64>,
+        #[serde(alias = "rollup-retention")]
+        rollup_retention: Option<u64>,
+        #[serde(alias = "maintenance-interval")]
+        maintenance_interval: Option<u64>,
+        #[serde(alias = "flush-size")]
+        flush_size: Option<usize>,
+        #[serde(alias = "flush-interval")]
+        flush_interval: Option<u64>,
+    }
+
+    #[cfg(feature = "tracker")]
+    impl Tracker {
+        /// Days to keep raw events and sessions, `None` means keep forever
+        pub fn retention(&self) -> Option<u64> {
+            self.retention.filter(|days| *days > 0)
+        }
+
+        /// Days to keep hourly rollups, `None` means keep forever
+        pub fn rollup_retention(&self) -> Option<u64> {
+            self.rollup_retention.filter(|days| *days > 0)
+        }
+
+        /// Interval of maintenance task in seconds
+        pub fn maintenance_interval(&self) -> u64 {
+            self.maintenance_interval.unwrap_or(3600).max(60)
+        }
+
+        /// Flush buffered events if buffer reach this size
+        pub fn flush_size(&self) -> usize {
+            self.flush_size.unwrap_or(64).max(1)
+        }
+
+        /// Flush buffered events interval in milliseconds
+        pub fn flush_interval(&self) -> u64 {
+            self.flush_interval.unwrap_or(1000).max(10)
+        }
+    }
+
     #[derive(Clone, Debug, Default, Deserialize)]
     pub struct Message {
         #[serde(alias = "move-to-channel")]
@@ -144,6 +186,15 @@ pub mod config {
         }
     }
 
+    #[derive(Clone, Copy, Debug, Default, Deserialize, PartialEq, Eq)]
+    #[serde(rename_all = "lowercase")]
+    pub enum Locale {
+        #[default]
+        En,
+        Zh,
+        Ja,
+    }
+
     #[derive(Clone, Debug, Deserialize)]
     pub struct Telegram {
         #[serde(alias = "api-key", alias = "api")]
@@ -155,6 +206,20 @@ pub mod config {
         responsible: bool,
         #[serde(default, alias = "allowed-chat")]
         allowed_chat: Vec<i64>,
+        #[serde(default)]
+        locale: Locale,
+        #[serde(default)]
+        template: HashMap<String, String>,
+        #[serde(default)]
+        outbox: bool,
+        #[serde(default)]
+        roles: Roles,
+        #[serde(default)]
+        status: Status,
+        #[serde(alias = "watch-cooldown")]
+        watch_cooldown: Option<u64>,
+        #[serde(default)]
+        filter: Filter,
     }
 
     impl Telegram {
@@ -180,17 +245,298 @@ pub mod config {
         pub fn allowed_chat(&self) -> &[i64] {
             &self.allowed_chat
         }
+
+        pub fn locale(&self) -> Locale {
+            self.locale
+        }
+
+        pub fn template(&self) -> &HashMap<String, String> {
+            &self.template
+        }
+
+        /// Persist unsent messages in KV database
+        pub fn outbox(&self) -> bool {
+            self.outbox
+        }
+
+        pub fn roles(&self) -> &Roles {
+            &self.roles
+        }
+
+        pub fn status(&self) -> &Status {
+            &self.status
+        }
+
+        /// Seconds which watchers of same client are not notified again
+        pub fn watch_cooldown(&self) -> u64 {
+            self.watch_cooldown.unwrap_or(300)
+        }
+
+        pub fn filter(&self) -> &Filter {
+            &self.filter
+        }
+    }
+
+    /// Filter of events which are sent to telegram target
+    #[derive(Clone, Debug, Default, Deserialize)]
+    pub struct Filter {
+        #[serde(default)]
+        events: Vec<String>,
+        #[serde(default, alias = "include-groups")]
+        include_groups: Vec<i64>,
+        #[serde(default, alias = "exclude-groups")]
+        exclude_groups: Vec<i64>,
+        #[serde(default, alias = "include-uid")]
+        include_uid: Vec<String>,
+        #[serde(default, alias = "exclude-uid")]
+        exclude_uid: Vec<String>,
+        #[serde(default, alias = "min-session")]
+        min_session: u64,
+        #[serde(alias = "quiet-hours")]
+        quiet_hours: Option<(String, String)>,
+    }
+
+    impl Filter {
+        pub fn events(&self) -> &[String] {
+            &self.events
+        }
+
+        pub fn include_groups(&self) -> &[i64] {
+            &self.include_groups
+        }
+
+        pub fn exclude_groups(&self) -> &[i64] {
+            &self.exclude_groups
+        }
+
+        pub fn include_uid(&self) -> &[String] {
+            &self.include_uid
+        }
+
+        pub fn exclude_uid(&self) -> &[String] {
+            &self.exclude_uid
+        }
+
+        /// Seconds which client should stay online before its leave is announced
+        pub fn min_session(&self) -> u64 {
+            self.min_session
+        }
+
+        /// Start and end time of quiet hours in `HH:MM` format
+        pub fn quiet_hours(&self) -> Option<(&str, &str)> {
+            self.quiet_hours
+                .as_ref()
+                .map(|(start, end)| (start.as_str(), end.as_str()))
+        }
+    }
+
+    #[derive(Clone, Debug, Default, Deserialize)]
+    pub struct Status {
+        #[serde(default)]
+        enable: bool,
+        chat: Option<i64>,
+        debounce: Option<u64>,
+    }
+
+    impl Status {
+        pub fn enable(&self) -> bool {
+            self.enable
+        }
+
+        /// Chat which status message is pinned in, default is telegram target
+        pub fn chat(&self) -> Option<i64> {
+            self.chat
+        }
+
+        /// Seconds to wait for further changes before edit status message
+        pub fn debounce(&self) -> u64 {
+            self.debounce.unwrap_or(10).max(1)
+        }
+    }
+
+    /// Role of telegram user, higher role can run every command of lower role
+    #[derive(Clone, Copy, Debug, Deserialize, PartialEq, Eq, PartialOrd, Ord)]
+    #[serde(rename_all = "lowercase")]
+    pub enum Role {
+        Viewer,
+        Moderator,
+        Admin,
+    }
+
+    #[derive(Clone, Debug, Default, Deserialize)]
+    pub struct Roles {
+        #[serde(default)]
+        admin: Vec<u64>,
+        #[serde(default)]
+        moderator: Vec<u64>,
+        #[serde(default)]
+        viewer: Vec<u64>,
+        default: Option<Role>,
+    }
+
+    impl Roles {
+        /// Role of telegram user, unlisted user gets `default` role,
+        /// every user is viewer if roles are not configured
+        pub fn role(&self, user_id: u64) -> Option<Role> {
+            if self.admin.contains(&user_id) {
+                return Some(Role::Admin);
+            }
+            if self.moderator.contains(&user_id) {
+                return Some(Role::Moderator);
+            }
+            if self.viewer.contains(&user_id) {
+                return Some(Role::Viewer);
+            }
+            if self.default.is_none()
+                && self.admin.is_empty()
+                && self.moderator.is_empty()
+                && self.viewer.is_empty()
+            {
+                return Some(Role::Viewer);
+            }
+            self.default.filter(|role| *role == Role::Viewer)
+        }
+
+        pub fn validate(&self) -> anyhow::Result<()> {
+            if self.default.is_some_and(|role| role != Role::Viewer) {
+                return Err(anyhow!(
+                    "Default role of telegram user can only be viewer, list user ids to grant higher role"
+                ));
+            }
+            Ok(())
+        }
+    }
+
+    #[derive(Clone, Debug, Default, Deserialize)]
+    pub struct Bridge {
+        #[serde(default)]
+        enable: bool,
+        #[serde(default)]
+        chat: i64,
+        channel: Option<i64>,
+    }
+
+    impl Bridge {
+        pub fn enable(&self) -> bool {
+            self.enable && self.chat != 0
+        }
+
+        /// Telegram group which chat is relayed to
+        pub fn chat(&self) -> i64 {
+            self.chat
+        }
+
+        /// Relay chat of this channel instead of server chat, observer will stay in it
+        pub fn channel(&self) -> Option<i64> {
+            self.channel
+        }
+
+        /// Text message target mode of relayed chat
+        pub fn target_mode(&self) -> i8 {
+            if self.channel.is_some() { 2 } else { 3 }
+        }
+    }
+
+    #[derive(Clone, Debug, Deserialize)]
+    pub struct Discord {
+        webhook: String,
+        username: Option<String>,
+    }
+
+    impl Discord {
+        pub fn webhook(&self) -> &str {
+            &self.webhook
+        }
+
+        pub fn username(&self) -> Option<&str> {
+            self.username.as_deref()
+        }
+    }
+
+    #[derive(Clone, Debug, Deserialize)]
+    pub struct Matrix {
+        homeserver: String,
+        #[serde(alias = "access-token")]
+        access_token: String,
+        room: String,
+        #[serde(default)]
+        responsible: bool,
+        #[serde(default, alias = "allowed-room")]
+        allowed_room: Vec<String>,
+    }
+
+    impl Matrix {
+        pub fn homeserver(&self) -> &str {
+            &self.homeserver
+        }
+
+        pub fn access_token(&self) -> &str {
+            &self.access_token
+        }
+
+        pub fn room(&self) -> &str {
+            &self.room
+        }
+
+        pub fn responsible(&self) -> bool {
+            self.responsible
+        }
+
+        /// Rooms allowed to use bot command, default is notification room
+        pub fn allowed_room(&self) -> Vec<String> {
+            if self.allowed_room.is_empty() {
+                vec![self.room.clone()]
+            } else {
+                self.allowed_room.clone()
+            }
+        }
+    }
+
+    #[derive(Clone, Debug, Deserialize)]
+    pub struct Webhook {
+        url: String,
+        secret: Option<String>,
+        #[serde(default)]
+        events: Vec<String>,
+        retries: Option<u32>,
+    }
+
+    impl Webhook {
+        pub fn url(&self) -> &str {
+            &self.url
+        }
+
+        /// Key of HMAC-SHA256 signature header
+        pub fn secret(&self) -> Option<&str> {
+            self.secret.as_deref()
+        }
+
+        /// Event types which should be posted, empty means all
+        pub fn events(&self) -> &[String] {
+            &self.events
+        }
+
+        pub fn retries(&self) -> u32 {
+            self.retries.unwrap_or(3).min(10)
+        }
     }
 
     #[derive(Clone, Debug, Deserialize)]
     pub struct Misc {
         interval: Option<u64>,
+        #[serde(default, alias = "list-empty-channel")]
+        list_empty_channel: bool,
     }
 
     impl Misc {
         pub fn interval(&self) -> u64 {
             self.interval.unwrap_or(5)
         }
+
+        /// Show channels without clients in `/list` and status message
+        pub fn list_empty_channel(&self) -> bool {
+            self.list_empty_channel
+        }
     }
 
     #[derive(Clone, Debug, Default, Deserialize)]
@@ -222,16 +568,420 @@ pub mod config {
         }
     }
 
+    #[derive(Clone, Copy, Debug, Default, Deserialize, PartialEq, Eq)]
+    #[serde(rename_all = "kebab-case")]
+    pub enum PolicyAction {
+        #[default]
+        Poke,
+        Kick,
+        Ban,
+    }
+
+    #[derive(Clone, Debug, Default, Deserialize)]
+    pub struct NicknamePolicy {
+        enable: bool,
+        #[serde(default)]
+        deny: Vec<String>,
+        #[serde(alias = "max-length")]
+        max_length: Option<usize>,
+        #[serde(default)]
+        staff: Vec<String>,
+        #[serde(default, alias = "staff-uid")]
+        staff_uid: Vec<String>,
+        similarity: Option<f64>,
+        #[serde(default)]
+        action: PolicyAction,
+        reason: Option<String>,
+        #[serde(alias = "ban-duration")]
+        ban_duration: Option<u64>,
+    }
+
+    impl NicknamePolicy {
+        pub fn enable(&self) -> bool {
+            self.enable
+        }
+
+        pub fn deny(&self) -> &[String] {
+            &self.deny
+        }
+
+        pub fn max_length(&self) -> Option<usize> {
+            self.max_length
+        }
+
+        pub fn staff(&self) -> &[String] {
+            &self.staff
+        }
+
+        pub fn staff_uid(&self) -> &[String] {
+            &self.staff_uid
+        }
+
+        pub fn similarity(&self) -> f64 {
+            self.similarity.unwrap_or(0.85)
+        }
+
+        pub fn action(&self) -> PolicyAction {
+            self.action
+        }
+
+        pub fn reason(&self) -> String {
+            self.reason
+                .clone()
+                .unwrap_or_else(|| "Your nickname is not allowed on this server.".into())
+        }
+
+        pub fn ban_duration(&self) -> u64 {
+            self.ban_duration.unwrap_or(600)
+        }
+    }
+
+    #[derive(Clone, Copy, Debug, Default, Deserialize, PartialEq, Eq)]
+    #[serde(rename_all = "kebab-case")]
+    pub enum CountryAction {
+        Kick,
+        Move,
+        #[default]
+        Notify,
+    }
+
+    #[derive(Clone, Debug, Default, Deserialize)]
+    pub struct CountryPolicy {
+        enable: bool,
+        #[serde(default)]
+        allow: Vec<String>,
+        #[serde(default)]
+        deny: Vec<String>,
+        #[serde(default)]
+        action: CountryAction,
+        #[serde(alias = "quarantine-channel")]
+        quarantine_channel: Option<i64>,
+        #[serde(default, alias = "exempt-group")]
+        exempt_group: Vec<i64>,
+        #[serde(default, alias = "exempt-uid")]
+        exempt_uid: Vec<String>,
+        reason: Option<String>,
+    }
+
+    impl CountryPolicy {
+        pub fn enable(&self) -> bool {
+            self.enable
+        }
+
+        pub fn allow(&self) -> &[String] {
+            &self.allow
+        }
+
+        pub fn deny(&self) -> &[String] {
+            &self.deny
+        }
+
+        pub fn action(&self) -> CountryAction {
+            self.action
+        }
+
+        pub fn quarantine_channel(&self) -> Option<i64> {
+            self.quarantine_channel
+        }
+
+        pub fn exempt_group(&self) -> &[i64] {
+            &self.exempt_group
+        }
+
+        pub fn exempt_uid(&self) -> &[String] {
+            &self.exempt_uid
+        }
+
+        pub fn reason(&self) -> String {
+            self.reason
+                .clone()
+                .unwrap_or_else(|| "Connections from your country are not allowed.".into())
+        }
+
+        pub fn validate(&self) -> anyhow::Result<()> {
+            if self.enable
+                && self.action == CountryAction::Move
+                && self.quarantine_channel.is_none()
+            {
+                return Err(anyhow!(
+                    "Country policy action is move but quarantine channel is not specified"
+                ));
+            }
+            Ok(())
+        }
+    }
+
+    #[derive(Clone, Debug, Default, Deserialize)]
+    pub struct CloneDetection {
+        enable: bool,
+        #[serde(alias = "max-per-ip")]
+        max_per_ip: Option<usize>,
+        #[serde(alias = "max-per-uid")]
+        max_per_uid: Option<usize>,
+        #[serde(alias = "max-uids-per-window")]
+        max_uids_per_window: Option<usize>,
+        window: Option<u64>,
+        #[serde(default)]
+        kick: bool,
+        reason: Option<String>,
+        #[serde(default, alias = "exempt-ip")]
+        exempt_ip: Vec<String>,
+    }
+
+    impl CloneDetection {
+        pub fn enable(&self) -> bool {
+            self.enable
+        }
+
+        pub fn max_per_ip(&self) -> Option<usize> {
+            self.max_per_ip
+        }
+
+        pub fn max_per_uid(&self) -> Option<usize> {
+            self.max_per_uid
+        }
+
+        pub fn max_uids_per_window(&self) -> Option<usize> {
+            self.max_uids_per_window
+        }
+
+        pub fn window(&self) -> u64 {
+            self.window.unwrap_or(60)
+        }
+
+        pub fn kick(&self) -> bool {
+            self.kick
+        }
+
+        pub fn reason(&self) -> String {
+            self.reason
+                .clone()
+                .unwrap_or_else(|| "Too many connections.".into())
+        }
+
+        pub fn exempt_ip(&self) -> &[String] {
+            &self.exempt_ip
+        }
+    }
+
+    #[derive(Clone, Debug, Default, Deserialize)]
+    pub struct RaidProtection {
+        enable: bool,
+        threshold: Option<usize>,
+        window: Option<u64>,
+        #[serde(alias = "fresh-connections")]
+        fresh_connections: Option<i64>,
+        #[serde(alias = "lobby-channel")]
+        lobby_channel: Option<i64>,
+        password: Option<String>,
+        #[serde(alias = "join-power")]
+        join_power: Option<i64>,
+        duration: Option<u64>,
+        #[serde(default, alias = "exempt-group")]
+        exempt_group: Vec<i64>,
+    }
+
+    impl RaidProtection {
+        pub fn enable(&self) -> bool {
+            self.enable
+        }
+
+        pub fn threshold(&self) -> usize {
+            self.threshold.unwrap_or(10)
+        }
+
+        pub fn window(&self) -> u64 {
+            self.window.unwrap_or(60)
+        }
+
+        pub fn fresh_connections(&self) -> i64 {
+            self.fresh_connections.unwrap_or(1)
+        }
+
+        pub fn lobby_channel(&self) -> Option<i64> {
+            self.lobby_channel
+        }
+
+        pub fn password(&self) -> Option<&str> {
+            self.password.as_deref()
+        }
+
+        /// Needed join power of channels during lockdown
+        pub fn join_power(&self) -> Option<i64> {
+            self.join_power
+        }
+
+        pub fn duration(&self) -> Option<u64> {
+            self.duration
+        }
+
+        pub fn exempt_group(&self) -> &[i64] {
+            &self.exempt_group
+        }
+    }
+
+    #[derive(Clone, Copy, Debug, Default, Deserialize, PartialEq, Eq)]
+    #[serde(rename_all = "kebab-case")]
+    pub enum WelcomeMode {
+        #[default]
+        Message,
+        Poke,
+    }
+
+    #[derive(Clone, Debug, Deserialize)]
+    pub struct WelcomeGroup {
+        #[serde(alias = "group-id")]
+        group_id: i64,
+        first: Option<String>,
+        returning: Option<String>,
+    }
+
+    impl WelcomeGroup {
+        pub fn group_id(&self) -> i64 {
+            self.group_id
+        }
+
+        pub fn template(&self, first_join: bool) -> Option<&str> {
+            if first_join {
+                self.first.as_deref()
+            } else {
+                self.returning.as_deref()
+            }
+        }
+    }
+
+    #[derive(Clone, Debug, Default, Deserialize)]
+    pub struct Welcome {
+        enable: bool,
+        #[serde(default)]
+        mode: WelcomeMode,
+        first: Option<String>,
+        returning: Option<String>,
+        #[serde(default, alias = "group")]
+        groups: Vec<WelcomeGroup>,
+    }
+
+    impl Welcome {
+        pub fn enable(&self) -> bool {
+            self.enable
+        }
+
+        pub fn mode(&self) -> WelcomeMode {
+            self.mode
+        }
+
+        /// Select template by first server group which has variant, fallback to default one
+        pub fn template(&self, server_groups: &[i64], first_join: bool) -> Option<&str> {
+            self.groups
+                .iter()
+                .filter(|group| server_groups.contains(&group.group_id()))
+                .find_map(|group| group.template(first_join))
+                .or(if first_join {
+                    self.first.as_deref()
+                } else {
+                    self.returning.as_deref()
+                })
+                .filter(|template| !template.is_empty())
+        }
+    }
+
+    #[derive(Clone, Debug, Deserialize)]
+    pub struct RankTier {
+        hours: u64,
+        #[serde(alias = "group-id")]
+        group_id: i64,
+        name: String,
+    }
+
+    impl RankTier {
+        pub fn hours(&self) -> u64 {
+            self.hours
+        }
+
+        pub fn group_id(&self) -> i64 {
+            self.group_id
+        }
+
+        pub fn name(&self) -> &str {
+            &self.name
+        }
+    }
+
+    #[derive(Clone, Debug, Default, Deserialize)]
+    pub struct Rank {
+        enable: bool,
+        #[serde(default, alias = "tier")]
+        tiers: Vec<RankTier>,
+        message: Option<String>,
+        #[serde(alias = "check-interval")]
+        check_interval: Option<u64>,
+    }
+
+    impl Rank {
+        pub fn enable(&self) -> bool {
+            self.enable
+        }
+
+        pub fn message(&self) -> &str {
+            self.message.as_deref().unwrap_or(
+                "Congratulations {nickname}, you have reached rank {rank} with {hours} hours online!",
+            )
+        }
+
+        /// Seconds between online time accounting, default `60`
+        pub fn check_interval(&self) -> u64 {
+            self.check_interval.unwrap_or(60).max(10)
+        }
+
+        /// Highest tier reached by online seconds
+        pub fn tier(&self, seconds: u64) -> Option<&RankTier> {
+            self.tiers
+                .iter()
+                .filter(|tier| tier.hours * 3600 <= seconds)
+                .max_by_key(|tier| tier.hours)
+        }
+
+        /// Lowest tier which not reached yet
+        pub fn next_tier(&self, seconds: u64) -> Option<&RankTier> {
+            self.tiers
+                .iter()
+                .filter(|tier| tier.hours * 3600 > seconds)
+                .min_by_key(|tier| tier.hours)
+        }
+    }
+
     #[derive(Clone, Debug, Deserialize)]
     pub struct Config {
         server: Server,
         misc: Misc,
         #[serde(default, alias = "mute-porter")]
         mute_porter: MutePorter,
+        #[serde(default, alias = "nickname-policy")]
+        nickname_policy: NicknamePolicy,
+        #[serde(default, alias = "country-policy")]
+        country_policy: CountryPolicy,
+        #[serde(default, alias = "clone-detection")]
+        clone_detection: CloneDetection,
+        #[serde(default, alias = "raid-protection")]
+        raid_protection: RaidProtection,
+        #[serde(default)]
+        welcome: Welcome,
+        #[serde(default)]
+        rank: Rank,
+        #[cfg(feature = "tracker")]
+        #[serde(default)]
+        tracker: Tracker,
         #[serde(alias = "custom-message")]
         custom_message: Option<Message>,
         permissions: Option<Vec<Permission>>,
         telegram: Telegram,
+        #[serde(default)]
+        bridge: Bridge,
+        #[serde(default)]
+        discord: Vec<Discord>,
+        #[serde(default)]
+        webhook: Vec<Webhook>,
+        matrix: Option<Matrix>,
         #[serde(alias = "raw-query")]
         raw_query: RawQuery,
         #[serde(default)]
@@ -259,6 +1009,22 @@ pub mod config {
             &self.telegram
         }
 
+        pub fn bridge(&self) -> &Bridge {
+            &self.bridge
+        }
+
+        pub fn discord(&self) -> &[Discord] {
+            &self.discord
+        }
+
+        pub fn webhook(&self) -> &[Webhook] {
+            &self.webhook
+        }
+
+        pub fn matrix(&self) -> Option<&Matrix> {
+            self.matrix.as_ref()
+        }
+
         pub fn channel_permissions(&self) -> HashMap<i64, Vec<(u64, i64)>> {
             let mut m = Default::default();
             match &self.permissions {
@@ -301,6 +1067,35 @@ pub mod config {
             &self.mute_porter
         }
 
+        pub fn nickname_policy(&self) -> &NicknamePolicy {
+            &self.nickname_policy
+        }
+
+        pub fn country_policy(&self) -> &CountryPolicy {
+            &self.country_policy
+        }
+
+        pub fn clone_detection(&self) -> &CloneDetection {
+            &self.clone_detection
+        }
+
+        pub fn raid_protection(&self) -> &RaidProtection {
+            &self.raid_protection
+        }
+
+        pub fn welcome(&self) -> &Welcome {
+            &self.welcome
+        }
+
+        pub fn rank(&self) -> &Rank {
+            &self.rank
+        }
+
+        #[cfg(feature = "tracker")]
+        pub fn tracker(&self) -> &Tracker {
+            &self.tracker
+        }
+
         pub fn additional(&self) -> &[String] {
             &self.additional
         }
@@ -333,7 +1128,11 @@ pub mod config {
             let mut buf = String::new();
 
             file.read_to_string(&mut buf).await?;
-            toml::from_str(&buf).map_err(|e| anyhow!("Deserialize failure: {e:?}"))
+            let config: Self =
+                toml::from_str(&buf).map_err(|e| anyhow!("Deserialize failure: {e:?}"))?;
+            config.country_policy().validate()?;
+            config.telegram().roles().validate()?;
+            Ok(config)
         }
 
         pub async fn load_kv_map(&self) -> anyhow::Result<(Backend, Box<dyn ForkConnection>)> {
@@ -355,3 +1154,27 @@ pub mod config {
 }
 
 pub use config::Config;
+
+#[cfg(test)]
+mod test {
+    use super::config::{Role, Roles};
+
+    #[test]
+    fn test_roles() {
+        let roles = Roles::default();
+        assert_eq!(roles.role(1), Some(Role::Viewer));
+
+        let roles: Roles = toml::from_str("admin = [1]\nmoderator = [2]").unwrap();
+        assert_eq!(roles.role(1), Some(Role::Admin));
+        assert_eq!(roles.role(2), Some(Role::Moderator));
+        assert_eq!(roles.role(3), None);
+
+        let roles: Roles = toml::from_str("default = \"admin\"").unwrap();
+        assert!(roles.validate().is_err());
+        assert_eq!(roles.role(1), None);
+
+        let roles: Roles = toml::from_str("moderator = [2]\ndefault = \"viewer\"").unwrap();
+        assert!(roles.validate().is_ok());
+        assert_eq!(roles.role(3), Some(Role::Viewer));
+    }
+}
@@ -5,22 +5,22 @@ mod inner {
     use crate::auto_channel::{AutoChannelInstance, auto_channel_staff};
     use crate::configure::Config;
     use crate::configure::config::RawQuery;
+    use crate::locale::Templates;
+    use crate::matrix::MatrixNotifier;
+    use crate::notifier::{DiscordWebhook, JsonWebhook, Notifier, NotifierSet};
     use crate::observer::{PrivateMessageRequest, observer_thread};
-    use crate::plugins::KVMap;
     #[cfg(feature = "tracker")]
     use crate::plugins::tracker::DatabaseHelper;
     use crate::socketlib::SocketConn;
     use crate::telegram::BindTelegramHelper;
-    #[cfg(feature = "tracker")]
     use crate::types::EventHelperTrait;
     #[cfg(not(feature = "tracker"))]
     use crate::types::PseudoEventHelper;
-    use crate::types::{ArgPass2Controller, SafeUserState};
+    use crate::types::{ArgPass2Controller, ArgPass2Thread};
     use anyhow::anyhow;
     use log::{error, info, trace, warn};
     use std::sync::Arc;
     use std::time::Duration;
-    #[cfg(feature = "tracker")]
     use tap::TapOptional;
     use tokio::sync::{Notify, mpsc};
     use tuple_conv::RepeatedTuple;
@@ -82,23 +82,47 @@ mod inner {
         notifier: Arc<Notify>,
         thread_id: String,
         telegram_sender: BindTelegramHelper,
-        kv_map: Box<dyn KVMap>,
-        user_map: SafeUserState,
+        thread_args: ArgPass2Thread,
     ) -> ClientResult<()> {
         let (observer_connection, auto_channel_connection) = conn;
+        let mut notifier_list: Vec<Box<dyn Notifier>> = vec![Box::new(telegram_sender)];
+        for discord in config.discord() {
+            notifier_list.push(Box::new(DiscordWebhook::spawn(discord).map_err(|e| {
+                anyhow!("[{thread_id}] Unable create discord webhook client: {e:?}")
+            })?));
+        }
+        for webhook in config.webhook() {
+            notifier_list.push(Box::new(
+                JsonWebhook::spawn(config.get_id(), webhook)
+                    .map_err(|e| anyhow!("[{thread_id}] Unable create webhook client: {e:?}"))?,
+            ));
+        }
+        if let Some(matrix) = config.matrix() {
+            notifier_list.push(Box::new(
+                MatrixNotifier::spawn(matrix, Templates::new(config.telegram()))
+                    .map_err(|e| anyhow!("[{thread_id}] Unable create matrix client: {e:?}"))?,
+            ));
+        }
+        let notifiers = NotifierSet::new(notifier_list);
+        let ArgPass2Thread {
+            kv_map,
+            observer_kv_map,
+            user_map,
+            private_message: (private_message_sender, private_message_receiver),
+        } = thread_args;
 
-        let (private_message_sender, private_message_receiver) = mpsc::channel(128);
         let (trigger_sender, trigger_receiver) = mpsc::channel(128);
         //let (telegram_sender, telegram_receiver) = mpsc::channel(4096);
 
         //let thread_id = Rc::new(thread_id);
 
         #[cfg(feature = "tracker")]
-        let (user_tracker, tracker_controller) =
-            DatabaseHelper::safe_new(config.server().track_channel_member().clone(), |e| {
-                error!("Unable to create tracker {e:?}")
-            })
-            .await;
+        let (user_tracker, tracker_controller) = DatabaseHelper::safe_new(
+            config.server().track_channel_member().clone(),
+            config.tracker().clone(),
+            |e| error!("Unable to create tracker {e:?}"),
+        )
+        .await;
 
         #[cfg(not(feature = "tracker"))]
         let (user_tracker, tracker_controller) = PseudoEventHelper::new();
@@ -125,10 +149,10 @@ mod inner {
         let observer_handler = tokio::spawn(observer_thread(
             observer_connection,
             private_message_receiver,
-            telegram_sender,
+            notifiers,
             auto_channel_instance,
             config.clone(),
-            Box::new(tracker_controller.clone()),
+            (Box::new(tracker_controller.clone()), observer_kv_map),
             thread_id.clone(),
         ));
 
@@ -141,7 +165,6 @@ mod inner {
                     .await
                     .inspect_err(|_| error!("Send terminate error"))
                     .ok();
-                #[cfg(feature = "tracker")]
                 tracker_controller
                     .terminate()
                     .await
@@ -184,8 +207,7 @@ mod inner {
         config: Config,
         thread_id: String,
         args: ArgPass2Controller,
-        kv_map: Box<dyn KVMap>,
-        user_map: SafeUserState,
+        thread_args: ArgPass2Thread,
     ) -> ClientResult<()> {
         // Await all client ready
         args.barrier.wait().await;
@@ -196,8 +218,7 @@ mod inner {
             args.notify,
             thread_id,
             args.helper.into_bind(config_id),
-            kv_map,
-            user_map,
+            thread_args,
         )
         .await
     }
@@ -273,15 +294,17 @@ mod types {
 mod controller {
     use super::inner::bootstrap;
     use crate::configure::Config;
+    use crate::matrix::matrix_bootstrap;
     use crate::plugins::Backend;
+    use crate::responder::user_state_map;
     use crate::telegram::telegram_bootstrap;
-    use crate::types::ArgPass2Controller;
+    use crate::types::{ArgPass2Controller, ArgPass2Thread, ConfigMappedSender};
     use log::error;
     use std::fmt::Debug;
     use std::future::Future;
     use std::pin::Pin;
     use std::sync::Arc;
-    use tokio::sync::{Barrier, Notify};
+    use tokio::sync::{Barrier, Notify, mpsc};
     use tokio::task::JoinHandle;
 
     #[derive(Debug)]
@@ -308,7 +331,11 @@ mod controller {
             path: String,
             notify: Arc<Notify>,
             exit_notify: Arc<Notify>,
-        ) -> anyhow::Result<(Backend, Vec<Controller>, JoinHandle<anyhow::Result<()>>)> {
+        ) -> anyhow::Result<(
+            Backend,
+            Vec<Controller>,
+            Vec<JoinHandle<anyhow::Result<()>>>,
+        )> {
             let configures = Config::load_config(path).await?;
             let (kv_backend, connection) = configures.first().unwrap().1.load_kv_map().await?;
 
@@ -316,19 +343,60 @@ mod controller {
 
             let mut v = Vec::new();
 
-            let (handler, telegram_helper, user_state_map) =
-                telegram_bootstrap(&configures, notify.clone())?;
+            let mut private_message_map = ConfigMappedSender::new();
+            let mut private_message_receivers = Vec::new();
+            for (_, config) in &configures {
+                let (sender, receiver) = mpsc::channel(128);
+                private_message_map.insert(config.get_id(), sender.clone());
+                private_message_receivers.push((sender, receiver));
+            }
+
+            let outbox = if configures
+                .iter()
+                .any(|(_, config)| config.telegram().outbox())
+            {
+                Some(connection.fork().await?)
+            } else {
+                None
+            };
+            let status = if configures
+                .iter()
+                .any(|(_, config)| config.telegram().status().enable())
+            {
+                Some(connection.fork().await?)
+            } else {
+                None
+            };
+            let user_state_map = user_state_map(&configures);
+            let (handler, telegram_helper) = telegram_bootstrap(
+                &configures,
+                notify.clone(),
+                private_message_map,
+                user_state_map.clone(),
+                outbox,
+                status,
+            )?;
+            let matrix_handler =
+                matrix_bootstrap(&configures, notify.clone(), user_state_map.clone())?;
 
             let controller_arg =
                 ArgPass2Controller::new(notify.clone(), barrier.clone(), telegram_helper.clone());
 
-            for (thread_id, config) in configures {
+            for ((thread_id, config), private_message) in
+                configures.into_iter().zip(private_message_receivers)
+            {
                 let kv_map = connection.fork().await?;
+                let observer_kv_map = connection.fork().await?;
                 let exit_notify = exit_notify.clone();
                 let arg = controller_arg.clone();
-                let map = user_state_map.get(&config.get_id()).unwrap().clone();
+                let thread_args = ArgPass2Thread {
+                    kv_map,
+                    observer_kv_map,
+                    user_map: user_state_map.get(&config.get_id()).unwrap().clone(),
+                    private_message,
+                };
                 v.push(Controller::new(Box::pin(async move {
-                    let result = bootstrap(config, thread_id.clone(), arg, kv_map, map).await;
+                    let result = bootstrap(config, thread_id.clone(), arg, thread_args).await;
                     exit_notify.notify_waiters();
                     if let Err(e) = result {
                         error!("In {thread_id}: {e:?}");
@@ -338,7 +406,7 @@ mod controller {
                 })));
             }
 
-            Ok((kv_backend, v, handler))
+            Ok((kv_backend, v, vec![handler, matrix_handler]))
         }
     }
 }
@@ -0,0 +1,278 @@
+use crate::configure::config::{Locale, Telegram};
+use crate::telegram::escape_html;
+use crate::template;
+use log::warn;
+use std::collections::HashMap;
+
+/// Kind of telegram message which can be customized by template
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Kind {
+    Enter,
+    Leave,
+    LeaveReason,
+    Timeout,
+    Kick,
+    Ban,
+    NoReason,
+    Country,
+    CountryKick,
+    CountryMove,
+    CountryNotify,
+    Clone,
+    CloneKick,
+    Raid,
+    LockdownEnd,
+    Chat,
+    Moderation,
+}
+
+impl Kind {
+    const ALL: [Kind; 17] = [
+        Kind::Enter,
+        Kind::Leave,
+        Kind::LeaveReason,
+        Kind::Timeout,
+        Kind::Kick,
+        Kind::Ban,
+        Kind::NoReason,
+        Kind::Country,
+        Kind::CountryKick,
+        Kind::CountryMove,
+        Kind::CountryNotify,
+        Kind::Clone,
+        Kind::CloneKick,
+        Kind::Raid,
+        Kind::LockdownEnd,
+        Kind::Chat,
+        Kind::Moderation,
+    ];
+
+    /// Key in `[telegram.template]` section
+    pub fn as_str(self) -> &'static str {
+        match self {
+            Kind::Enter => "enter",
+            Kind::Leave => "leave",
+            Kind::LeaveReason => "leave-reason",
+            Kind::Timeout => "timeout",
+            Kind::Kick => "kick",
+            Kind::Ban => "ban",
+            Kind::NoReason => "no-reason",
+            Kind::Country => "country",
+            Kind::CountryKick => "country-kick",
+            Kind::CountryMove => "country-move",
+            Kind::CountryNotify => "country-notify",
+            Kind::Clone => "clone",
+            Kind::CloneKick => "clone-kick",
+            Kind::Raid => "raid",
+            Kind::LockdownEnd => "lockdown-end",
+            Kind::Chat => "chat",
+            Kind::Moderation => "moderation",
+        }
+    }
+}
+
+fn english(kind: Kind) -> &'static str {
+    match kind {
+        Kind::Enter => "[{time}] <b>{nickname}</b>(<code>{uid}</code>:{client_id})[{flag}] joined",
+        Kind::Leave => "[{time}] <b>{nickname}</b>({client_id}) left",
+        Kind::LeaveReason => "[{time}] <b>{nickname}</b>({client_id}) left ({reason})",
+        Kind::Timeout => "[{time}] <b>{nickname}</b>({client_id}) connection lost #timeout",
+        Kind::Kick => {
+            "[{time}] <b>{nickname}</b>({client_id}) was #kicked by <b>{invoker}</b>(<code>{invoker_uid}</code>): {reason}"
+        }
+        Kind::Ban => {
+            "[{time}] <b>{nickname}</b>({client_id}) was #banned by <b>{invoker}</b>(<code>{invoker_uid}</code>): {reason}"
+        }
+        Kind::NoReason => "no reason",
+        Kind::Country => {
+            "[{time}] <b>{nickname}</b>(<code>{uid}</code>:{client_id})[{flag}] #country_policy {action}"
+        }
+        Kind::CountryKick => "kicked",
+        Kind::CountryMove => "moved to quarantine",
+        Kind::CountryNotify => "detected",
+        Kind::Clone => {
+            "[{time}] <b>{nickname}</b>(<code>{uid}</code>:{client_id}) from <code>{ip}</code> #clone: {alert}"
+        }
+        Kind::CloneKick => {
+            "[{time}] <b>{nickname}</b>(<code>{uid}</code>:{client_id}) from <code>{ip}</code> #clone: {alert}, kicked"
+        }
+        Kind::Raid => {
+            "[{time}] #raid detected: {count} new users joined in short time, server is in lockdown"
+        }
+        Kind::LockdownEnd => "[{time}] #raid lockdown ended",
+        Kind::Chat => "<b>{nickname}</b>: {message}",
+        Kind::Moderation => "[{time}] #moderation <b>{invoker}</b> via telegram: {action}",
+    }
+}
+
+fn chinese(kind: Kind) -> &'static str {
+    match kind {
+        Kind::Enter => {
+            "[{time}] <b>{nickname}</b>(<code>{uid}</code>:{client_id})[{flag}] 加入了服务器"
+        }
+        Kind::Leave => "[{time}] <b>{nickname}</b>({client_id}) 离开了服务器",
+        Kind::LeaveReason => "[{time}] <b>{nickname}</b>({client_id}) 离开了服务器 ({reason})",
+        Kind::Timeout => "[{time}] <b>{nickname}</b>({client_id}) 连接超时 #timeout",
+        Kind::Kick => {
+            "[{time}] <b>{nickname}</b>({client_id}) 被 <b>{invoker}</b>(<code>{invoker_uid}</code>) 踢出 #kicked: {reason}"
+        }
+        Kind::Ban => {
+            "[{time}] <b>{nickname}</b>({client_id}) 被 <b>{invoker}</b>(<code>{invoker_uid}</code>) 封禁 #banned: {reason}"
+        }
+        Kind::NoReason => "无理由",
+        Kind::Country => {
+            "[{time}] <b>{nickname}</b>(<code>{uid}</code>:{client_id})[{flag}] #country_policy {action}"
+        }
+        Kind::CountryKick => "已踢出",
+        Kind::CountryMove => "已移至隔离频道",
+        Kind::CountryNotify => "已检测到",
+        Kind::Clone => {
+            "[{time}] <b>{nickname}</b>(<code>{uid}</code>:{client_id}) 来自 <code>{ip}</code> #clone: {alert}"
+        }
+        Kind::CloneKick => {
+            "[{time}] <b>{nickname}</b>(<code>{uid}</code>:{client_id}) 来自 <code>{ip}</code> #clone: {alert}，已踢出"
+        }
+        Kind::Raid => "[{time}] 检测到 #raid：短时间内有 {count} 名新用户加入，服务器已锁定",
+        Kind::LockdownEnd => "[{time}] #raid 锁定已解除",
+        Kind::Chat => "<b>{nickname}</b>: {message}",
+        Kind::Moderation => "[{time}] #moderation <b>{invoker}</b> 通过 telegram 执行: {action}",
+    }
+}
+
+fn japanese(kind: Kind) -> &'static str {
+    match kind {
+        Kind::Enter => {
+            "[{time}] <b>{nickname}</b>(<code>{uid}</code>:{client_id})[{flag}] が参加しました"
+        }
+        Kind::Leave => "[{time}] <b>{nickname}</b>({client_id}) が退出しました",
+        Kind::LeaveReason => "[{time}] <b>{nickname}</b>({client_id}) が退出しました ({reason})",
+        Kind::Timeout => "[{time}] <b>{nickname}</b>({client_id}) の接続が切れました #timeout",
+        Kind::Kick => {
+            "[{time}] <b>{nickname}</b>({client_id}) は <b>{invoker}</b>(<code>{invoker_uid}</code>) にキックされました #kicked: {reason}"
+        }
+        Kind::Ban => {
+            "[{time}] <b>{nickname}</b>({client_id}) は <b>{invoker}</b>(<code>{invoker_uid}</code>) にBANされました #banned: {reason}"
+        }
+        Kind::NoReason => "理由なし",
+        Kind::Country => {
+            "[{time}] <b>{nickname}</b>(<code>{uid}</code>:{client_id})[{flag}] #country_policy {action}"
+        }
+        Kind::CountryKick => "キックしました",
+        Kind::CountryMove => "隔離チャンネルへ移動しました",
+        Kind::CountryNotify => "検出しました",
+        Kind::Clone => {
+            "[{time}] <b>{nickname}</b>(<code>{uid}</code>:{client_id}) <code>{ip}</code> から #clone: {alert}"
+        }
+        Kind::CloneKick => {
+            "[{time}] <b>{nickname}</b>(<code>{uid}</code>:{client_id}) <code>{ip}</code> から #clone: {alert}、キックしました"
+        }
+        Kind::Raid => {
+            "[{time}] #raid を検出: 短時間に {count} 人の新規ユーザーが参加したため、サーバーをロックダウンしました"
+        }
+        Kind::LockdownEnd => "[{time}] #raid ロックダウンが解除されました",
+        Kind::Chat => "<b>{nickname}</b>: {message}",
+        Kind::Moderation => "[{time}] #moderation <b>{invoker}</b> が telegram から実行: {action}",
+    }
+}
+
+fn builtin(locale: Locale, kind: Kind) -> &'static str {
+    match locale {
+        Locale::En => english(kind),
+        Locale::Zh => chinese(kind),
+        Locale::Ja => japanese(kind),
+    }
+}
+
+/// Telegram message templates of configure, custom template overrides built-in locale pack
+#[derive(Clone, Debug, Default)]
+pub struct Templates {
+    locale: Locale,
+    custom: HashMap<String, String>,
+}
+
+impl Templates {
+    pub fn new(telegram: &Telegram) -> Self {
+        for key in telegram.template().keys() {
+            if !Kind::ALL.iter().any(|kind| kind.as_str().eq(key)) {
+                warn!("Unknown telegram template {key:?}, ignored");
+            }
+        }
+        Self {
+            locale: telegram.locale(),
+            custom: telegram.template().clone(),
+        }
+    }
+
+    pub fn get(&self, kind: Kind) -> &str {
+        self.custom
+            .get(kind.as_str())
+            .map(String::as_str)
+            .unwrap_or_else(|| builtin(self.locale, kind))
+    }
+
+    /// Render template of `kind`, values will be HTML escaped
+    pub fn render(&self, kind: Kind, values: &[(&str, &str)]) -> String {
+        let escaped = values
+            .iter()
+            .map(|(key, value)| (*key, escape_html(value)))
+            .collect::<Vec<_>>();
+        template::render(
+            self.get(kind),
+            &escaped
+                .iter()
+                .map(|(key, value)| (*key, value.as_str()))
+                .collect::<Vec<_>>(),
+        )
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::{Kind, Templates, builtin};
+    use crate::configure::config::{Locale, Telegram};
+
+    fn placeholders(template: &str) -> Vec<&str> {
+        let mut v = template
+            .split('{')
+            .skip(1)
+            .filter_map(|s| s.split_once('}').map(|(key, _)| key))
+            .collect::<Vec<_>>();
+        v.sort();
+        v
+    }
+
+    #[test]
+    fn test_locale() {
+        for kind in Kind::ALL {
+            let english = placeholders(builtin(Locale::En, kind));
+            for locale in [Locale::Zh, Locale::Ja] {
+                assert_eq!(
+                    placeholders(builtin(locale, kind)),
+                    english,
+                    "{locale:?} {}",
+                    kind.as_str()
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn test_template() {
+        let telegram: Telegram = toml::from_str(
+            r#"
+api-key = ""
+target = 0
+locale = "ja"
+[template]
+leave = "{nickname} bye"
+"#,
+        )
+        .unwrap();
+        let templates = Templates::new(&telegram);
+        assert_eq!(
+            templates.render(Kind::Leave, &[("nickname", "<b>Alice & Bob</b>")]),
+            "&lt;b&gt;Alice &amp; Bob&lt;/b&gt; bye"
+        );
+        assert_eq!(templates.get(Kind::NoReason), "理由なし");
+    }
+}
@@ -1,11 +1,23 @@
 mod auto_channel;
 mod configure;
 mod hypervisor;
+mod locale;
+mod matrix;
+mod moderation;
+mod notifier;
 mod observer;
 mod plugins;
+mod policy;
+mod rank;
+#[cfg(feature = "tracker")]
+mod report;
+mod responder;
 mod socketlib;
 mod telegram;
+mod template;
 mod types;
+mod watch;
+mod welcome;
 
 use crate::hypervisor::{Controller, SYSTEMD_MODE};
 use clap::{arg, command};
@@ -27,7 +39,7 @@ async fn start_services(config: String, systemd_mode: bool) -> anyhow::Result<()
 
     SYSTEMD_MODE.set(systemd_mode).unwrap();
 
-    let (kv_backend, controllers, telegram_handler) =
+    let (kv_backend, controllers, service_handlers) =
         Controller::bootstrap_controller(config, notify.clone(), exit_notify.clone()).await?;
 
     tokio::select! {
@@ -73,7 +85,9 @@ async fn start_services(config: String, systemd_mode: bool) -> anyhow::Result<()
 
     kv_backend.disconnect().await?;
 
-    telegram_handler.await??;
+    for handler in service_handlers {
+        handler.await??;
+    }
     Ok(())
 }
 
@@ -99,20 +113,29 @@ fn build_logger(count: u8, systemd_mode: bool) {
 }
 
 fn main() -> anyhow::Result<()> {
-    let matches = command!()
-        .args(&[
-            arg!([CONFIG_FILE] "Override default configure file location")
-                .default_value("config.toml"),
-            arg!(--systemd "Start in systemd mode, which enable wait if connect failed"),
-            arg!(--"observer-name" <OBSERVER_NAME> "Override observer nickname"),
-            arg!(--"autochannel-name" <AUTO_CHANNEL_NAME> "Override auto channel nickname"),
-            arg!(-d --debug ... "Enable debug mode (can specify more times)"),
-        ])
-        .get_matches();
+    let command = command!().args(&[
+        arg!([CONFIG_FILE] "Override default configure file location").default_value("config.toml"),
+        arg!(--systemd "Start in systemd mode, which enable wait if connect failed"),
+        arg!(--"observer-name" <OBSERVER_NAME> "Override observer nickname"),
+        arg!(--"autochannel-name" <AUTO_CHANNEL_NAME> "Override auto channel nickname"),
+        arg!(-d --debug ... "Enable debug mode (can specify more times)"),
+    ]);
+    #[cfg(feature = "tracker")]
+    let command = command.subcommand(report::command());
+    let matches = command.get_matches();
 
     let systemd_mode = matches.get_flag("systemd");
     build_logger(matches.get_count("debug"), systemd_mode);
 
+    #[cfg(feature = "tracker")]
+    if let Some(matches) = matches.subcommand_matches("report") {
+        return tokio::runtime::Builder::new_current_thread()
+            .enable_all()
+            .build()
+            .unwrap()
+            .block_on(report::run(matches));
+    }
+
     if let Some(nickname) = matches.get_one::<String>("observer-name") {
         OBSERVER_NICKNAME_OVERRIDE
             .set(nickname.to_string())
@@ -0,0 +1,392 @@
+mod client {
+    use anyhow::anyhow;
+    use reqwest::Url;
+    use serde::Deserialize;
+    use serde_json::{Value, json};
+    use std::collections::HashMap;
+    use std::sync::atomic::{AtomicU64, Ordering};
+    use std::time::Duration;
+
+    use crate::telegram::strip_html;
+
+    /// Only message events are needed by bot
+    const SYNC_FILTER: &str = r#"{"presence":{"types":[]},"account_data":{"types":[]},"room":{"state":{"types":[]},"ephemeral":{"types":[]},"account_data":{"types":[]},"timeline":{"types":["m.room.message"]}}}"#;
+
+    #[derive(Deserialize)]
+    struct WhoAmI {
+        user_id: String,
+    }
+
+    #[derive(Deserialize)]
+    pub(super) struct SyncResponse {
+        pub(super) next_batch: String,
+        #[serde(default)]
+        pub(super) rooms: Rooms,
+    }
+
+    #[derive(Default, Deserialize)]
+    pub(super) struct Rooms {
+        #[serde(default)]
+        pub(super) join: HashMap<String, JoinedRoom>,
+    }
+
+    #[derive(Deserialize)]
+    pub(super) struct JoinedRoom {
+        #[serde(default)]
+        pub(super) timeline: Timeline,
+    }
+
+    #[derive(Default, Deserialize)]
+    pub(super) struct Timeline {
+        #[serde(default)]
+        pub(super) events: Vec<RoomEvent>,
+    }
+
+    #[derive(Deserialize)]
+    pub(super) struct RoomEvent {
+        #[serde(rename = "type")]
+        pub(super) kind: String,
+        pub(super) sender: String,
+        #[serde(default)]
+        pub(super) content: Value,
+    }
+
+    impl RoomEvent {
+        /// Body of text message
+        pub(super) fn text(&self) -> Option<&str> {
+            if self.kind != "m.room.message" || self.content["msgtype"] != "m.text" {
+                return None;
+            }
+            self.content["body"].as_str()
+        }
+    }
+
+    /// Minimal matrix client-server API client
+    #[derive(Debug)]
+    pub(super) struct MatrixClient {
+        client: reqwest::Client,
+        homeserver: Url,
+        access_token: String,
+        transaction: AtomicU64,
+    }
+
+    impl MatrixClient {
+        pub(super) fn new(homeserver: &str, access_token: &str) -> anyhow::Result<Self> {
+            Ok(Self {
+                client: reqwest::Client::builder()
+                    .timeout(Duration::from_secs(60))
+                    .build()?,
+                homeserver: homeserver.parse()?,
+                access_token: access_token.to_string(),
+                transaction: AtomicU64::new(0),
+            })
+        }
+
+        fn endpoint(&self, segments: &[&str]) -> anyhow::Result<Url> {
+            let mut url = self.homeserver.clone();
+            url.path_segments_mut()
+                .map_err(|_| anyhow!("Invalid homeserver URL {}", self.homeserver))?
+                .pop_if_empty()
+                .extend(["_matrix", "client", "v3"])
+                .extend(segments);
+            Ok(url)
+        }
+
+        /// Send HTML message as notice, plain text body is generated from HTML
+        pub(super) async fn send(&self, room_id: &str, html: &str) -> anyhow::Result<()> {
+            let transaction = format!(
+                "tmt{}.{}",
+                kstool::time::get_current_second(),
+                self.transaction.fetch_add(1, Ordering::Relaxed)
+            );
+            self.client
+                .put(self.endpoint(&["rooms", room_id, "send", "m.room.message", &transaction])?)
+                .bearer_auth(&self.access_token)
+                .json(&json!({
+                    "msgtype": "m.notice",
+                    "body": strip_html(html),
+                    "format": "org.matrix.custom.html",
+                    "formatted_body": html.replace('\n', "<br/>"),
+                }))
+                .send()
+                .await?
+                .error_for_status()?;
+            Ok(())
+        }
+
+        pub(super) async fn whoami(&self) -> anyhow::Result<String> {
+            Ok(self
+                .client
+                .get(self.endpoint(&["account", "whoami"])?)
+                .bearer_auth(&self.access_token)
+                .send()
+                .await?
+                .error_for_status()?
+                .json::<WhoAmI>()
+                .await?
+                .user_id)
+        }
+
+        /// Long polling sync, `timeout` in milliseconds
+        pub(super) async fn sync(
+            &self,
+            since: Option<&str>,
+            timeout: u64,
+        ) -> anyhow::Result<SyncResponse> {
+            let mut url = self.endpoint(&["sync"])?;
+            url.query_pairs_mut()
+                .append_pair("filter", SYNC_FILTER)
+                .append_pair("timeout", &timeout.to_string());
+            if let Some(since) = since {
+                url.query_pairs_mut().append_pair("since", since);
+            }
+            Ok(self
+                .client
+                .get(url)
+                .bearer_auth(&self.access_token)
+                .send()
+                .await?
+                .error_for_status()?
+                .json()
+                .await?)
+        }
+    }
+}
+
+mod notifier {
+    use super::client::MatrixClient;
+    use crate::configure::config::Matrix;
+    use crate::locale::Templates;
+    use crate::notifier::{Event, Notifier};
+    use async_trait::async_trait;
+    use log::error;
+    use tokio::sync::mpsc;
+
+    /// Send events to matrix room, message is rendered by telegram templates
+    #[derive(Clone, Debug)]
+    pub struct MatrixNotifier {
+        sender: mpsc::Sender<Event>,
+    }
+
+    impl MatrixNotifier {
+        pub fn spawn(config: &Matrix, templates: Templates) -> anyhow::Result<Self> {
+            let client = MatrixClient::new(config.homeserver(), config.access_token())?;
+            let (sender, receiver) = mpsc::channel(64);
+            tokio::spawn(Self::run(
+                client,
+                config.room().to_string(),
+                templates,
+                receiver,
+            ));
+            Ok(Self { sender })
+        }
+
+        async fn run(
+            client: MatrixClient,
+            room_id: String,
+            templates: Templates,
+            mut receiver: mpsc::Receiver<Event>,
+        ) {
+            while let Some(event) = receiver.recv().await {
+                client
+                    .send(&room_id, &event.render(&templates))
+                    .await
+                    .inspect_err(|e| error!("Unable send event to matrix room {room_id}: {e:?}"))
+                    .ok();
+            }
+        }
+    }
+
+    #[async_trait]
+    impl Notifier for MatrixNotifier {
+        async fn notify(&self, event: &Event) -> Option<()> {
+            crate::notifier::enqueue(&self.sender, "Matrix", event)
+        }
+    }
+}
+
+mod bot {
+    use super::client::{MatrixClient, SyncResponse};
+    use crate::configure::Config;
+    use crate::responder::{self, Command};
+    use crate::types::ConfigMappedUserState;
+    use futures_util::future::join_all;
+    use log::{error, info, warn};
+    use std::collections::HashMap;
+    use std::sync::Arc;
+    use std::time::Duration;
+    use tokio::sync::Notify;
+
+    const SYNC_TIMEOUT: u64 = 30000;
+
+    /// Room id with configure ids relationship
+    type RoomMap = HashMap<String, Vec<String>>;
+
+    /// Build responsible bots, configures with same access token share one bot
+    fn build_bots(configs: &[(String, Config)]) -> anyhow::Result<Vec<(MatrixClient, RoomMap)>> {
+        let mut bots: HashMap<(String, String), RoomMap> = HashMap::new();
+        for (_, config) in configs {
+            let Some(matrix) = config.matrix().filter(|matrix| matrix.responsible()) else {
+                continue;
+            };
+            let rooms = bots
+                .entry((
+                    matrix.homeserver().to_string(),
+                    matrix.access_token().to_string(),
+                ))
+                .or_default();
+            for room in matrix.allowed_room() {
+                rooms.entry(room).or_default().push(config.get_id());
+            }
+        }
+        bots.into_iter()
+            .map(|((homeserver, access_token), rooms)| {
+                Ok((MatrixClient::new(&homeserver, &access_token)?, rooms))
+            })
+            .collect()
+    }
+
+    async fn handle(
+        client: &MatrixClient,
+        response: &SyncResponse,
+        user_id: &str,
+        room_map: &RoomMap,
+        channel_map: &ConfigMappedUserState,
+    ) {
+        for (room_id, room) in &response.rooms.join {
+            for event in &room.timeline.events {
+                if event.sender == user_id {
+                    continue;
+                }
+                let Some(command) = event.text().and_then(Command::parse) else {
+                    continue;
+                };
+                let message = match command {
+                    Command::Ping => responder::ping(room_id),
+                    Command::List => {
+                        let Some(configs) = room_map.get(room_id) else {
+                            warn!("Deny unauthorized access room {room_id}");
+                            continue;
+                        };
+                        responder::list(configs, channel_map).await
+                    }
+                };
+                client
+                    .send(room_id, &message)
+                    .await
+                    .inspect_err(|e| error!("Unable reply matrix room {room_id}: {e:?}"))
+                    .ok();
+            }
+        }
+    }
+
+    /// Sync once and return next batch token, messages before start up should not be replied
+    async fn sync_once(
+        client: &MatrixClient,
+        user_id: &str,
+        since: Option<&str>,
+        (room_map, channel_map): (&RoomMap, &ConfigMappedUserState),
+    ) -> anyhow::Result<String> {
+        let response = client
+            .sync(since, if since.is_some() { SYNC_TIMEOUT } else { 0 })
+            .await?;
+        if since.is_some() {
+            handle(client, &response, user_id, room_map, channel_map).await;
+        }
+        Ok(response.next_batch)
+    }
+
+    async fn run(client: MatrixClient, room_map: RoomMap, channel_map: ConfigMappedUserState) {
+        let user_id = loop {
+            match client.whoami().await {
+                Ok(user_id) => break user_id,
+                Err(e) => error!("Unable get matrix bot user id: {e:?}"),
+            }
+            tokio::time::sleep(Duration::from_secs(5)).await;
+        };
+        info!("Matrix bot {user_id} started");
+        let mut since = None;
+        loop {
+            match sync_once(
+                &client,
+                &user_id,
+                since.as_deref(),
+                (&room_map, &channel_map),
+            )
+            .await
+            {
+                Ok(next_batch) => since = Some(next_batch),
+                Err(e) => {
+                    error!("Matrix sync error: {e:?}");
+                    tokio::time::sleep(Duration::from_secs(5)).await;
+                }
+            }
+        }
+    }
+
+    pub fn matrix_bootstrap(
+        configs: &[(String, Config)],
+        notifier: Arc<Notify>,
+        channel_map: ConfigMappedUserState,
+    ) -> anyhow::Result<tokio::task::JoinHandle<anyhow::Result<()>>> {
+        let bots = build_bots(configs)?;
+        Ok(tokio::spawn(async move {
+            if bots.is_empty() {
+                return Ok(());
+            }
+            tokio::select! {
+                _ = join_all(
+                    bots.into_iter()
+                        .map(|(client, room_map)| run(client, room_map, channel_map.clone())),
+                ) => {}
+                _ = notifier.notified() => {}
+            }
+            Ok(())
+        }))
+    }
+}
+
+pub use bot::matrix_bootstrap;
+pub use notifier::MatrixNotifier;
+
+#[cfg(test)]
+mod test {
+    use super::client::MatrixClient;
+    use crate::notifier::test::stub;
+    use serde_json::{Value, json};
+    use tokio::net::TcpListener;
+
+    #[tokio::test]
+    async fn test_matrix_send() {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let client = MatrixClient::new(
+            &format!("http://{}/", listener.local_addr().unwrap()),
+            "token",
+        )
+        .unwrap();
+        let server = tokio::spawn(stub(listener));
+        client
+            .send("!room:example.org", "<b>Alice &amp; Bob</b> joined\nhello")
+            .await
+            .unwrap();
+        let (head, body) = server.await.unwrap();
+        assert!(
+            head.starts_with(
+                "PUT /_matrix/client/v3/rooms/!room:example.org/send/m.room.message/tmt"
+            )
+        );
+        assert!(
+            head.lines()
+                .any(|line| line.eq_ignore_ascii_case("authorization: Bearer token"))
+        );
+        assert_eq!(
+            serde_json::from_str::<Value>(&body).unwrap(),
+            json!({
+                "msgtype": "m.notice",
+                "body": "Alice & Bob joined\nhello",
+                "format": "org.matrix.custom.html",
+                "formatted_body": "<b>Alice &amp; Bob</b> joined<br/>hello",
+            })
+        );
+    }
+}
@@ -0,0 +1,234 @@
+use crate::configure::config::Role;
+use std::fmt::{Display, Formatter};
+
+/// Default reason of kick and ban which requested from chat bot
+const DEFAULT_REASON: &str = "Requested by moderator";
+
+/// Client which moderation action applies to
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum Target {
+    ClientId(i64),
+    Uid(String),
+    Nickname(String),
+}
+
+impl Target {
+    /// Parse target, digits are treated as client id and 28 characters end with `=` as UID
+    fn parse(s: &str) -> Self {
+        if let Ok(client_id) = s.parse() {
+            return Self::ClientId(client_id);
+        }
+        if s.len() == 28 && s.ends_with('=') {
+            return Self::Uid(s.to_string());
+        }
+        Self::Nickname(s.to_string())
+    }
+
+    /// Split target from arguments, nickname contains whitespace should be quoted
+    fn split(args: &str) -> Option<(Self, &str)> {
+        let args = args.trim();
+        if let Some(quoted) = args.strip_prefix('"') {
+            let (nickname, rest) = quoted.split_once('"')?;
+            return Some((Self::Nickname(nickname.to_string()), rest.trim()));
+        }
+        let (target, rest) = args.split_once(char::is_whitespace).unwrap_or((args, ""));
+        (!target.is_empty()).then(|| (Self::parse(target), rest.trim()))
+    }
+
+    /// Parse target from arguments, rest of arguments are ignored
+    pub fn from_args(args: &str) -> Option<Self> {
+        Self::split(args).map(|(target, _)| target)
+    }
+
+    /// Find client id of target from `(client id, nickname, uid)` of online clients
+    pub fn resolve<'a>(
+        &self,
+        clients: impl Iterator<Item = (i64, &'a str, &'a str)>,
+    ) -> anyhow::Result<i64> {
+        let clients = clients.collect::<Vec<_>>();
+        let matched = match self {
+            Self::ClientId(client_id) => clients
+                .iter()
+                .filter(|(id, ..)| id == client_id)
+                .collect::<Vec<_>>(),
+            Self::Uid(uid) => clients
+                .iter()
+                .filter(|(_, _, client_uid)| client_uid == uid)
+                .collect(),
+            Self::Nickname(nickname) => {
+                let exact = clients
+                    .iter()
+                    .filter(|(_, client_nickname, _)| client_nickname == nickname)
+                    .collect::<Vec<_>>();
+                if exact.is_empty() {
+                    clients
+                        .iter()
+                        .filter(|(_, client_nickname, _)| {
+                            client_nickname.to_lowercase() == nickname.to_lowercase()
+                        })
+                        .collect()
+                } else {
+                    exact
+                }
+            }
+        };
+        match matched.as_slice() {
+            [] => Err(anyhow::anyhow!("Client {self} not found")),
+            [(client_id, ..)] => Ok(*client_id),
+            // Same UID may connect multiple times
+            _ => Err(anyhow::anyhow!(
+                "Client {self} is ambiguous, use client id instead"
+            )),
+        }
+    }
+}
+
+impl Display for Target {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::ClientId(client_id) => write!(f, "#{client_id}"),
+            Self::Uid(uid) => write!(f, "{uid}"),
+            Self::Nickname(nickname) => write!(f, "\"{nickname}\""),
+        }
+    }
+}
+
+/// Moderation action which requested from chat bot
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum Action {
+    Kick(Target, String),
+    /// Ban duration in seconds, `0` means permanent
+    Ban(Target, u64, String),
+    Move(Target, i64),
+    Poke(Target, String),
+    Message(Target, String),
+    Unban(i64),
+}
+
+impl Action {
+    /// Parse arguments of command, return usage if arguments are invalid
+    pub fn parse(command: &str, args: &str) -> Result<Self, &'static str> {
+        let usage = Self::usage(command);
+        if command == "unban" {
+            return args.trim().parse().map(Self::Unban).map_err(|_| usage);
+        }
+        let (target, rest) = Target::split(args).ok_or(usage)?;
+        let reason = |rest: &str| {
+            if rest.is_empty() {
+                DEFAULT_REASON.to_string()
+            } else {
+                rest.to_string()
+            }
+        };
+        Ok(match command {
+            "kick" => Self::Kick(target, reason(rest)),
+            "ban" => {
+                let (duration, rest) = rest.split_once(char::is_whitespace).unwrap_or((rest, ""));
+                Self::Ban(
+                    target,
+                    parse_duration(duration).ok_or(usage)?,
+                    reason(rest.trim()),
+                )
+            }
+            "move" => Self::Move(target, rest.parse().map_err(|_| usage)?),
+            "poke" if !rest.is_empty() => Self::Poke(target, rest.to_string()),
+            "msg" if !rest.is_empty() => Self::Message(target, rest.to_string()),
+            _ => return Err(usage),
+        })
+    }
+
+    /// Role which is required to execute this action
+    pub fn role(&self) -> Role {
+        match self {
+            Self::Ban(..) | Self::Unban(..) => Role::Admin,
+            _ => Role::Moderator,
+        }
+    }
+
+    fn usage(command: &str) -> &'static str {
+        match command {
+            "kick" => "Usage: /kick <target> [reason]",
+            "ban" => {
+                "Usage: /ban <target> <duration, e.g. 30m, 2h, 7d or 0 for permanent> [reason]"
+            }
+            "move" => "Usage: /move <target> <channel id>",
+            "poke" => "Usage: /poke <target> <message>",
+            "msg" => "Usage: /msg <target> <message>",
+            "unban" => "Usage: /unban <ban id>",
+            _ => "Unknown moderation command",
+        }
+    }
+}
+
+impl Display for Action {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::Kick(target, reason) => write!(f, "kick {target}: {reason}"),
+            Self::Ban(target, 0, reason) => write!(f, "ban {target} permanently: {reason}"),
+            Self::Ban(target, duration, reason) => {
+                write!(f, "ban {target} for {duration}s: {reason}")
+            }
+            Self::Move(target, channel_id) => write!(f, "move {target} to channel {channel_id}"),
+            Self::Poke(target, message) => write!(f, "poke {target}: {message}"),
+            Self::Message(target, message) => write!(f, "message {target}: {message}"),
+            Self::Unban(ban_id) => write!(f, "remove ban {ban_id}"),
+        }
+    }
+}
+
+/// Parse duration like `90`, `30m`, `2h` or `7d` to seconds
+fn parse_duration(s: &str) -> Option<u64> {
+    let (value, unit) = match s.char_indices().last()? {
+        (index, unit) if unit.is_ascii_alphabetic() => (&s[..index], unit),
+        _ => (s, 's'),
+    };
+    let multiplier = match unit.to_ascii_lowercase() {
+        's' => 1,
+        'm' => 60,
+        'h' => 3600,
+        'd' => 86400,
+        _ => return None,
+    };
+    value.parse::<u64>().ok()?.checked_mul(multiplier)
+}
+
+#[cfg(test)]
+mod test {
+    use super::{Action, DEFAULT_REASON, Target};
+
+    #[test]
+    fn test_moderation() {
+        assert_eq!(
+            Action::parse("kick", "5"),
+            Ok(Action::Kick(Target::ClientId(5), DEFAULT_REASON.into()))
+        );
+        assert_eq!(
+            Action::parse("ban", "\"Alice Bob\" 2h spam bot"),
+            Ok(Action::Ban(
+                Target::Nickname("Alice Bob".into()),
+                7200,
+                "spam bot".into()
+            ))
+        );
+        assert_eq!(
+            Action::parse("msg", "QuietTeamspeak0000000000000= hello there"),
+            Ok(Action::Message(
+                Target::Uid("QuietTeamspeak0000000000000=".into()),
+                "hello there".into()
+            ))
+        );
+        assert_eq!(Action::parse("unban", " 12 "), Ok(Action::Unban(12)));
+        assert!(Action::parse("ban", "Alice forever").is_err());
+        assert!(Action::parse("move", "Alice lobby").is_err());
+        assert!(Action::parse("poke", "Alice").is_err());
+        assert!(Action::parse("kick", "").is_err());
+
+        let clients = [(1, "Alice", "a="), (2, "alice", "b="), (3, "Bob", "c=")];
+        let resolve = |target: Target| target.resolve(clients.iter().copied());
+        assert_eq!(resolve(Target::Nickname("Alice".into())).unwrap(), 1);
+        assert_eq!(resolve(Target::Nickname("BOB".into())).unwrap(), 3);
+        assert!(resolve(Target::Nickname("ALICE".into())).is_err());
+        assert_eq!(resolve(Target::Uid("b=".into())).unwrap(), 2);
+        assert!(resolve(Target::ClientId(4)).is_err());
+    }
+}
@@ -0,0 +1,275 @@
+use super::{Event, Notifier, country_action_kind, flag};
+use crate::configure::config::Discord;
+use crate::locale::Kind;
+use anyhow::anyhow;
+use async_trait::async_trait;
+use log::error;
+use reqwest::StatusCode;
+use serde::Deserialize;
+use serde_json::{Value, json};
+use std::time::Duration;
+use tokio::sync::mpsc;
+
+/// Maximum attempts when webhook is rate limited
+const RETRY_TIMES: usize = 3;
+
+#[derive(Deserialize)]
+struct RateLimited {
+    retry_after: f64,
+}
+
+/// Post events to discord webhook as embeds
+#[derive(Clone, Debug)]
+pub struct DiscordWebhook {
+    sender: mpsc::Sender<Event>,
+}
+
+impl DiscordWebhook {
+    pub fn spawn(config: &Discord) -> anyhow::Result<Self> {
+        let client = reqwest::Client::builder()
+            .timeout(Duration::from_secs(10))
+            .build()?;
+        let (sender, receiver) = mpsc::channel(64);
+        tokio::spawn(Self::run(
+            client,
+            config.webhook().to_string(),
+            config.username().map(str::to_string),
+            receiver,
+        ));
+        Ok(Self { sender })
+    }
+
+    async fn run(
+        client: reqwest::Client,
+        webhook: String,
+        username: Option<String>,
+        mut receiver: mpsc::Receiver<Event>,
+    ) {
+        while let Some(event) = receiver.recv().await {
+            Self::post(&client, &webhook, &payload(username.as_deref(), &event))
+                .await
+                .inspect_err(|e| error!("Unable send event to discord webhook: {e:?}"))
+                .ok();
+        }
+    }
+
+    async fn post(client: &reqwest::Client, webhook: &str, payload: &Value) -> anyhow::Result<()> {
+        for _ in 0..RETRY_TIMES {
+            let response = client.post(webhook).json(payload).send().await?;
+            if response.status() == StatusCode::TOO_MANY_REQUESTS {
+                let retry_after = response
+                    .json::<RateLimited>()
+                    .await
+                    .map(|r| r.retry_after)
+                    .unwrap_or(1.0);
+                tokio::time::sleep(Duration::from_secs_f64(retry_after.clamp(0.0, 60.0))).await;
+                continue;
+            }
+            response.error_for_status()?;
+            return Ok(());
+        }
+        Err(anyhow!("Still rate limited after {RETRY_TIMES} attempts"))
+    }
+}
+
+#[async_trait]
+impl Notifier for DiscordWebhook {
+    async fn notify(&self, event: &Event) -> Option<()> {
+        super::enqueue(&self.sender, "Discord webhook", event)
+    }
+}
+
+/// Escape discord markdown in user controlled text
+fn escape_markdown(text: &str) -> String {
+    let mut output = String::with_capacity(text.len());
+    for c in text.chars() {
+        if matches!(
+            c,
+            '\\' | '*' | '_' | '~' | '`' | '|' | '>' | '#' | '[' | ']'
+        ) {
+            output.push('\\');
+        }
+        output.push(c);
+    }
+    output
+}
+
+fn field(name: &str, value: &str) -> Value {
+    json!({"name": name, "value": value, "inline": true})
+}
+
+fn client_fields(nickname: &str, uid: &str, client_id: i64) -> Vec<Value> {
+    vec![
+        field("Nickname", &escape_markdown(nickname)),
+        field("Unique ID", &format!("`{uid}`")),
+        field("Client ID", &client_id.to_string()),
+    ]
+}
+
+fn style(kind: Kind) -> (&'static str, u32) {
+    match kind {
+        Kind::Enter => ("Client joined", 0x57F287),
+        Kind::Timeout => ("Connection lost", 0xE67E22),
+        Kind::Kick => ("Client kicked", 0xED4245),
+        Kind::Ban => ("Client banned", 0x992D22),
+        Kind::Country => ("Country policy", 0xFEE75C),
+        Kind::Clone | Kind::CloneKick => ("Clone detected", 0xFEE75C),
+        Kind::Raid => ("Raid detected", 0xED4245),
+        Kind::LockdownEnd => ("Lockdown ended", 0x57F287),
+        Kind::Chat => ("Chat", 0x5865F2),
+        Kind::Moderation => ("Moderation", 0x3498DB),
+        _ => ("Client left", 0x95A5A6),
+    }
+}
+
+/// Build webhook payload of event
+fn payload(username: Option<&str>, event: &Event) -> Value {
+    let kind = event.kind();
+    let (time, fields) = match event {
+        Event::Enter(time, client_id, uid, nickname, country, ..) => {
+            let mut fields = client_fields(nickname, uid, *client_id);
+            fields.push(field("Country", &flag(country)));
+            (time, fields)
+        }
+        Event::Left(time, view, nickname, ..) => {
+            let mut fields = vec![
+                field("Nickname", &escape_markdown(nickname)),
+                field("Client ID", &view.client_id().to_string()),
+            ];
+            if !view.reason().is_empty() {
+                fields.push(field("Reason", &escape_markdown(view.reason())));
+            }
+            if matches!(kind, Kind::Kick | Kind::Ban) {
+                fields.push(field(
+                    "Invoker",
+                    &format!(
+                        "{} (`{}`)",
+                        escape_markdown(view.invoker_name()),
+                        view.invoker_uid()
+                    ),
+                ));
+            }
+            (time, fields)
+        }
+        Event::Country(time, client_id, uid, nickname, country, action) => {
+            let mut fields = client_fields(nickname, uid, *client_id);
+            fields.push(field("Country", &flag(country)));
+            fields.push(field(
+                "Action",
+                match country_action_kind(*action) {
+                    Kind::CountryKick => "Kicked",
+                    Kind::CountryMove => "Moved to quarantine",
+                    _ => "Detected",
+                },
+            ));
+            (time, fields)
+        }
+        Event::Clone(time, client_id, uid, nickname, ip, alert, kicked) => {
+            let mut fields = client_fields(nickname, uid, *client_id);
+            fields.push(field("IP", &format!("`{ip}`")));
+            fields.push(field("Alert", &alert.to_string()));
+            fields.push(field("Kicked", if *kicked { "Yes" } else { "No" }));
+            (time, fields)
+        }
+        Event::Raid(time, count) => (time, vec![field("New users", &count.to_string())]),
+        Event::LockdownEnd(time) => (time, vec![]),
+        Event::Chat(time, nickname, message) => (
+            time,
+            vec![
+                field("Nickname", &escape_markdown(nickname)),
+                field("Message", &escape_markdown(message)),
+            ],
+        ),
+        Event::Moderation(time, invoker, action) => (
+            time,
+            vec![
+                field("Invoker", &escape_markdown(invoker)),
+                field("Action", &escape_markdown(action)),
+            ],
+        ),
+    };
+    let (title, color) = style(kind);
+    let mut payload = json!({
+        "embeds": [{
+            "title": title,
+            "color": color,
+            "fields": fields,
+            "footer": {"text": time},
+        }],
+        "allowed_mentions": {"parse": []},
+    });
+    if let Some(username) = username {
+        payload["username"] = json!(username);
+    }
+    payload
+}
+
+#[cfg(test)]
+mod test {
+    use super::DiscordWebhook;
+    use crate::configure::config::Discord;
+    use crate::notifier::test::stub;
+    use crate::notifier::{Event, Notifier};
+    use crate::types::{FromQueryString, NotifyClientLeftView};
+    use serde_json::{Value, json};
+    use tokio::net::TcpListener;
+
+    #[tokio::test]
+    async fn test_discord() {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let config: Discord = toml::from_str(&format!(
+            "webhook = \"http://{}/webhook\"\nusername = \"TeamSpeak\"",
+            listener.local_addr().unwrap()
+        ))
+        .unwrap();
+        let server = tokio::spawn(stub(listener));
+
+        let view = NotifyClientLeftView::from_query(
+            "cfid=1 ctid=0 reasonid=5 reasonmsg=spam invokerid=1 invokername=admin invokeruid=abc= clid=5",
+        )
+        .unwrap();
+        let webhook = DiscordWebhook::spawn(&config).unwrap();
+        webhook
+            .notify(&Event::Left(
+                "2026-01-01 00:00:00".into(),
+                view,
+                "*Alice*".into(),
+                "alice=".into(),
+                "JP".into(),
+                vec![8],
+                60,
+            ))
+            .await
+            .unwrap();
+
+        let (head, body) = server.await.unwrap();
+        assert!(head.starts_with("POST /webhook HTTP/1.1\r\n"));
+        let payload: Value = serde_json::from_str(&body).unwrap();
+        assert_eq!(payload["username"], "TeamSpeak");
+        assert_eq!(payload["allowed_mentions"], json!({"parse": []}));
+        let embed = &payload["embeds"][0];
+        assert_eq!(embed["title"], "Client kicked");
+        assert_eq!(embed["color"], 0xED4245);
+        assert_eq!(embed["footer"]["text"], "2026-01-01 00:00:00");
+        let fields = embed["fields"]
+            .as_array()
+            .unwrap()
+            .iter()
+            .map(|field| {
+                (
+                    field["name"].as_str().unwrap(),
+                    field["value"].as_str().unwrap(),
+                )
+            })
+            .collect::<Vec<_>>();
+        assert_eq!(
+            fields,
+            vec![
+                ("Nickname", "\\*Alice\\*"),
+                ("Client ID", "5"),
+                ("Reason", "spam"),
+                ("Invoker", "admin (`abc=`)"),
+            ]
+        );
+    }
+}
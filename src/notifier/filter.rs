@@ -0,0 +1,145 @@
+use super::{EVENT_TYPES, Event};
+use crate::configure::config::Filter;
+use crate::locale::Kind;
+use anyhow::anyhow;
+use chrono::NaiveTime;
+use log::warn;
+
+/// Decide which events are sent to telegram target, and when they should be held for digest
+#[derive(Clone, Debug, Default)]
+pub struct EventFilter {
+    config: Filter,
+    quiet_hours: Option<(NaiveTime, NaiveTime)>,
+}
+
+fn parse_time(s: &str) -> anyhow::Result<NaiveTime> {
+    NaiveTime::parse_from_str(s, "%H:%M").map_err(|e| anyhow!("Invalid quiet hours {s:?}: {e}"))
+}
+
+impl EventFilter {
+    pub fn new(config: &Filter) -> anyhow::Result<Self> {
+        for event in config.events() {
+            if !EVENT_TYPES.contains(&event.as_str()) {
+                warn!("Unknown telegram filter event type {event:?}, ignored");
+            }
+        }
+        let quiet_hours = match config.quiet_hours() {
+            Some((start, end)) => Some((parse_time(start)?, parse_time(end)?)),
+            None => None,
+        };
+        Ok(Self {
+            config: config.clone(),
+            quiet_hours,
+        })
+    }
+
+    /// Whether event should be sent, client filters only apply to enter and leave events
+    pub fn accept(&self, event: &Event) -> bool {
+        let config = &self.config;
+        if !config.events().is_empty() && !config.events().iter().any(|e| e == event.event_type()) {
+            return false;
+        }
+        let (uid, groups) = match event {
+            Event::Enter(_, _, uid, _, _, _, groups) => (uid, groups),
+            Event::Left(_, _, _, uid, _, groups, session) => {
+                // Kicked or banned client is always announced
+                if matches!(
+                    event.kind(),
+                    Kind::Leave | Kind::LeaveReason | Kind::Timeout
+                ) && *session < config.min_session()
+                {
+                    return false;
+                }
+                (uid, groups)
+            }
+            _ => return true,
+        };
+        if config.exclude_uid().contains(uid)
+            || groups
+                .iter()
+                .any(|group| config.exclude_groups().contains(group))
+        {
+            return false;
+        }
+        if config.include_uid().is_empty() && config.include_groups().is_empty() {
+            return true;
+        }
+        config.include_uid().contains(uid)
+            || groups
+                .iter()
+                .any(|group| config.include_groups().contains(group))
+    }
+
+    /// Whether `time` is in quiet hours, quiet hours may cross midnight
+    pub fn quiet(&self, time: NaiveTime) -> bool {
+        match self.quiet_hours {
+            Some((start, end)) if start <= end => start <= time && time < end,
+            Some((start, end)) => time >= start || time < end,
+            None => false,
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::EventFilter;
+    use crate::configure::config::Filter;
+    use crate::notifier::Event;
+    use crate::types::{FromQueryString, NotifyClientLeftView};
+    use chrono::NaiveTime;
+
+    #[test]
+    fn test_filter() {
+        let config: Filter = toml::from_str(
+            r#"
+events = ["enter", "leave", "kick"]
+include-groups = [8]
+exclude-uid = ["bot="]
+min-session = 60
+quiet-hours = ["23:00", "07:30"]
+"#,
+        )
+        .unwrap();
+        let filter = EventFilter::new(&config).unwrap();
+        let enter = |uid: &str, groups: Vec<i64>| {
+            Event::Enter(
+                "2026-01-01 00:00:00".into(),
+                5,
+                uid.into(),
+                "Alice".into(),
+                "JP".into(),
+                1,
+                groups,
+            )
+        };
+        let left = |query: &str, session: u64| {
+            Event::Left(
+                "2026-01-01 00:00:00".into(),
+                NotifyClientLeftView::from_query(query).unwrap(),
+                "Alice".into(),
+                "alice=".into(),
+                "JP".into(),
+                vec![8],
+                session,
+            )
+        };
+        assert!(filter.accept(&enter("alice=", vec![6, 8])));
+        assert!(!filter.accept(&enter("alice=", vec![6])));
+        assert!(!filter.accept(&enter("bot=", vec![8])));
+        assert!(!filter.accept(&left("cfid=1 ctid=0 reasonid=8 clid=5", 30)));
+        assert!(filter.accept(&left("cfid=1 ctid=0 reasonid=8 clid=5", 90)));
+        assert!(filter.accept(&left("cfid=1 ctid=0 reasonid=5 clid=5", 30)));
+        assert!(!filter.accept(&left("cfid=1 ctid=0 reasonid=3 clid=5", 90)));
+        assert!(!filter.accept(&Event::Raid("2026-01-01 00:00:00".into(), 10)));
+
+        let time = |s: &str| NaiveTime::parse_from_str(s, "%H:%M").unwrap();
+        assert!(filter.quiet(time("23:00")));
+        assert!(filter.quiet(time("03:00")));
+        assert!(!filter.quiet(time("07:30")));
+        assert!(!filter.quiet(time("12:00")));
+        assert!(!EventFilter::default().quiet(time("03:00")));
+
+        let config: Filter = toml::from_str("quiet-hours = [\"25:00\", \"07:00\"]").unwrap();
+        assert!(EventFilter::new(&config).is_err());
+    }
+}
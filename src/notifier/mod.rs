@@ -0,0 +1,415 @@
+mod discord;
+mod filter;
+mod webhook;
+
+use crate::configure::config::CountryAction;
+use crate::locale::{Kind, Templates};
+use crate::policy::clone::CloneAlert;
+use crate::types::{NotifyClientEnterView, NotifyClientLeftView};
+use async_trait::async_trait;
+use log::warn;
+use tokio::sync::mpsc::{self, error::TrySendError};
+
+pub use discord::DiscordWebhook;
+pub use filter::EventFilter;
+pub use webhook::JsonWebhook;
+
+/// Event types which can be used in `events` filter
+pub const EVENT_TYPES: [&str; 10] = [
+    "enter",
+    "leave",
+    "timeout",
+    "kick",
+    "ban",
+    "country",
+    "clone",
+    "raid",
+    "lockdown-end",
+    "moderation",
+];
+
+/// Event which observer reports to notifiers
+#[derive(Clone, Debug)]
+#[non_exhaustive]
+pub enum Event {
+    Enter(String, i64, String, String, String, i64, Vec<i64>),
+    /// Server groups and session seconds of client are appended
+    Left(
+        String,
+        NotifyClientLeftView,
+        String,
+        String,
+        String,
+        Vec<i64>,
+        u64,
+    ),
+    Country(String, i64, String, String, String, CountryAction),
+    Clone(String, i64, String, String, String, CloneAlert, bool),
+    Raid(String, usize),
+    LockdownEnd(String),
+    Chat(String, String, String),
+    Moderation(String, String, String),
+}
+
+impl Event {
+    fn from_left(
+        time: String,
+        view: &NotifyClientLeftView,
+        nickname: String,
+        unique_identifier: String,
+        country: String,
+        (server_groups, session): (Vec<i64>, u64),
+    ) -> Self {
+        Self::Left(
+            time,
+            view.clone(),
+            nickname,
+            unique_identifier,
+            country,
+            server_groups,
+            session,
+        )
+    }
+    fn from_enter(time: String, view: &NotifyClientEnterView) -> Self {
+        Self::Enter(
+            time,
+            view.client_id(),
+            view.client_unique_identifier().to_string(),
+            view.client_nickname().to_string(),
+            view.client_country().to_string(),
+            view.channel_id(),
+            view.server_groups(),
+        )
+    }
+    fn from_country(time: String, view: &NotifyClientEnterView, action: CountryAction) -> Self {
+        Self::Country(
+            time,
+            view.client_id(),
+            view.client_unique_identifier().to_string(),
+            view.client_nickname().to_string(),
+            view.client_country().to_string(),
+            action,
+        )
+    }
+    fn from_clone(
+        time: String,
+        view: &NotifyClientEnterView,
+        ip: String,
+        alert: CloneAlert,
+        kicked: bool,
+    ) -> Self {
+        Self::Clone(
+            time,
+            view.client_id(),
+            view.client_unique_identifier().to_string(),
+            view.client_nickname().to_string(),
+            ip,
+            alert,
+            kicked,
+        )
+    }
+}
+
+impl Event {
+    /// Template kind of this event
+    pub fn kind(&self) -> Kind {
+        match self {
+            Event::Enter(..) => Kind::Enter,
+            Event::Left(_, view, ..) => match view.reason_id() {
+                8 if view.reason().is_empty() => Kind::Leave,
+                8 => Kind::LeaveReason,
+                3 => Kind::Timeout,
+                5 => Kind::Kick,
+                6 => Kind::Ban,
+                _ => unreachable!("Got unexpected left message: {view:?}"),
+            },
+            Event::Country(..) => Kind::Country,
+            Event::Clone(.., true) => Kind::CloneKick,
+            Event::Clone(..) => Kind::Clone,
+            Event::Raid(..) => Kind::Raid,
+            Event::LockdownEnd(..) => Kind::LockdownEnd,
+            Event::Chat(..) => Kind::Chat,
+            Event::Moderation(..) => Kind::Moderation,
+        }
+    }
+
+    /// Name of event type in `events` filter
+    pub fn event_type(&self) -> &'static str {
+        match self.kind() {
+            Kind::Leave | Kind::LeaveReason => "leave",
+            Kind::CloneKick => "clone",
+            kind => kind.as_str(),
+        }
+    }
+
+    /// Render message by templates of configure
+    pub fn render(&self, templates: &Templates) -> String {
+        match self {
+            Event::Enter(time, client_id, client_identifier, nickname, country, ..) => templates
+                .render(
+                    Kind::Enter,
+                    &[
+                        ("time", time),
+                        ("nickname", nickname),
+                        ("uid", client_identifier),
+                        ("client_id", &client_id.to_string()),
+                        ("flag", &flag(country)),
+                    ],
+                ),
+            Event::Left(time, view, nickname, ..) => {
+                let client_id = view.client_id().to_string();
+                let reason = if view.reason().is_empty() {
+                    templates.get(Kind::NoReason)
+                } else {
+                    view.reason()
+                };
+                templates.render(
+                    self.kind(),
+                    &[
+                        ("time", time),
+                        ("nickname", nickname),
+                        ("client_id", &client_id),
+                        ("invoker", view.invoker_name()),
+                        ("invoker_uid", view.invoker_uid()),
+                        ("reason", reason),
+                    ],
+                )
+            }
+            Event::Country(time, client_id, client_identifier, nickname, country, action) => {
+                templates.render(
+                    Kind::Country,
+                    &[
+                        ("time", time),
+                        ("nickname", nickname),
+                        ("uid", client_identifier),
+                        ("client_id", &client_id.to_string()),
+                        ("flag", &flag(country)),
+                        ("action", templates.get(country_action_kind(*action))),
+                    ],
+                )
+            }
+            Event::Clone(time, client_id, client_identifier, nickname, ip, alert, _) => templates
+                .render(
+                    self.kind(),
+                    &[
+                        ("time", time),
+                        ("nickname", nickname),
+                        ("uid", client_identifier),
+                        ("client_id", &client_id.to_string()),
+                        ("ip", ip),
+                        ("alert", &alert.to_string()),
+                    ],
+                ),
+            Event::Raid(time, count) => {
+                templates.render(Kind::Raid, &[("time", time), ("count", &count.to_string())])
+            }
+            Event::LockdownEnd(time) => templates.render(Kind::LockdownEnd, &[("time", time)]),
+            Event::Chat(time, nickname, message) => templates.render(
+                Kind::Chat,
+                &[("time", time), ("nickname", nickname), ("message", message)],
+            ),
+            Event::Moderation(time, invoker, action) => templates.render(
+                Kind::Moderation,
+                &[("time", time), ("invoker", invoker), ("action", action)],
+            ),
+        }
+    }
+}
+
+fn country_action_kind(action: CountryAction) -> Kind {
+    match action {
+        CountryAction::Kick => Kind::CountryKick,
+        CountryAction::Move => Kind::CountryMove,
+        CountryAction::Notify => Kind::CountryNotify,
+    }
+}
+
+fn flag(country: &str) -> String {
+    country_emoji::flag(country).unwrap_or_else(|| country.into())
+}
+
+/// Destination of observer events, e.g. telegram bot or discord webhook
+#[async_trait]
+pub trait Notifier: Send + Sync {
+    /// Queue event to this notifier, return `None` if notifier is gone
+    async fn notify(&self, event: &Event) -> Option<()>;
+
+    /// Whether this notifier relays server chat
+    fn bridge(&self) -> bool {
+        false
+    }
+
+    /// Send event to users privately, notifier which unable do that should ignore it
+    async fn notify_users(&self, _users: &[u64], _event: &Event) -> Option<()> {
+        Some(())
+    }
+}
+
+/// Queue event to background sender without waiting, so a slow endpoint never blocks observer.
+/// Event is dropped if queue is full.
+pub(crate) fn enqueue(sender: &mpsc::Sender<Event>, name: &str, event: &Event) -> Option<()> {
+    match sender.try_send(event.clone()) {
+        Ok(()) => Some(()),
+        Err(TrySendError::Full(event)) => {
+            warn!("{name} queue is full, drop {} event", event.event_type());
+            None
+        }
+        Err(TrySendError::Closed(_)) => None,
+    }
+}
+
+/// Notifiers which one configure routes to
+pub struct NotifierSet {
+    notifiers: Vec<Box<dyn Notifier>>,
+}
+
+impl NotifierSet {
+    pub fn new(notifiers: Vec<Box<dyn Notifier>>) -> Self {
+        Self { notifiers }
+    }
+
+    async fn dispatch(&self, event: Event) -> Option<()> {
+        let mut ret = Some(());
+        for notifier in &self.notifiers {
+            if notifier.notify(&event).await.is_none() {
+                ret = None;
+            }
+        }
+        ret
+    }
+
+    pub async fn send_left(
+        &self,
+        time: String,
+        view: &NotifyClientLeftView,
+        nickname: String,
+        unique_identifier: String,
+        country: String,
+        session: (Vec<i64>, u64),
+    ) -> Option<()> {
+        self.dispatch(Event::from_left(
+            time,
+            view,
+            nickname,
+            unique_identifier,
+            country,
+            session,
+        ))
+        .await
+    }
+    pub async fn send_enter(&self, time: String, view: &NotifyClientEnterView) -> Option<()> {
+        self.dispatch(Event::from_enter(time, view)).await
+    }
+    pub async fn send_country(
+        &self,
+        time: String,
+        view: &NotifyClientEnterView,
+        action: CountryAction,
+    ) -> Option<()> {
+        self.dispatch(Event::from_country(time, view, action)).await
+    }
+    pub async fn send_clone(
+        &self,
+        time: String,
+        view: &NotifyClientEnterView,
+        ip: String,
+        alert: CloneAlert,
+        kicked: bool,
+    ) -> Option<()> {
+        self.dispatch(Event::from_clone(time, view, ip, alert, kicked))
+            .await
+    }
+    pub async fn send_raid(&self, time: String, count: usize) -> Option<()> {
+        self.dispatch(Event::Raid(time, count)).await
+    }
+    pub async fn send_lockdown_end(&self, time: String) -> Option<()> {
+        self.dispatch(Event::LockdownEnd(time)).await
+    }
+    /// Audit moderation action which requested from chat bot
+    pub async fn send_moderation(
+        &self,
+        time: String,
+        invoker: String,
+        action: String,
+    ) -> Option<()> {
+        self.dispatch(Event::Moderation(time, invoker, action))
+            .await
+    }
+    /// Notify users which watch entered client
+    pub async fn send_presence(
+        &self,
+        time: String,
+        view: &NotifyClientEnterView,
+        users: &[u64],
+    ) -> Option<()> {
+        let event = Event::from_enter(time, view);
+        let mut ret = Some(());
+        for notifier in &self.notifiers {
+            if notifier.notify_users(users, &event).await.is_none() {
+                ret = None;
+            }
+        }
+        ret
+    }
+    /// Relay server chat to notifiers which support bridge
+    pub async fn send_chat(&self, time: String, nickname: String, message: String) -> Option<()> {
+        let event = Event::Chat(time, nickname, message);
+        let mut ret = Some(());
+        for notifier in self.notifiers.iter().filter(|notifier| notifier.bridge()) {
+            if notifier.notify(&event).await.is_none() {
+                ret = None;
+            }
+        }
+        ret
+    }
+}
+
+#[cfg(test)]
+pub(crate) mod test {
+    use super::{Event, enqueue};
+    use tokio::io::{AsyncReadExt, AsyncWriteExt};
+    use tokio::net::TcpListener;
+    use tokio::sync::mpsc;
+
+    #[test]
+    fn test_enqueue() {
+        let (sender, mut receiver) = mpsc::channel(1);
+        let event = Event::LockdownEnd("2026-01-01 00:00:00".into());
+        assert!(enqueue(&sender, "test", &event).is_some());
+        // Full queue drops event instead of waiting
+        assert!(enqueue(&sender, "test", &event).is_none());
+        assert!(receiver.try_recv().is_ok());
+        assert!(receiver.try_recv().is_err());
+        drop(receiver);
+        assert!(enqueue(&sender, "test", &event).is_none());
+    }
+
+    /// Accept one request, reply `204 No Content`, return request head and body
+    pub(crate) async fn stub(listener: TcpListener) -> (String, String) {
+        let (mut stream, _) = listener.accept().await.unwrap();
+        let mut buf = vec![];
+        let mut chunk = [0u8; 1024];
+        loop {
+            let size = stream.read(&mut chunk).await.unwrap();
+            assert_ne!(size, 0, "Connection closed before request complete");
+            buf.extend_from_slice(&chunk[..size]);
+            let request = String::from_utf8_lossy(&buf).to_string();
+            let Some((head, body)) = request.split_once("\r\n\r\n") else {
+                continue;
+            };
+            let length = head
+                .lines()
+                .filter_map(|line| line.split_once(':'))
+                .find(|(key, _)| key.eq_ignore_ascii_case("content-length"))
+                .and_then(|(_, value)| value.trim().parse::<usize>().ok())
+                .unwrap_or_default();
+            if body.len() >= length {
+                stream
+                    .write_all(b"HTTP/1.1 204 No Content\r\n\r\n")
+                    .await
+                    .unwrap();
+                return (head.to_string(), body.to_string());
+            }
+        }
+    }
+}
@@ -0,0 +1,254 @@
+use super::{EVENT_TYPES, Event, Notifier};
+use crate::configure::config::{CountryAction, Webhook};
+use anyhow::anyhow;
+use async_trait::async_trait;
+use hmac::{Hmac, Mac};
+use log::{error, warn};
+use reqwest::StatusCode;
+use reqwest::header::CONTENT_TYPE;
+use serde_json::{Value, json};
+use sha2::Sha256;
+use std::time::Duration;
+use tokio::sync::mpsc;
+
+const SIGNATURE_HEADER: &str = "X-Signature-256";
+
+/// Post every event as JSON to HTTP endpoint
+#[derive(Clone, Debug)]
+pub struct JsonWebhook {
+    sender: mpsc::Sender<Event>,
+    events: Vec<String>,
+}
+
+impl JsonWebhook {
+    pub fn spawn(config_id: String, config: &Webhook) -> anyhow::Result<Self> {
+        for event in config.events() {
+            if !EVENT_TYPES.contains(&event.as_str()) {
+                warn!("Unknown webhook event type {event:?}, ignored");
+            }
+        }
+        let client = reqwest::Client::builder()
+            .timeout(Duration::from_secs(10))
+            .build()?;
+        let (sender, receiver) = mpsc::channel(64);
+        tokio::spawn(Self::run(client, config_id, config.clone(), receiver));
+        Ok(Self {
+            sender,
+            events: config.events().to_vec(),
+        })
+    }
+
+    fn accept(&self, event: &Event) -> bool {
+        self.events.is_empty() || self.events.iter().any(|e| e.eq(event.event_type()))
+    }
+
+    async fn run(
+        client: reqwest::Client,
+        config_id: String,
+        config: Webhook,
+        mut receiver: mpsc::Receiver<Event>,
+    ) {
+        while let Some(event) = receiver.recv().await {
+            Self::post(&client, &config, payload(&config_id, &event).to_string())
+                .await
+                .inspect_err(|e| error!("Unable send event to webhook {}: {e:?}", config.url()))
+                .ok();
+        }
+    }
+
+    /// Post body, retry with exponential backoff on network error, rate limit or server error
+    async fn post(client: &reqwest::Client, config: &Webhook, body: String) -> anyhow::Result<()> {
+        let mut attempt = 0;
+        loop {
+            let mut request = client
+                .post(config.url())
+                .header(CONTENT_TYPE, "application/json")
+                .body(body.clone());
+            if let Some(secret) = config.secret() {
+                request = request.header(SIGNATURE_HEADER, signature(secret, &body));
+            }
+            let error = match request.send().await {
+                Ok(response) if response.status().is_success() => return Ok(()),
+                Ok(response)
+                    if response.status().is_server_error()
+                        || response.status() == StatusCode::TOO_MANY_REQUESTS =>
+                {
+                    anyhow!("Server responded {}", response.status())
+                }
+                Ok(response) => return Err(anyhow!("Server responded {}", response.status())),
+                Err(e) => e.into(),
+            };
+            if attempt >= config.retries() {
+                return Err(error);
+            }
+            warn!(
+                "Post to webhook {} failed ({error}), retry in {}s",
+                config.url(),
+                1 << attempt
+            );
+            tokio::time::sleep(Duration::from_secs(1 << attempt)).await;
+            attempt += 1;
+        }
+    }
+}
+
+#[async_trait]
+impl Notifier for JsonWebhook {
+    async fn notify(&self, event: &Event) -> Option<()> {
+        if !self.accept(event) {
+            return Some(());
+        }
+        super::enqueue(&self.sender, "JSON webhook", event)
+    }
+}
+
+/// HMAC-SHA256 of body, formatted as `sha256=<hex>`
+fn signature(secret: &str, body: &str) -> String {
+    let mut mac =
+        Hmac::<Sha256>::new_from_slice(secret.as_bytes()).expect("HMAC accepts any key length");
+    mac.update(body.as_bytes());
+    format!("sha256={}", hex::encode(mac.finalize().into_bytes()))
+}
+
+fn client(client_id: i64, uid: &str, nickname: &str) -> Value {
+    json!({
+        "client_id": client_id,
+        "uid": uid,
+        "nickname": nickname,
+    })
+}
+
+/// Build JSON payload of event
+fn payload(config_id: &str, event: &Event) -> Value {
+    let (time, detail) = match event {
+        Event::Enter(time, client_id, uid, nickname, country, channel_id, _) => {
+            let mut detail = client(*client_id, uid, nickname);
+            detail["country"] = json!(country);
+            detail["channel_id"] = json!(channel_id);
+            (time, detail)
+        }
+        Event::Left(time, view, nickname, uid, country, ..) => {
+            let mut detail = client(view.client_id(), uid, nickname);
+            detail["country"] = json!(country);
+            detail["channel_id"] = json!(view.channel_id());
+            detail["reason"] = json!(view.reason());
+            if !view.invoker_uid().is_empty() {
+                detail["invoker"] = json!({
+                    "uid": view.invoker_uid(),
+                    "nickname": view.invoker_name(),
+                });
+            }
+            (time, detail)
+        }
+        Event::Country(time, client_id, uid, nickname, country, action) => {
+            let mut detail = client(*client_id, uid, nickname);
+            detail["country"] = json!(country);
+            detail["action"] = json!(match action {
+                CountryAction::Kick => "kick",
+                CountryAction::Move => "move",
+                CountryAction::Notify => "notify",
+            });
+            (time, detail)
+        }
+        Event::Clone(time, client_id, uid, nickname, ip, alert, kicked) => {
+            let mut detail = client(*client_id, uid, nickname);
+            detail["ip"] = json!(ip);
+            detail["alert"] = json!(alert.to_string());
+            detail["kicked"] = json!(kicked);
+            (time, detail)
+        }
+        Event::Raid(time, count) => (time, json!({"count": count})),
+        Event::LockdownEnd(time) => (time, json!({})),
+        Event::Chat(time, nickname, message) => {
+            (time, json!({"nickname": nickname, "message": message}))
+        }
+        Event::Moderation(time, invoker, action) => {
+            (time, json!({"invoker": invoker, "action": action}))
+        }
+    };
+    let mut payload = json!({
+        "config_id": config_id,
+        "event": event.event_type(),
+        "time": time,
+    });
+    if let (Some(payload), Value::Object(detail)) = (payload.as_object_mut(), detail) {
+        payload.extend(detail);
+    }
+    payload
+}
+
+#[cfg(test)]
+mod test {
+    use super::{JsonWebhook, SIGNATURE_HEADER, signature};
+    use crate::configure::config::Webhook;
+    use crate::notifier::test::stub;
+    use crate::notifier::{Event, Notifier};
+    use crate::types::{FromQueryString, NotifyClientLeftView};
+    use serde_json::{Value, json};
+    use tokio::net::TcpListener;
+
+    #[tokio::test]
+    async fn test_webhook() {
+        // RFC 4231 test case 2
+        assert_eq!(
+            signature("Jefe", "what do ya want for nothing?"),
+            "sha256=5bdcc146bf60754e6a042426089575c75a003f089d2739839dec58b964ec3843"
+        );
+
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let config: Webhook = toml::from_str(&format!(
+            "url = \"http://{}/events\"\nsecret = \"secret\"\nevents = [\"kick\", \"ban\"]",
+            listener.local_addr().unwrap()
+        ))
+        .unwrap();
+        let server = tokio::spawn(stub(listener));
+        let webhook = JsonWebhook::spawn("localhost:10011(1)".into(), &config).unwrap();
+
+        // Filtered out, server only accepts one request
+        webhook
+            .notify(&Event::Raid("2026-01-01 00:00:00".into(), 10))
+            .await
+            .unwrap();
+        let view = NotifyClientLeftView::from_query(
+            "cfid=2 ctid=0 reasonid=5 reasonmsg=spam invokerid=1 invokername=admin invokeruid=abc= clid=5",
+        )
+        .unwrap();
+        webhook
+            .notify(&Event::Left(
+                "2026-01-01 00:00:01".into(),
+                view,
+                "Alice".into(),
+                "alice=".into(),
+                "JP".into(),
+                vec![8],
+                60,
+            ))
+            .await
+            .unwrap();
+
+        let (head, body) = server.await.unwrap();
+        assert!(head.starts_with("POST /events HTTP/1.1\r\n"));
+        let header = head
+            .lines()
+            .filter_map(|line| line.split_once(": "))
+            .find(|(key, _)| key.eq_ignore_ascii_case(SIGNATURE_HEADER))
+            .map(|(_, value)| value)
+            .unwrap();
+        assert_eq!(header, signature("secret", &body));
+        assert_eq!(
+            serde_json::from_str::<Value>(&body).unwrap(),
+            json!({
+                "config_id": "localhost:10011(1)",
+                "event": "kick",
+                "time": "2026-01-01 00:00:01",
+                "client_id": 5,
+                "uid": "alice=",
+                "nickname": "Alice",
+                "country": "JP",
+                "channel_id": 2,
+                "reason": "spam",
+                "invoker": {"uid": "abc=", "nickname": "admin"},
+            })
+        );
+    }
+}
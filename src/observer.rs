@@ -1,7 +1,11 @@
 use crate::auto_channel::AutoChannelInstance;
 use crate::configure::Config;
+use crate::configure::config::{Bridge, Rank, Welcome};
+use crate::moderation::{Action, Target};
+use crate::plugins::KVMap;
 use crate::socketlib::SocketConn;
-use crate::types::EventHelperTrait;
+use crate::types::{Client, EventHelperTrait, EventType, NotifyClientEnterView, TrackerEvent};
+use crate::watch::{self, WatchRequest};
 use crate::{DEFAULT_OBSERVER_NICKNAME, OBSERVER_NICKNAME_OVERRIDE};
 use anyhow::anyhow;
 use log::{error, info, trace, warn};
@@ -9,11 +13,19 @@ use std::borrow::Cow;
 use std::collections::HashMap;
 use std::time::Duration;
 use tap::TapOptional;
-use tokio::sync::mpsc;
+use tokio::sync::{mpsc, oneshot};
 
 pub enum PrivateMessageRequest {
     // Credit: SpriteOvO
     Message(i64, Cow<'static, str>),
+    /// End raid lockdown, reply `true` if server was in lockdown
+    EndLockdown(oneshot::Sender<bool>),
+    /// Message from telegram which should be relayed to server chat
+    Bridge(String),
+    /// Moderation action requested by telegram user, reply description of executed action
+    Moderate(String, Action, oneshot::Sender<anyhow::Result<String>>),
+    /// Presence subscription of telegram user
+    Watch(u64, WatchRequest, oneshot::Sender<anyhow::Result<String>>),
     KeepAlive,
     Terminate,
 }
@@ -22,10 +34,18 @@ struct Arguments<'a> {
     ignore_list: &'a [String],
     monitor_channel: &'a AutoChannelInstance,
     whitelist_ip: &'a [String],
-    telegram_sender: &'a BindTelegramHelper,
+    notifier: &'a NotifierSet,
     current_time: &'a str,
     tracker_controller: &'a (dyn EventHelperTrait + Send + Sync),
     thread_id: &'a str,
+    policy: &'a PolicySet,
+    welcome: &'a Welcome,
+    rank: &'a Rank,
+    server_id: &'a str,
+    tracker_database: Option<&'a str>,
+    bridge: &'a Bridge,
+    observer_id: i64,
+    watch_cooldown: u64,
 }
 
 impl<'a> Arguments<'a> {
@@ -38,8 +58,8 @@ impl<'a> Arguments<'a> {
     pub fn whitelist_ip(&self) -> &'a [String] {
         self.whitelist_ip
     }
-    pub fn telegram_sender(&self) -> &'a BindTelegramHelper {
-        self.telegram_sender
+    pub fn notifier(&self) -> &'a NotifierSet {
+        self.notifier
     }
     pub fn current_time(&self) -> &'a str {
         self.current_time
@@ -51,36 +71,134 @@ impl<'a> Arguments<'a> {
     pub fn thread_id(&self) -> &'a str {
         self.thread_id
     }
+    pub fn policy(&self) -> &'a PolicySet {
+        self.policy
+    }
+    pub fn welcome(&self) -> &'a Welcome {
+        self.welcome
+    }
+    pub fn rank(&self) -> &'a Rank {
+        self.rank
+    }
+    pub fn server_id(&self) -> &'a str {
+        self.server_id
+    }
+    pub fn tracker_database(&self) -> Option<&'a str> {
+        self.tracker_database
+    }
+    pub fn bridge(&self) -> &'a Bridge {
+        self.bridge
+    }
+    /// Client id of observer itself
+    pub fn observer_id(&self) -> i64 {
+        self.observer_id
+    }
+    pub fn watch_cooldown(&self) -> u64 {
+        self.watch_cooldown
+    }
+}
+
+#[derive(Clone, Debug)]
+pub(crate) struct ClientRecord {
+    nickname: String,
+    unique_identifier: String,
+    database_id: i64,
+    ip: String,
+    country: String,
+    server_query: bool,
+    server_groups: Vec<i64>,
+    /// Time of client entered or observer started
+    entered: u64,
+    /// Time of online time last accounted
+    accounted: u64,
+}
 
-    #[must_use]
-    pub fn new(
-        ignore_list: &'a [String],
-        monitor_channel: &'a AutoChannelInstance,
-        whitelist_ip: &'a [String],
-        telegram_sender: &'a BindTelegramHelper,
-        current_time: &'a str,
-        tracker_controller: &'a (dyn EventHelperTrait + Send + Sync),
-        thread_id: &'a str,
-    ) -> Self {
+impl ClientRecord {
+    pub fn from_enter(view: &NotifyClientEnterView, server_query: bool) -> Self {
         Self {
-            ignore_list,
-            monitor_channel,
-            whitelist_ip,
-            telegram_sender,
-            current_time,
-            tracker_controller,
-            thread_id,
+            nickname: view.client_nickname().to_string(),
+            unique_identifier: view.client_unique_identifier().to_string(),
+            database_id: view.client_database_id(),
+            ip: String::new(),
+            country: view.client_country().to_string(),
+            server_query,
+            server_groups: view.server_groups(),
+            entered: kstool::time::get_current_second(),
+            accounted: kstool::time::get_current_second(),
         }
     }
+
+    pub fn from_client(client: &Client) -> Self {
+        Self {
+            nickname: client.client_nickname().to_string(),
+            unique_identifier: client.client_unique_identifier().to_string(),
+            database_id: client.client_database_id(),
+            ip: client.client_ip().to_string(),
+            country: client.client_country().to_string(),
+            server_query: false,
+            server_groups: client.server_groups(),
+            entered: kstool::time::get_current_second(),
+            accounted: kstool::time::get_current_second(),
+        }
+    }
+
+    pub fn nickname(&self) -> &str {
+        &self.nickname
+    }
+    pub fn unique_identifier(&self) -> &str {
+        &self.unique_identifier
+    }
+    pub fn ip(&self) -> &str {
+        &self.ip
+    }
+    pub fn country(&self) -> &str {
+        &self.country
+    }
+    pub fn database_id(&self) -> i64 {
+        self.database_id
+    }
+    pub fn server_query(&self) -> bool {
+        self.server_query
+    }
+    pub fn server_groups(&self) -> &[i64] {
+        &self.server_groups
+    }
+    /// Seconds since client entered
+    pub fn session(&self, now: u64) -> u64 {
+        now.saturating_sub(self.entered)
+    }
+    /// Take online seconds since last accounted
+    pub fn take_online(&mut self, now: u64) -> u64 {
+        let seconds = now.saturating_sub(self.accounted);
+        self.accounted = now;
+        seconds
+    }
+    /// Build tracker event with client information
+    pub fn tracker_event(&self, event_type: EventType, client_id: i64) -> TrackerEvent {
+        TrackerEvent::new(event_type, client_id)
+            .with_client(self.database_id, &self.unique_identifier, &self.nickname)
+            .with_address(&self.ip, &self.country)
+    }
+    pub fn set_nickname(&mut self, nickname: String) {
+        self.nickname = nickname;
+    }
+    pub fn set_ip(&mut self, ip: String) {
+        self.ip = ip;
+    }
 }
 
 mod processor {
-    use super::Arguments;
+    use super::{Arguments, ClientRecord};
+    use crate::configure::config::{CountryAction, PolicyAction, WelcomeMode};
+    use crate::plugins::KVMap;
+    use crate::policy::execute_action;
+    use crate::policy::raid::RaidStatus;
     use crate::socketlib::SocketConn;
     use crate::types::{
-        BanEntry, FromQueryString, NotifyClientEnterView, NotifyClientLeftView,
-        NotifyClientMovedView, NotifyTextMessage,
+        BanEntry, EventType, FromQueryString, NotifyClientEnterView, NotifyClientLeftView,
+        NotifyClientMovedView, NotifyClientUpdated, NotifyTextMessage,
     };
+    use crate::{rank, template, watch, welcome};
     use anyhow::anyhow;
     use futures_util::FutureExt;
     use log::{error, info, trace, warn};
@@ -91,10 +209,328 @@ mod processor {
     pub(super) struct Processor;
 
     impl Processor {
+        /// Return `true` if client has been removed from server by nickname policy.
+        async fn check_nickname(
+            argument: &Arguments<'_>,
+            conn: &mut SocketConn,
+            client_id: i64,
+            unique_identifier: &str,
+            nickname: &str,
+        ) -> anyhow::Result<bool> {
+            let checker = argument.policy().nickname();
+            let Some(violation) = checker.check(unique_identifier, nickname) else {
+                return Ok(false);
+            };
+            let policy = checker.policy();
+            info!(
+                "[{}] Nickname {nickname:?}({unique_identifier}) {violation}, apply {:?}",
+                argument.thread_id(),
+                policy.action(),
+            );
+            if let Err(e) = execute_action(
+                conn,
+                policy.action(),
+                client_id,
+                &policy.reason(),
+                policy.ban_duration(),
+            )
+            .await
+            {
+                warn!(
+                    "[{}] Unable apply {:?} to client {client_id}: {e}",
+                    argument.thread_id(),
+                    policy.action(),
+                );
+                return Ok(false);
+            }
+            Ok(policy.action() != PolicyAction::Poke)
+        }
+
+        /// Return `true` if client has been removed from server or moved to quarantine by country
+        /// policy.
+        async fn check_country(
+            argument: &Arguments<'_>,
+            conn: &mut SocketConn,
+            view: &NotifyClientEnterView,
+        ) -> anyhow::Result<bool> {
+            let checker = argument.policy().country();
+            if !checker.check(
+                view.client_unique_identifier(),
+                &view.server_groups(),
+                view.client_country(),
+            ) {
+                return Ok(false);
+            }
+            let policy = checker.policy();
+            info!(
+                "[{}] Client {:?}({}) from {:?}, apply {:?}",
+                argument.thread_id(),
+                view.client_nickname(),
+                view.client_unique_identifier(),
+                view.client_country(),
+                policy.action(),
+            );
+            let result = match (policy.action(), policy.quarantine_channel()) {
+                (CountryAction::Kick, _) => {
+                    conn.kick_client(view.client_id(), &policy.reason()).await
+                }
+                // Quarantine channel is validated when configure is loaded
+                (CountryAction::Move, Some(channel)) => {
+                    conn.move_client(view.client_id(), channel).await
+                }
+                _ => Ok(()),
+            };
+            if let Err(e) = result {
+                warn!(
+                    "[{}] Unable apply {:?} to client {}: {e}",
+                    argument.thread_id(),
+                    policy.action(),
+                    view.client_id(),
+                );
+                return Ok(false);
+            }
+            argument
+                .notifier()
+                .send_country(argument.current_time().to_string(), view, policy.action())
+                .await
+                .tap_none(|| {
+                    error!(
+                        "[{}] Got error while send data to notifier",
+                        argument.thread_id()
+                    )
+                });
+            Ok(policy.action() != CountryAction::Notify)
+        }
+
+        /// Return `true` if client has been kicked by clone detection.
+        async fn check_clone(
+            argument: &Arguments<'_>,
+            conn: &mut SocketConn,
+            view: &NotifyClientEnterView,
+            client_map: &mut HashMap<i64, ClientRecord>,
+        ) -> anyhow::Result<bool> {
+            let detector = argument.policy().clone_detector();
+            if !detector.enabled() {
+                return Ok(false);
+            }
+            // Client may leave before query
+            let Some(info) = conn
+                .query_client_info(view.client_id())
+                .await
+                .inspect_err(|e| {
+                    warn!(
+                        "[{}] Unable query client information: {e}",
+                        argument.thread_id()
+                    )
+                })
+                .ok()
+                .flatten()
+            else {
+                return Ok(false);
+            };
+            let ip = info.connection_client_ip().to_string();
+            if let Some(record) = client_map.get_mut(&view.client_id()) {
+                record.set_ip(ip.clone());
+            }
+            let Some(alert) = detector.check(
+                &ip,
+                view.client_unique_identifier(),
+                kstool::time::get_current_second(),
+                client_map
+                    .values()
+                    .filter(|record| !record.server_query())
+                    .map(|record| (record.ip(), record.unique_identifier())),
+            ) else {
+                return Ok(false);
+            };
+            warn!(
+                "[{}] Client {:?}({}) from {ip}: {alert}",
+                argument.thread_id(),
+                view.client_nickname(),
+                view.client_unique_identifier(),
+            );
+            let kick = detector.policy().kick()
+                && conn
+                    .kick_client(view.client_id(), &detector.policy().reason())
+                    .await
+                    .inspect_err(|e| {
+                        warn!(
+                            "[{}] Unable kick client {}: {e}",
+                            argument.thread_id(),
+                            view.client_id()
+                        )
+                    })
+                    .is_ok();
+            argument
+                .notifier()
+                .send_clone(argument.current_time().to_string(), view, ip, alert, kick)
+                .await
+                .tap_none(|| {
+                    error!(
+                        "[{}] Got error while send data to notifier",
+                        argument.thread_id()
+                    )
+                });
+            Ok(kick)
+        }
+
+        async fn check_raid(
+            argument: &Arguments<'_>,
+            conn: &mut SocketConn,
+            view: &NotifyClientEnterView,
+        ) -> Result {
+            let guard = argument.policy().raid();
+            let status =
+                guard.on_enter(view.total_connections(), kstool::time::get_current_second());
+            if let RaidStatus::Triggered(count) = status {
+                warn!(
+                    "[{}] {count} fresh clients joined in short time, start lockdown",
+                    argument.thread_id()
+                );
+                super::start_lockdown(conn, argument.policy(), argument.thread_id()).await;
+                argument
+                    .notifier()
+                    .send_raid(argument.current_time().to_string(), count)
+                    .await
+                    .tap_none(|| {
+                        error!(
+                            "[{}] Got error while send data to notifier",
+                            argument.thread_id()
+                        )
+                    });
+            }
+            if status == RaidStatus::Normal
+                || view
+                    .server_groups()
+                    .iter()
+                    .any(|group| guard.policy().exempt_group().contains(group))
+            {
+                return Ok(());
+            }
+            if let Some(lobby) = guard.policy().lobby_channel() {
+                conn.move_client(view.client_id(), lobby)
+                    .await
+                    .inspect_err(|e| {
+                        warn!(
+                            "[{}] Unable move client {} to lobby: {e}",
+                            argument.thread_id(),
+                            view.client_id()
+                        )
+                    })
+                    .ok();
+            }
+            Ok(())
+        }
+
+        async fn notify_watchers(
+            argument: &Arguments<'_>,
+            kv_map: &mut Box<dyn KVMap>,
+            view: &NotifyClientEnterView,
+        ) -> Result {
+            let users = watch::watchers(
+                kv_map,
+                argument.server_id(),
+                view.client_unique_identifier(),
+                argument.watch_cooldown(),
+            )
+            .await?;
+            if users.is_empty() {
+                return Ok(());
+            }
+            argument
+                .notifier()
+                .send_presence(argument.current_time().to_string(), view, &users)
+                .await
+                .tap_none(|| {
+                    error!(
+                        "[{}] Got error while send data to notifier",
+                        argument.thread_id()
+                    )
+                });
+            Ok(())
+        }
+
+        async fn welcome(
+            argument: &Arguments<'_>,
+            conn: &mut SocketConn,
+            kv_map: &mut Box<dyn KVMap>,
+            view: &NotifyClientEnterView,
+            online: usize,
+        ) -> Result {
+            let last_seen = welcome::last_seen(
+                kv_map,
+                argument.server_id(),
+                view.client_unique_identifier(),
+            )
+            .await?;
+            welcome::update_last_seen(
+                kv_map,
+                argument.server_id(),
+                view.client_unique_identifier(),
+                kstool::time::get_current_second() as i64,
+            )
+            .await?;
+
+            let Some(template) = argument
+                .welcome()
+                .template(&view.server_groups(), last_seen.is_none())
+            else {
+                return Ok(());
+            };
+            let online = online.to_string();
+            let last_seen = last_seen
+                .map(template::format_timestamp)
+                .unwrap_or_else(|| "never".into());
+            let text = template::render(
+                template,
+                &[
+                    ("nickname", view.client_nickname()),
+                    ("country", view.client_country()),
+                    ("online", &online),
+                    ("last_seen", &last_seen),
+                ],
+            );
+            match argument.welcome().mode() {
+                WelcomeMode::Message => conn.send_text_message(view.client_id(), &text).await?,
+                WelcomeMode::Poke => conn.poke_client(view.client_id(), &text).await?,
+            }
+            trace!(
+                "[{}] Welcome message sent to {}",
+                argument.thread_id(),
+                view.client_id()
+            );
+            Ok(())
+        }
+
+        /// Query IP address of client if it is unknown
+        async fn query_ip(
+            argument: &Arguments<'_>,
+            conn: &mut SocketConn,
+            client_id: i64,
+            client_map: &mut HashMap<i64, ClientRecord>,
+        ) {
+            let Some(record) = client_map
+                .get_mut(&client_id)
+                .filter(|record| record.ip().is_empty())
+            else {
+                return;
+            };
+            match conn.query_client_info(client_id).await {
+                Ok(Some(info)) => record.set_ip(info.connection_client_ip().to_string()),
+                Ok(None) => {}
+                Err(e) => error!(
+                    "[{}] Unable query client information: {e:?}",
+                    argument.thread_id()
+                ),
+            }
+        }
+
         pub(super) async fn user_enter(
             line: &str,
             argument: &Arguments<'_>,
-            client_map: &mut HashMap<i64, (String, bool)>,
+            client_map: &mut HashMap<i64, ClientRecord>,
+            conn: &mut SocketConn,
+            kv_map: &mut Box<dyn KVMap>,
         ) -> Result {
             let view = NotifyClientEnterView::from_query(line)
                 .map_err(|e| anyhow!("Got error while deserialize enter view: {e:?}"))?;
@@ -105,11 +541,54 @@ mod processor {
                     .any(|element| element.eq(view.client_unique_identifier()));
             client_map.insert(
                 view.client_id(),
-                (view.client_nickname().to_string(), is_server_query),
+                ClientRecord::from_enter(&view, is_server_query),
             );
             if is_server_query {
                 return Ok(());
             }
+            if Self::check_nickname(
+                argument,
+                conn,
+                view.client_id(),
+                view.client_unique_identifier(),
+                view.client_nickname(),
+            )
+            .await?
+                || Self::check_country(argument, conn, &view).await?
+                || Self::check_clone(argument, conn, &view, client_map).await?
+            {
+                return Ok(());
+            }
+            Self::check_raid(argument, conn, &view).await?;
+            let event = if argument.tracker_controller().enabled() {
+                Self::query_ip(argument, conn, view.client_id(), client_map).await;
+                client_map.get(&view.client_id()).map(|record| {
+                    record
+                        .tracker_event(EventType::Enter, view.client_id())
+                        .with_channel(view.channel_id())
+                })
+            } else {
+                None
+            };
+            if argument.welcome().enable() {
+                let online = client_map
+                    .values()
+                    .filter(|record| !record.server_query())
+                    .count();
+                Self::welcome(argument, conn, kv_map, &view, online)
+                    .await
+                    .inspect_err(|e| {
+                        error!(
+                            "[{}] Unable send welcome message: {e:?}",
+                            argument.thread_id()
+                        )
+                    })
+                    .ok();
+            }
+            Self::notify_watchers(argument, kv_map, &view)
+                .await
+                .inspect_err(|e| error!("[{}] Unable notify watchers: {e:?}", argument.thread_id()))
+                .ok();
             tokio::join!(
                 argument
                     .monitor_channel()
@@ -122,26 +601,22 @@ mod processor {
                         })
                     }),
                 argument
-                    .telegram_sender()
+                    .notifier()
                     .send_enter(argument.current_time().to_string(), &view)
                     .map(|result| result.tap_none(|| error!(
-                        "[{}] Got error while send data to telegram",
+                        "[{}] Got error while send data to notifier",
                         argument.thread_id()
                     ))),
                 async {
-                    #[cfg(feature = "tracker")]
-                    argument
-                        .tracker_controller()
-                        .insert(
-                            view.client_id() as i32,
-                            Some(view.client_unique_identifier().to_string()),
-                            Some(view.client_nickname().to_string()),
-                            Some(view.channel_id() as i32),
-                        )
-                        .await
-                        .tap_none(|| {
-                            warn!("[{}] Unable send message to tracker", argument.thread_id())
-                        })
+                    if let Some(event) = event {
+                        argument
+                            .tracker_controller()
+                            .insert(event)
+                            .await
+                            .tap_none(|| {
+                                warn!("[{}] Unable send message to tracker", argument.thread_id())
+                            });
+                    }
                 }
             )
             .0?;
@@ -149,10 +624,41 @@ mod processor {
             Ok(())
         }
 
+        pub(super) async fn user_updated(
+            line: &str,
+            argument: &Arguments<'_>,
+            client_map: &mut HashMap<i64, ClientRecord>,
+            conn: &mut SocketConn,
+        ) -> Result {
+            let view = NotifyClientUpdated::from_query(line)
+                .map_err(|e| anyhow!("Got error while deserialize updated view: {e:?}"))?;
+            let Some(nickname) = view.client_nickname() else {
+                return Ok(());
+            };
+            let Some(record) = client_map.get_mut(&view.client_id()) else {
+                return Ok(());
+            };
+            if record.server_query() || record.nickname().eq(nickname) {
+                return Ok(());
+            }
+            record.set_nickname(nickname.to_string());
+            let unique_identifier = record.unique_identifier().to_string();
+            Self::check_nickname(
+                argument,
+                conn,
+                view.client_id(),
+                &unique_identifier,
+                nickname,
+            )
+            .await
+            .map(|_| ())
+        }
+
         pub(super) async fn user_left(
             line: &str,
             argument: &Arguments<'_>,
-            client_map: &mut HashMap<i64, (String, bool)>,
+            client_map: &mut HashMap<i64, ClientRecord>,
+            kv_map: &mut Box<dyn KVMap>,
         ) -> Result {
             let view = NotifyClientLeftView::from_query(line)
                 .map_err(|e| anyhow!("Got error while deserialize left view: {e:?}"))?;
@@ -164,31 +670,69 @@ mod processor {
                 );
                 return Ok(());
             }
-            let nickname = client_map.get(&view.client_id()).unwrap();
-            if nickname.1 {
+            let record = client_map.get_mut(&view.client_id()).unwrap();
+            if record.server_query() {
                 return Ok(());
             }
+            if argument.rank().enable() {
+                let seconds = record.take_online(kstool::time::get_current_second());
+                rank::add_online_time(
+                    kv_map,
+                    argument.server_id(),
+                    record.unique_identifier(),
+                    seconds,
+                )
+                .await
+                .inspect_err(|e| {
+                    error!(
+                        "[{}] Unable update online time: {e:?}",
+                        argument.thread_id()
+                    )
+                })
+                .ok();
+            }
+            if argument.welcome().enable() {
+                welcome::update_last_seen(
+                    kv_map,
+                    argument.server_id(),
+                    record.unique_identifier(),
+                    kstool::time::get_current_second() as i64,
+                )
+                .await
+                .inspect_err(|e| {
+                    error!("[{}] Unable update last seen: {e:?}", argument.thread_id())
+                })
+                .ok();
+            }
             argument
-                .telegram_sender()
+                .notifier()
                 .send_left(
                     argument.current_time().to_string(),
                     &view,
-                    nickname.0.clone(),
+                    record.nickname().to_string(),
+                    record.unique_identifier().to_string(),
+                    record.country().to_string(),
+                    (
+                        record.server_groups().to_vec(),
+                        record.session(kstool::time::get_current_second()),
+                    ),
                 )
                 .await
                 .tap_none(|| {
                     error!(
-                        "[{}] Got error while send data to telegram",
+                        "[{}] Got error while send data to notifier",
                         argument.thread_id()
                     )
                 });
             argument
                 .tracker_controller()
                 .insert(
-                    view.client_id() as i32,
-                    None,
-                    Some(nickname.0.clone()),
-                    None,
+                    record
+                        .tracker_event(
+                            EventType::from_left_reason(view.reason_id()),
+                            view.client_id(),
+                        )
+                        .with_reason(view.reason()),
                 )
                 .await
                 .tap_none(|| warn!("[{}] Unable send message to tracker", argument.thread_id()));
@@ -196,7 +740,11 @@ mod processor {
             Ok(())
         }
 
-        pub(super) async fn user_move(line: &str, argument: &Arguments<'_>) -> Result {
+        pub(super) async fn user_move(
+            line: &str,
+            argument: &Arguments<'_>,
+            client_map: &HashMap<i64, ClientRecord>,
+        ) -> Result {
             let view = NotifyClientMovedView::from_query(line)
                 .map_err(|e| anyhow!("Got error while deserialize moved view: {e:?}"))?;
             argument
@@ -208,25 +756,142 @@ mod processor {
                         trace!("[{}] Notify auto channel thread", argument.thread_id())
                     }
                 })?;
-            #[cfg(feature = "tracker")]
+            let Some(record) = client_map
+                .get(&view.client_id())
+                .filter(|record| !record.server_query())
+            else {
+                return Ok(());
+            };
             argument
                 .tracker_controller()
                 .insert(
-                    view.client_id() as i32,
-                    Some(view.invoker_uid().to_string()),
-                    Some(view.invoker_name().to_string()),
-                    Some(view.channel_id() as i32),
+                    record
+                        .tracker_event(EventType::Move, view.client_id())
+                        .with_channel(view.channel_id()),
                 )
                 .await
                 .tap_none(|| warn!("[{}] Unable send message to tracker", argument.thread_id()));
             Ok(())
         }
 
-        pub(super) async fn user_text(line: &str, argument: &Arguments<'_>) -> Result {
+        /// Reply last seen of client which matched `query`
+        async fn seen(
+            view: &NotifyTextMessage,
+            query: &str,
+            argument: &Arguments<'_>,
+            conn: &mut SocketConn,
+        ) -> Result {
+            let reply = match argument.tracker_database() {
+                _ if query.is_empty() => "Usage: !seen <nickname|uid>".to_string(),
+                None => "Tracker is not enabled".to_string(),
+                Some(database) => super::seen_report(database, query, conn)
+                    .await
+                    .unwrap_or_else(|e| {
+                        error!(
+                            "[{}] Unable query tracker database: {e:?}",
+                            argument.thread_id()
+                        );
+                        "Query failed".to_string()
+                    }),
+            };
+            conn.send_text_message(view.invoker_id(), &reply)
+                .await
+                .map(|_| {
+                    trace!(
+                        "[{}] Reply seen {query:?} to {}",
+                        argument.thread_id(),
+                        view.invoker_id()
+                    )
+                })
+                .inspect_err(|e| warn!("[{}] Unable reply seen: {e}", argument.thread_id()))
+                .ok();
+            Ok(())
+        }
+
+        /// Reply online time and rank progress of invoker
+        async fn rank(
+            view: &NotifyTextMessage,
+            argument: &Arguments<'_>,
+            conn: &mut SocketConn,
+            client_map: &mut HashMap<i64, ClientRecord>,
+            kv_map: &mut Box<dyn KVMap>,
+        ) -> Result {
+            let reply = match client_map
+                .get_mut(&view.invoker_id())
+                .filter(|record| !record.server_query())
+            {
+                _ if !argument.rank().enable() => "Rank is not enabled".to_string(),
+                None => return Ok(()),
+                Some(record) => match super::update_rank(
+                    conn,
+                    kv_map,
+                    (argument.rank(), argument.server_id(), argument.thread_id()),
+                    view.invoker_id(),
+                    record,
+                )
+                .await
+                {
+                    Some(total) => rank::progress(argument.rank(), total),
+                    None => "Unable query online time, please try again later".to_string(),
+                },
+            };
+            conn.send_text_message(view.invoker_id(), &reply)
+                .await
+                .inspect_err(|e| warn!("[{}] Unable reply rank: {e}", argument.thread_id()))
+                .ok();
+            Ok(())
+        }
+
+        /// Relay server or channel chat, message sent by observer itself is ignored to prevent loop
+        async fn bridge(view: &NotifyTextMessage, argument: &Arguments<'_>) -> Result {
+            if !argument.bridge().enable()
+                || view.target_mode() != argument.bridge().target_mode()
+                || view.invoker_id() == argument.observer_id()
+            {
+                return Ok(());
+            }
+            argument
+                .notifier()
+                .send_chat(
+                    argument.current_time().to_string(),
+                    view.invoker_name().to_string(),
+                    view.msg().to_string(),
+                )
+                .await
+                .tap_none(|| {
+                    error!(
+                        "[{}] Got error while send data to notifier",
+                        argument.thread_id()
+                    )
+                });
+            Ok(())
+        }
+
+        pub(super) async fn user_text(
+            line: &str,
+            argument: &Arguments<'_>,
+            conn: &mut SocketConn,
+            client_map: &mut HashMap<i64, ClientRecord>,
+            kv_map: &mut Box<dyn KVMap>,
+        ) -> Result {
             let view = NotifyTextMessage::from_query(line)
                 .map_err(|e| anyhow!("Got error while deserialize moved view: {e:?}"))?;
 
-            if !view.msg().eq("!reset") {
+            if view.target_mode() != 1 {
+                return Self::bridge(&view, argument).await;
+            }
+
+            let (command, query) = view
+                .msg()
+                .split_once(char::is_whitespace)
+                .unwrap_or((view.msg(), ""));
+            match command {
+                "!seen" => return Self::seen(&view, query.trim(), argument, conn).await,
+                "!rank" => return Self::rank(&view, argument, conn, client_map, kv_map).await,
+                _ => {}
+            }
+
+            if !view.msg().eq("!reset") || !argument.monitor_channel().valid() {
                 return Ok(());
             }
             argument
@@ -268,29 +933,287 @@ mod processor {
         }
     }
 }
-use crate::telegram::BindTelegramHelper;
+use crate::notifier::NotifierSet;
+use crate::policy::PolicySet;
+use crate::policy::raid::Restore;
+use crate::rank;
+use crate::template;
 use processor::Processor;
 
+const JOIN_POWER: &str = "i_channel_needed_join_power";
+
+/// Apply raid lockdown settings, changed settings are kept in guard to restore when lockdown ends.
+async fn start_lockdown(conn: &mut SocketConn, policy: &PolicySet, thread_id: &str) {
+    let guard = policy.raid();
+    let mut restore = Restore::default();
+    if let Some(password) = guard.policy().password() {
+        match conn.query_server_info().await {
+            // Password can't be read back, keep existing one
+            Ok(server_info) if server_info.has_password() => {
+                warn!("[{thread_id}] Server password is set, keep it during lockdown")
+            }
+            Ok(_) => {
+                restore.password = conn
+                    .edit_server(&[("virtualserver_password", password.to_string())])
+                    .await
+                    .inspect_err(|e| error!("[{thread_id}] Unable set server password: {e}"))
+                    .is_ok()
+            }
+            Err(e) => error!("[{thread_id}] Query server info error: {e}"),
+        }
+    }
+    if let Some(power) = guard.policy().join_power() {
+        let channels = conn
+            .query_channels()
+            .await
+            .inspect_err(|e| error!("[{thread_id}] Query channels error: {e}"))
+            .unwrap_or_default();
+        for channel in channels
+            .iter()
+            .filter(|channel| Some(channel.cid()) != guard.policy().lobby_channel())
+        {
+            let result = match conn
+                .query_channel_permission(channel.cid(), JOIN_POWER)
+                .await
+            {
+                Ok(previous) => conn
+                    .set_channel_permission(channel.cid(), JOIN_POWER, power)
+                    .await
+                    .map(|_| previous),
+                Err(e) => Err(e),
+            };
+            match result {
+                Ok(previous) => restore.join_power.push((channel.cid(), previous)),
+                Err(e) => error!(
+                    "[{thread_id}] Unable set join power of channel {}: {e}",
+                    channel.cid()
+                ),
+            }
+        }
+    }
+    guard.set_restore(restore);
+}
+
+/// End raid lockdown and restore server settings, return `true` if server was in lockdown.
+async fn end_lockdown(conn: &mut SocketConn, policy: &PolicySet, thread_id: &str) -> bool {
+    let Some(restore) = policy.raid().end() else {
+        return false;
+    };
+    if restore.password {
+        conn.edit_server(&[("virtualserver_password", String::new())])
+            .await
+            .inspect_err(|e| error!("[{thread_id}] Unable remove server password: {e}"))
+            .ok();
+    }
+    for (channel_id, previous) in restore.join_power {
+        match previous {
+            Some(value) => {
+                conn.set_channel_permission(channel_id, JOIN_POWER, value)
+                    .await
+            }
+            None => conn.delete_channel_permission(channel_id, JOIN_POWER).await,
+        }
+        .inspect_err(|e| {
+            error!("[{thread_id}] Unable restore join power of channel {channel_id}: {e}")
+        })
+        .ok();
+    }
+    true
+}
+
+/// Execute moderation action on online client, return description for audit log
+async fn moderate(
+    conn: &mut SocketConn,
+    client_map: &HashMap<i64, ClientRecord>,
+    action: &Action,
+) -> anyhow::Result<String> {
+    let resolve = |target: &Target| -> anyhow::Result<(i64, String)> {
+        let client_id = target.resolve(
+            client_map
+                .iter()
+                .filter(|(_, record)| !record.server_query())
+                .map(|(client_id, record)| {
+                    (*client_id, record.nickname(), record.unique_identifier())
+                }),
+        )?;
+        let record = &client_map[&client_id];
+        Ok((
+            client_id,
+            format!(
+                "{}({client_id}, {})",
+                record.nickname(),
+                record.unique_identifier()
+            ),
+        ))
+    };
+    Ok(match action {
+        Action::Kick(target, reason) => {
+            let (client_id, client) = resolve(target)?;
+            conn.kick_client(client_id, reason).await?;
+            format!("kicked {client}: {reason}")
+        }
+        Action::Ban(target, duration, reason) => {
+            let (client_id, client) = resolve(target)?;
+            conn.ban_client(client_id, *duration, reason).await?;
+            if *duration == 0 {
+                format!("banned {client} permanently: {reason}")
+            } else {
+                format!("banned {client} for {duration}s: {reason}")
+            }
+        }
+        Action::Move(target, channel_id) => {
+            let (client_id, client) = resolve(target)?;
+            conn.move_client(client_id, *channel_id).await?;
+            format!("moved {client} to channel {channel_id}")
+        }
+        Action::Poke(target, message) => {
+            let (client_id, client) = resolve(target)?;
+            conn.poke_client(client_id, message).await?;
+            format!("poked {client}: {message}")
+        }
+        Action::Message(target, message) => {
+            let (client_id, client) = resolve(target)?;
+            conn.send_text_message(client_id, message).await?;
+            format!("sent message to {client}: {message}")
+        }
+        Action::Unban(ban_id) => {
+            conn.ban_del(*ban_id).await?;
+            format!("removed ban {ban_id}")
+        }
+    })
+}
+
+#[cfg(feature = "tracker")]
+async fn seen_report(database: &str, query: &str, conn: &mut SocketConn) -> anyhow::Result<String> {
+    use crate::plugins::tracker::stats;
+    /// Maximum matches of `!seen`, keep reply in text message length limit
+    const SEEN_LIMIT: usize = 3;
+
+    let mut result = {
+        let mut database = stats::open(database).await?;
+        stats::seen(&mut database, query, SEEN_LIMIT).await?
+    };
+    if result.is_empty() {
+        return Ok("Client not found".into());
+    }
+    let channels = conn
+        .query_channels()
+        .await
+        .map_err(|e| anyhow!("Query channels error: {e:?}"))?;
+    for seen in &mut result {
+        if let Some(channel) = seen
+            .channel()
+            .and_then(|id| channels.iter().find(|channel| channel.cid() == id))
+        {
+            seen.set_channel_name(channel.channel_name().to_string());
+        }
+    }
+    Ok(result
+        .iter()
+        .map(ToString::to_string)
+        .collect::<Vec<_>>()
+        .join("\n\n"))
+}
+
+#[cfg(not(feature = "tracker"))]
+async fn seen_report(
+    _database: &str,
+    _query: &str,
+    _conn: &mut SocketConn,
+) -> anyhow::Result<String> {
+    Err(anyhow!("Tracker feature is not enabled"))
+}
+
+/// Account online time of client, grant server group and congratulate when reaching new tier
+///
+/// Errors are logged, total online seconds is returned if it was accounted
+async fn update_rank(
+    conn: &mut SocketConn,
+    kv_map: &mut Box<dyn KVMap>,
+    (rank, server_id, thread_id): (&Rank, &str, &str),
+    client_id: i64,
+    record: &mut ClientRecord,
+) -> Option<u64> {
+    let seconds = record.take_online(kstool::time::get_current_second());
+    let total = rank::add_online_time(kv_map, server_id, record.unique_identifier(), seconds)
+        .await
+        .inspect_err(|e| error!("[{thread_id}] Unable update online time: {e:?}"))
+        .ok()?;
+    let Some(tier) = rank.tier(total) else {
+        return Some(total);
+    };
+    match rank::reached(kv_map, server_id, record.unique_identifier()).await {
+        Ok(reached) if reached.is_some_and(|hours| hours >= tier.hours()) => return Some(total),
+        Ok(_) => {}
+        Err(e) => {
+            error!("[{thread_id}] Unable read reached rank: {e:?}");
+            return Some(total);
+        }
+    }
+    if let Err(e) = conn
+        .add_server_group(tier.group_id(), record.database_id())
+        .await
+    {
+        warn!(
+            "[{thread_id}] Unable grant rank {} to {}({}): {e}",
+            tier.name(),
+            record.nickname(),
+            record.unique_identifier()
+        );
+        return Some(total);
+    }
+    rank::set_reached(kv_map, server_id, record.unique_identifier(), tier.hours())
+        .await
+        .inspect_err(|e| error!("[{thread_id}] Unable save reached rank: {e:?}"))
+        .ok();
+    let text = template::render(
+        rank.message(),
+        &[
+            ("nickname", record.nickname()),
+            ("rank", tier.name()),
+            ("hours", &tier.hours().to_string()),
+        ],
+    );
+    conn.send_text_message(client_id, &text)
+        .await
+        .inspect_err(|e| warn!("[{thread_id}] Unable congratulate client {client_id}: {e}"))
+        .ok();
+    info!(
+        "[{thread_id}] {}({}) reached rank {}",
+        record.nickname(),
+        record.unique_identifier(),
+        tier.name()
+    );
+    Some(total)
+}
+
 async fn staff(
     line: &str,
-    client_map: &mut HashMap<i64, (String, bool)>,
+    client_map: &mut HashMap<i64, ClientRecord>,
     conn: &mut SocketConn,
+    kv_map: &mut Box<dyn KVMap>,
     argument: &Arguments<'_>,
 ) -> anyhow::Result<()> {
     if line.starts_with("notifycliententerview") {
-        return Processor::user_enter(line, argument, client_map).await;
+        return Processor::user_enter(line, argument, client_map, conn, kv_map).await;
+    }
+
+    if line.starts_with("notifyclientupdated") && argument.policy().nickname().enabled() {
+        return Processor::user_updated(line, argument, client_map, conn).await;
     }
 
     if line.starts_with("notifyclientleftview") {
-        return Processor::user_left(line, argument, client_map).await;
+        return Processor::user_left(line, argument, client_map, kv_map).await;
     }
 
-    if line.contains("notifyclientmoved") && argument.monitor_channel().valid() {
-        return Processor::user_move(line, argument).await;
+    if line.contains("notifyclientmoved")
+        && (argument.monitor_channel().valid() || argument.tracker_controller().enabled())
+    {
+        return Processor::user_move(line, argument, client_map).await;
     }
 
-    if line.contains("notifytextmessage") && argument.monitor_channel().valid() {
-        return Processor::user_text(line, argument).await;
+    if line.contains("notifytextmessage") {
+        return Processor::user_text(line, argument, conn, client_map, kv_map).await;
     }
     if line.starts_with("banid") {
         return Processor::ban_list(line, argument, conn).await;
@@ -304,19 +1227,21 @@ async fn staff(
 pub async fn observer_thread(
     mut conn: SocketConn,
     mut recv: mpsc::Receiver<PrivateMessageRequest>,
-    telegram_sender: BindTelegramHelper,
+    notifier: NotifierSet,
     monitor_channel: AutoChannelInstance,
     config: Config,
-    tracker_controller: Box<dyn EventHelperTrait + Send + Sync>,
+    (tracker_controller, mut kv_map): (Box<dyn EventHelperTrait + Send + Sync>, Box<dyn KVMap>),
     thread_id: String,
 ) -> anyhow::Result<()> {
     let interval = config.misc().interval();
     let whitelist_ip = config.server().whitelist_ip();
     let ignore_list = config.server().ignore_user_name();
+    let policy = PolicySet::new(&config)?;
     info!(
-        "[{thread_id}], interval: {interval}, ban list checker: {}, mute porter: {}",
+        "[{thread_id}], interval: {interval}, ban list checker: {}, mute porter: {}, nickname policy: {}",
         !whitelist_ip.is_empty(),
-        config.mute_porter().enable()
+        config.mute_porter().enable(),
+        policy.nickname().enabled(),
     );
 
     conn.change_nickname(
@@ -325,7 +1250,12 @@ pub async fn observer_thread(
     .await
     .map_err(|e| anyhow!("Got error while change nickname: {e:?}"))?;
 
-    let mut client_map: HashMap<i64, (String, bool)> = HashMap::new();
+    let server_info = conn
+        .query_server_info()
+        .await
+        .map_err(|e| anyhow!("Query server info error: {e:?}"))?;
+
+    let mut client_map: HashMap<i64, ClientRecord> = HashMap::new();
 
     for client in conn
         .query_clients()
@@ -336,37 +1266,82 @@ pub async fn observer_thread(
             continue;
         }
 
-        client_map.insert(
-            client.client_id(),
-            (client.client_nickname().to_string(), false),
-        );
+        let record = ClientRecord::from_client(&client);
         tracker_controller
             .insert(
-                client.client_id() as i32,
-                Some(client.client_database_id().to_string()),
-                Some(client.client_nickname().to_string()),
-                Some(client.channel_id() as i32),
+                record
+                    .tracker_event(EventType::Enter, client.client_id())
+                    .with_channel(client.channel_id()),
             )
             .await
             .tap_none(|| warn!("[{thread_id}] Unable send insert request"));
+        client_map.insert(client.client_id(), record);
+    }
+
+    let whoami = conn
+        .who_am_i()
+        .await
+        .map_err(|e| anyhow!("Got error while query observer self: {e:?}"))?;
+
+    let bridge = config
+        .bridge()
+        .enable()
+        .then(|| config.bridge().target_mode());
+    if let Some(channel) = config.bridge().channel().filter(|_| bridge.is_some()) {
+        // Observer may already in this channel, so only warn if move failed
+        if let Err(e) = conn.move_client(whoami.client_id(), channel).await {
+            warn!("[{thread_id}] Unable move observer to bridge channel {channel}: {e:?}");
+        }
     }
 
     // TODO: Check if this is necessary
-    conn.register_observer_events()
+    conn.register_observer_events(bridge)
         .await
         .map_err(|e| anyhow!("Got error while register events: {e:?}"))?;
 
-    if monitor_channel.valid() {
+    // Client update notification is only sent to channel event subscriber
+    if monitor_channel.valid() || policy.nickname().enabled() {
         conn.register_channel_events()
             .await
-            .map_err(|e| anyhow!("Register monitor channel error: {e:?}"))?;
+            .map_err(|e| anyhow!("Register channel events error: {e:?}"))?;
     }
 
     if !whitelist_ip.is_empty() {
         conn.write_data("banlist\n\r").await.ok();
     }
 
+    let mut next_rank_check = kstool::time::get_current_second() + config.rank().check_interval();
+
     loop {
+        if config.rank().enable() && kstool::time::get_current_second() >= next_rank_check {
+            next_rank_check = kstool::time::get_current_second() + config.rank().check_interval();
+            for (client_id, record) in client_map
+                .iter_mut()
+                .filter(|(_, record)| !record.server_query())
+            {
+                update_rank(
+                    &mut conn,
+                    &mut kv_map,
+                    (
+                        config.rank(),
+                        server_info.virtual_server_unique_identifier(),
+                        &thread_id,
+                    ),
+                    *client_id,
+                    record,
+                )
+                .await;
+            }
+        }
+        if policy.raid().expired(kstool::time::get_current_second())
+            && end_lockdown(&mut conn, &policy, &thread_id).await
+        {
+            info!("[{thread_id}] Lockdown expired");
+            notifier
+                .send_lockdown_end(chrono::Local::now().format("%Y-%m-%d %H:%M:%S").to_string())
+                .await
+                .tap_none(|| error!("[{thread_id}] Got error while send data to notifier"));
+        }
         tokio::select! {
             message = tokio::time::timeout(Duration::from_millis(interval), recv.recv()) => {
                 let message = match message {
@@ -376,12 +1351,69 @@ pub async fn observer_thread(
                 match message {
                     PrivateMessageRequest::Message(client_id, message) => {
 
-                        conn.send_text_message_unchecked(client_id, &message)
+                        conn.send_text_message(client_id, &message)
                         .await
                         .map(|_| trace!("[{thread_id}] Send message to {client_id}"))
-                        .map_err(|e| {
-                            anyhow!("[{thread_id}] Got error while send message to {client_id} {e:?}")
-                        })?;
+                        .inspect_err(|e| {
+                            warn!("[{thread_id}] Got error while send message to {client_id} {e:?}")
+                        })
+                        .ok();
+                        continue
+                    }
+                    PrivateMessageRequest::Bridge(message) => {
+                        let target = config
+                            .bridge()
+                            .channel()
+                            .unwrap_or_else(|| config.server().server_id());
+                        conn.send_chat_message(config.bridge().target_mode(), target, &message)
+                            .await
+                            .inspect_err(|e| warn!("[{thread_id}] Unable relay message: {e}"))
+                            .ok();
+                        continue
+                    }
+                    PrivateMessageRequest::Moderate(invoker, action, sender) => {
+                        let result = moderate(&mut conn, &client_map, &action).await;
+                        match &result {
+                            Ok(description) => {
+                                info!("[{thread_id}] {invoker} {description}");
+                                notifier
+                                    .send_moderation(
+                                        chrono::Local::now().format("%Y-%m-%d %H:%M:%S").to_string(),
+                                        invoker,
+                                        description.clone(),
+                                    )
+                                    .await
+                                    .tap_none(|| error!("[{thread_id}] Got error while send data to notifier"));
+                            }
+                            Err(e) => warn!("[{thread_id}] Unable {action} requested by {invoker}: {e}"),
+                        }
+                        sender.send(result).ok();
+                        continue
+                    }
+                    PrivateMessageRequest::Watch(user_id, request, sender) => {
+                        let result = watch::handle(
+                            &mut kv_map,
+                            server_info.virtual_server_unique_identifier(),
+                            client_map
+                                .iter()
+                                .filter(|(_, record)| !record.server_query())
+                                .map(|(client_id, record)| {
+                                    (*client_id, record.nickname(), record.unique_identifier())
+                                }),
+                            user_id,
+                            &request,
+                        )
+                        .await;
+                        if let Err(e) = &result {
+                            warn!("[{thread_id}] Unable handle {request:?} of telegram user {user_id}: {e}");
+                        }
+                        sender.send(result).ok();
+                        continue
+                    }
+                    PrivateMessageRequest::EndLockdown(sender) => {
+                        let ended = end_lockdown(&mut conn, &policy, &thread_id).await;
+                        info!("[{thread_id}] End lockdown by request: {ended}");
+                        sender.send(ended).ok();
                         continue
                     }
                     PrivateMessageRequest::KeepAlive => {
@@ -414,22 +1446,44 @@ pub async fn observer_thread(
         }
         let data = data.unwrap();
         let current_time = chrono::Local::now().format("%Y-%m-%d %H:%M:%S").to_string();
-        let arguments = Arguments::new(
-            &ignore_list,
-            &monitor_channel,
-            &whitelist_ip,
-            &telegram_sender,
-            &current_time,
-            tracker_controller.as_ref(),
-            &thread_id,
-        );
+        let arguments = Arguments {
+            ignore_list: &ignore_list,
+            monitor_channel: &monitor_channel,
+            whitelist_ip: &whitelist_ip,
+            notifier: &notifier,
+            current_time: &current_time,
+            tracker_controller: tracker_controller.as_ref(),
+            thread_id: &thread_id,
+            policy: &policy,
+            welcome: config.welcome(),
+            rank: config.rank(),
+            server_id: server_info.virtual_server_unique_identifier(),
+            #[cfg(feature = "tracker")]
+            tracker_database: config.server().track_channel_member().as_deref(),
+            #[cfg(not(feature = "tracker"))]
+            tracker_database: None,
+            bridge: config.bridge(),
+            observer_id: whoami.client_id(),
+            watch_cooldown: config.telegram().watch_cooldown(),
+        };
         for line in data.lines().map(|line| line.trim()) {
             if line.is_empty() {
                 continue;
             }
             trace!("[{thread_id}] {line}",);
 
-            staff(line, &mut client_map, &mut conn, &arguments).await?;
+            staff(line, &mut client_map, &mut conn, &mut kv_map, &arguments).await?;
+        }
+        // Handle notifications which received while executing command
+        loop {
+            let pending = conn.take_notifies();
+            if pending.is_empty() {
+                break;
+            }
+            for line in &pending {
+                trace!("[{thread_id}] {line}",);
+                staff(line, &mut client_map, &mut conn, &mut kv_map, &arguments).await?;
+            }
         }
         //trace!("message loop end");
     }
@@ -442,3 +1496,144 @@ pub async fn observer_thread(
 
     Ok(())
 }
+
+#[cfg(test)]
+mod test {
+    use super::{Arguments, ClientRecord, staff};
+    use crate::auto_channel::AutoChannelInstance;
+    use crate::configure::Config;
+    use crate::notifier::NotifierSet;
+    use crate::plugins::KVMap;
+    use crate::policy::PolicySet;
+    use crate::socketlib::SocketConn;
+    use crate::types::{Client, EventHelperTrait, FromQueryString, TrackerEvent};
+    use async_trait::async_trait;
+    use std::collections::HashMap;
+    use tokio::io::{AsyncReadExt, AsyncWriteExt};
+    use tokio::net::TcpListener;
+    use tokio::sync::mpsc;
+
+    #[derive(Default)]
+    struct MemoryMap(HashMap<String, String>);
+
+    #[async_trait]
+    impl KVMap for MemoryMap {
+        async fn set(&mut self, key: String, value: String) -> anyhow::Result<Option<()>> {
+            Ok(self.0.insert(key, value).map(|_| ()))
+        }
+
+        async fn delete(&mut self, key: String) -> anyhow::Result<()> {
+            self.0.remove(&key);
+            Ok(())
+        }
+
+        async fn get(&mut self, key: String) -> anyhow::Result<Option<String>> {
+            Ok(self.0.get(&key).cloned())
+        }
+    }
+
+    struct NoTracker;
+
+    #[async_trait]
+    impl EventHelperTrait for NoTracker {
+        fn enabled(&self) -> bool {
+            false
+        }
+
+        async fn insert(&self, _event: TrackerEvent) -> Option<()> {
+            Some(())
+        }
+
+        async fn terminate(&self) -> Option<()> {
+            Some(())
+        }
+    }
+
+    /// Fake ServerQuery which acknowledges every command and reports received commands
+    async fn fake_server() -> (u16, mpsc::UnboundedReceiver<String>) {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let port = listener.local_addr().unwrap().port();
+        let (sender, receiver) = mpsc::unbounded_channel();
+        tokio::spawn(async move {
+            let (mut stream, _) = listener.accept().await.unwrap();
+            stream.write_all(b"error id=0 msg=ok\n\r").await.unwrap();
+            let mut buffer = [0u8; 512];
+            while let Ok(size @ 1..) = stream.read(&mut buffer).await {
+                sender
+                    .send(String::from_utf8_lossy(&buffer[..size]).to_string())
+                    .ok();
+                stream.write_all(b"error id=0 msg=ok\n\r").await.unwrap();
+            }
+        });
+        (port, receiver)
+    }
+
+    #[tokio::test]
+    async fn test_nickname_updated() {
+        let config: Config = toml::from_str(
+            r#"
+            [server]
+            channel-id = 1
+            privilege-group-id = 5
+            [misc]
+            [raw-query]
+            user = "serveradmin"
+            password = ""
+            [nickname-policy]
+            enable = true
+            deny = ["(?i)admin"]
+            action = "kick"
+            [telegram]
+            api-key = ""
+            target = 0
+            "#,
+        )
+        .unwrap();
+        let policy = PolicySet::new(&config).unwrap();
+        let monitor_channel = AutoChannelInstance::new(vec![], None);
+        let notifier = NotifierSet::new(vec![]);
+        let argument = Arguments {
+            ignore_list: &[],
+            monitor_channel: &monitor_channel,
+            whitelist_ip: &[],
+            notifier: &notifier,
+            current_time: "",
+            tracker_controller: &NoTracker,
+            thread_id: "test",
+            policy: &policy,
+            welcome: config.welcome(),
+            rank: config.rank(),
+            server_id: "",
+            tracker_database: None,
+            bridge: config.bridge(),
+            observer_id: 1,
+            watch_cooldown: 0,
+        };
+
+        let (port, mut commands) = fake_server().await;
+        let mut conn = SocketConn::connect("127.0.0.1", port).await.unwrap();
+        let mut kv_map: Box<dyn KVMap> = Box::new(MemoryMap::default());
+        let client = Client::from_query(
+            "clid=5 cid=1 client_database_id=2 client_nickname=bob client_type=0",
+        )
+        .unwrap();
+        let mut client_map = HashMap::from([(5, ClientRecord::from_client(&client))]);
+
+        staff(
+            "notifyclientupdated clid=5 client_nickname=Admin",
+            &mut client_map,
+            &mut conn,
+            &mut kv_map,
+            &argument,
+        )
+        .await
+        .unwrap();
+        assert!(
+            commands
+                .recv()
+                .await
+                .unwrap()
+                .starts_with("clientkick clid=5 ")
+        );
+    }
+}
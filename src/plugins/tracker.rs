@@ -1,11 +1,9 @@
 pub type DatabaseResult<T> = Result<T, sqlx::Error>;
 
 pub mod v1 {
-    use super::DatabaseResult;
-    use sqlx::SqliteConnection;
-
     pub const VERSION: &str = "1";
 
+    #[cfg(test)]
     pub(super) const CREATE_TABLE: &str = r#"
         CREATE TABLE "users" (
             "timestamp"	INTEGER NOT NULL,
@@ -20,40 +18,289 @@ pub mod v1 {
             "value" TEXT
         );
         "#;
+}
+
+pub mod v2 {
+    use super::DatabaseResult;
+    use crate::types::TrackerEvent;
+    use sqlx::SqliteConnection;
+
+    pub const VERSION: &str = "2";
+
+    pub(super) const CREATE_TABLE: &str = r#"
+        CREATE TABLE "events" (
+            "timestamp"	INTEGER NOT NULL,
+            "event" TEXT NOT NULL,
+            "client_id" INTEGER NOT NULL,
+            "database_id" INTEGER,
+            "unique_identifier" TEXT,
+            "nickname" TEXT,
+            "channel" INTEGER,
+            "ip" TEXT,
+            "country" TEXT,
+            "reason" TEXT
+        );
+
+        CREATE TABLE "sessions" (
+            "id" INTEGER PRIMARY KEY AUTOINCREMENT,
+            "client_id" INTEGER NOT NULL,
+            "database_id" INTEGER,
+            "unique_identifier" TEXT,
+            "nickname" TEXT,
+            "ip" TEXT,
+            "country" TEXT,
+            "join_time" INTEGER NOT NULL,
+            "leave_time" INTEGER,
+            "leave_reason" TEXT
+        );
+
+        CREATE INDEX "events_timestamp" ON "events" ("timestamp");
+        CREATE INDEX "sessions_unique_identifier" ON "sessions" ("unique_identifier");
+        "#;
+
+    /// Move loosely-typed rows of v1 `users` table to `events` table and rebuild sessions, should
+    /// be executed after [`CREATE_TABLE`].
+    ///
+    /// In v1, row with NULL channel is leave event, and `id` column contains database id (on
+    /// observer start up) or unique identifier (on client enter or move). Row with unique
+    /// identifier following another row with channel of same client is move event, unless client
+    /// id is reused by another client after missed leave. Session is closed by next enter or leave
+    /// of same client id.
+    pub(super) const MIGRATE_FROM_V1: &str = r#"
+        INSERT INTO "events" ("timestamp", "event", "client_id", "database_id", "unique_identifier", "nickname", "channel")
+            SELECT "timestamp",
+                CASE
+                    WHEN "channel" IS NULL THEN 'leave'
+                    WHEN "id" NOT GLOB '*[^0-9]*' AND "id" <> '' THEN 'enter'
+                    WHEN LAG("channel") OVER "client" IS NULL THEN 'enter'
+                    WHEN LAG("id") OVER "client" GLOB '*[^0-9]*' AND LAG("id") OVER "client" <> "id" THEN 'enter'
+                    ELSE 'move'
+                END,
+                "client_id",
+                CASE WHEN "id" NOT GLOB '*[^0-9]*' AND "id" <> '' THEN CAST("id" AS INTEGER) END,
+                CASE WHEN "id" GLOB '*[^0-9]*' THEN "id" END,
+                "nickname",
+                "channel"
+            FROM "users"
+            WINDOW "client" AS (PARTITION BY "client_id" ORDER BY "timestamp", "rowid")
+            ORDER BY "timestamp", "rowid";
+
+        INSERT INTO "sessions" ("client_id", "database_id", "unique_identifier", "nickname", "join_time", "leave_time", "leave_reason")
+            SELECT "client_id", "database_id", "unique_identifier", "nickname", "timestamp", "leave_time",
+                CASE "next_event" WHEN 'leave' THEN 'leave' WHEN 'enter' THEN 'interrupted' END
+            FROM (
+                SELECT *,
+                    LEAD("timestamp") OVER "client" AS "leave_time",
+                    LEAD("event") OVER "client" AS "next_event"
+                FROM "events"
+                WHERE "event" IN ('enter', 'leave')
+                WINDOW "client" AS (PARTITION BY "client_id" ORDER BY "timestamp", "rowid")
+            )
+            WHERE "event" = 'enter';
+
+        DROP TABLE "users";
+        "#;
 
     pub(super) async fn insert(
         conn: &mut SqliteConnection,
-        client_id: i32,
-        user_id: Option<String>,
-        nickname: Option<String>,
-        channel: Option<i32>,
+        event: &TrackerEvent,
     ) -> DatabaseResult<()> {
-        sqlx::query(r#"INSERT INTO "users" VALUES (?, ?, ?, ?, ?)"#)
-            .bind(kstool::time::get_current_second() as i64)
-            .bind(client_id)
-            .bind(user_id)
-            .bind(nickname)
-            .bind(channel)
+        sqlx::query(r#"INSERT INTO "events" VALUES (?, ?, ?, ?, ?, ?, ?, ?, ?, ?)"#)
+            .bind(event.timestamp())
+            .bind(event.event_type().as_str())
+            .bind(event.client_id())
+            .bind(event.database_id())
+            .bind(event.unique_identifier())
+            .bind(event.nickname())
+            .bind(event.channel())
+            .bind(event.ip())
+            .bind(event.country())
+            .bind(event.reason())
+            .execute(&mut *conn)
+            .await?;
+
+        if event.event_type() == crate::types::EventType::Enter {
+            sqlx::query(
+                r#"INSERT INTO "sessions" ("client_id", "database_id", "unique_identifier", "nickname", "ip", "country", "join_time") VALUES (?, ?, ?, ?, ?, ?, ?)"#,
+            )
+            .bind(event.client_id())
+            .bind(event.database_id())
+            .bind(event.unique_identifier())
+            .bind(event.nickname())
+            .bind(event.ip())
+            .bind(event.country())
+            .bind(event.timestamp())
             .execute(conn)
-            .await
-            .map(|_| ())
+            .await?;
+        } else if event.event_type().is_leave() {
+            sqlx::query(
+                r#"UPDATE "sessions" SET "leave_time" = ?, "leave_reason" = ? WHERE "client_id" = ? AND "leave_time" IS NULL"#,
+            )
+            .bind(event.timestamp())
+            .bind(event.reason().unwrap_or(event.event_type().as_str()))
+            .bind(event.client_id())
+            .execute(conn)
+            .await?;
+        }
+        Ok(())
     }
-}
 
-#[allow(unused)]
-async fn update_database_version(conn: &mut SqliteConnection) -> DatabaseResult<()> {
-    sqlx::query(r#"UPDATE "meta" SET "value" = ? WHERE "key" = 'version'"#)
-        .bind(VERSION)
+    /// Close sessions which not closed in last run, use last event time as leave time
+    pub(super) async fn close_sessions(
+        conn: &mut SqliteConnection,
+        reason: &str,
+    ) -> DatabaseResult<()> {
+        sqlx::query(
+            r#"UPDATE "sessions" SET "leave_time" = MAX("join_time", COALESCE((SELECT MAX("timestamp") FROM "events"), "join_time")), "leave_reason" = ? WHERE "leave_time" IS NULL"#,
+        )
+        .bind(reason)
         .execute(conn)
         .await
         .map(|_| ())
+    }
 }
 
-async fn create_new_database(conn: &mut SqliteConnection) -> DatabaseResult<()> {
-    sqlx::query(current::CREATE_TABLE)
-        .execute(conn)
+pub mod v3 {
+    use super::v2;
+
+    pub const VERSION: &str = "3";
+
+    /// Rollup of events which pruned by retention
+    pub(super) const CREATE_ROLLUP_TABLE: &str = r#"
+        CREATE TABLE "hourly" (
+            "hour" INTEGER PRIMARY KEY,
+            "enters" INTEGER NOT NULL DEFAULT 0,
+            "leaves" INTEGER NOT NULL DEFAULT 0,
+            "moves" INTEGER NOT NULL DEFAULT 0,
+            "clients" INTEGER NOT NULL DEFAULT 0
+        );
+        "#;
+
+    pub(super) const CREATE_TABLE: &[&str] = &[v2::CREATE_TABLE, CREATE_ROLLUP_TABLE];
+
+    pub(super) use v2::{close_sessions, insert};
+}
+
+pub mod v4 {
+    use super::v3;
+
+    pub const VERSION: &str = "4";
+
+    /// Speed up last seen and nickname history lookup
+    pub(super) const CREATE_INDEX: &str = r#"
+        CREATE INDEX "events_unique_identifier" ON "events" ("unique_identifier", "timestamp");
+        "#;
+
+    pub(super) const CREATE_TABLE: &[&str] =
+        &[v3::CREATE_TABLE[0], v3::CREATE_TABLE[1], CREATE_INDEX];
+
+    pub(super) use v3::{close_sessions, insert};
+}
+
+pub mod migration {
+    use super::{DatabaseResult, v1, v2, v3, v4};
+    use anyhow::anyhow;
+    use sqlx::{Connection, SqliteConnection};
+
+    /// Statements to upgrade database from previous version to `version`
+    pub(super) struct Migration {
+        version: &'static str,
+        statements: &'static [&'static str],
+    }
+
+    /// Migrations ordered by version, last one should be [`super::VERSION`]
+    pub(super) const MIGRATIONS: &[Migration] = &[
+        Migration {
+            version: v2::VERSION,
+            statements: &[v2::CREATE_TABLE, v2::MIGRATE_FROM_V1],
+        },
+        Migration {
+            version: v3::VERSION,
+            statements: &[v3::CREATE_ROLLUP_TABLE],
+        },
+        Migration {
+            version: v4::VERSION,
+            statements: &[v4::CREATE_INDEX],
+        },
+    ];
+
+    fn parse_version(version: &str) -> anyhow::Result<u32> {
+        version
+            .parse()
+            .map_err(|_| anyhow!("Invalid database version: {version:?}"))
+    }
+
+    async fn update_database_version(
+        conn: &mut SqliteConnection,
+        version: &str,
+    ) -> DatabaseResult<()> {
+        sqlx::query(r#"UPDATE "meta" SET "value" = ? WHERE "key" = 'version'"#)
+            .bind(version)
+            .execute(conn)
+            .await
+            .map(|_| ())
+    }
+
+    pub(super) async fn query_database_version(
+        conn: &mut SqliteConnection,
+    ) -> DatabaseResult<Option<String>> {
+        sqlx::query_as::<_, (Option<String>,)>(
+            r#"SELECT "value" FROM "meta" WHERE "key" = 'version'"#,
+        )
+        .fetch_optional(conn)
         .await
-        .map(|_| ())
+        .map(|row| row.and_then(|(version,)| version))
+    }
+
+    /// Run pending migrations in single transaction, return `true` if database upgraded.
+    ///
+    /// Database created by newer version will be refused.
+    pub async fn migrate(conn: &mut SqliteConnection) -> anyhow::Result<bool> {
+        let version = query_database_version(conn)
+            .await?
+            .ok_or_else(|| anyhow!("Database version not found"))?;
+        let current = parse_version(&version)?;
+        let latest = parse_version(super::VERSION)?;
+        if current > latest {
+            return Err(anyhow!(
+                "Database version {current} is newer than supported version {latest}"
+            ));
+        }
+        if current == latest {
+            return Ok(false);
+        }
+        if current < parse_version(v1::VERSION)? {
+            return Err(anyhow!("Unsupported database version: {current}"));
+        }
+
+        let mut transaction = conn.begin().await?;
+        for migration in MIGRATIONS {
+            if parse_version(migration.version)? <= current {
+                continue;
+            }
+            for statement in migration.statements {
+                sqlx::query(statement).execute(&mut *transaction).await?;
+            }
+            update_database_version(&mut transaction, migration.version).await?;
+        }
+        transaction.commit().await?;
+        Ok(true)
+    }
+}
+
+const CREATE_META_TABLE: &str = r#"
+    CREATE TABLE "meta" (
+        "key" TEXT NOT NULL,
+        "value" TEXT
+    );
+    "#;
+
+async fn create_new_database(conn: &mut SqliteConnection) -> DatabaseResult<()> {
+    sqlx::query(CREATE_META_TABLE).execute(&mut *conn).await?;
+    for statement in current::CREATE_TABLE {
+        sqlx::query(statement).execute(&mut *conn).await?;
+    }
+    Ok(())
 }
 
 async fn insert_database_version(conn: &mut SqliteConnection) -> DatabaseResult<()> {
@@ -74,13 +321,13 @@ async fn check_database(conn: &mut SqliteConnection) -> DatabaseResult<bool> {
 }
 
 pub mod types {
-    use crate::types::EventHelperTrait;
+    use crate::types::{EventHelperTrait, TrackerEvent};
     use async_trait::async_trait;
     use tokio::sync::mpsc;
 
     #[derive(Clone, Debug)]
     pub enum Event {
-        Insert(i32, Option<String>, Option<String>, Option<i32>),
+        Insert(TrackerEvent),
         Terminate,
     }
 
@@ -100,15 +347,12 @@ pub mod types {
 
     #[async_trait]
     impl EventHelperTrait for EventHelper {
-        async fn insert(
-            &self,
-            client_id: i32,
-            user_id: Option<String>,
-            nickname: Option<String>,
-            channel: Option<i32>,
-        ) -> Option<()> {
-            self.send(Event::Insert(client_id, user_id, nickname, channel))
-                .await
+        fn enabled(&self) -> bool {
+            self.sender.is_some()
+        }
+
+        async fn insert(&self, event: TrackerEvent) -> Option<()> {
+            self.send(Event::Insert(event)).await
         }
 
         async fn terminate(&self) -> Option<()> {
@@ -131,61 +375,301 @@ pub mod types {
     }
 }
 
+pub mod retention {
+    use super::DatabaseResult;
+    use crate::configure::config::Tracker;
+    use sqlx::{Connection, SqliteConnection};
+    use std::fmt::{Display, Formatter};
+
+    const ROLLUP_EVENTS: &str = r#"
+        INSERT INTO "hourly" ("hour", "enters", "leaves", "moves", "clients")
+            SELECT "timestamp" / 3600 * 3600 AS "start",
+                SUM("event" = 'enter'),
+                SUM("event" IN ('leave', 'kick', 'ban')),
+                SUM("event" = 'move'),
+                COUNT(DISTINCT "unique_identifier")
+            FROM "events"
+            WHERE "timestamp" < ?
+            GROUP BY "start"
+        ON CONFLICT ("hour") DO UPDATE SET
+            "enters" = "enters" + excluded."enters",
+            "leaves" = "leaves" + excluded."leaves",
+            "moves" = "moves" + excluded."moves",
+            "clients" = MAX("clients", excluded."clients")
+        "#;
+
+    #[derive(Debug, Default)]
+    pub struct Summary {
+        hours: u64,
+        events: u64,
+        sessions: u64,
+        rollups: u64,
+    }
+
+    impl Summary {
+        pub fn removed(&self) -> u64 {
+            self.events + self.sessions + self.rollups
+        }
+    }
+
+    impl Display for Summary {
+        fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+            write!(
+                f,
+                "rolled up {} hours, removed {} events, {} sessions and {} hourly rollups",
+                self.hours, self.events, self.sessions, self.rollups
+            )
+        }
+    }
+
+    /// Roll up and prune expired events, then prune expired rollups
+    pub async fn run(
+        conn: &mut SqliteConnection,
+        option: &Tracker,
+        now: i64,
+    ) -> DatabaseResult<Summary> {
+        let mut summary = Summary::default();
+        if let Some(days) = option.retention() {
+            // Align to hour, so each hour will be rolled up only once
+            let before = (now - days as i64 * 86400) / 3600 * 3600;
+            let mut transaction = conn.begin().await?;
+            summary.hours = sqlx::query(ROLLUP_EVENTS)
+                .bind(before)
+                .execute(&mut *transaction)
+                .await?
+                .rows_affected();
+            summary.events = sqlx::query(r#"DELETE FROM "events" WHERE "timestamp" < ?"#)
+                .bind(before)
+                .execute(&mut *transaction)
+                .await?
+                .rows_affected();
+            summary.sessions = sqlx::query(
+                r#"DELETE FROM "sessions" WHERE "leave_time" IS NOT NULL AND "leave_time" < ?"#,
+            )
+            .bind(before)
+            .execute(&mut *transaction)
+            .await?
+            .rows_affected();
+            transaction.commit().await?;
+        }
+        if let Some(days) = option.rollup_retention() {
+            summary.rollups = sqlx::query(r#"DELETE FROM "hourly" WHERE "hour" < ?"#)
+                .bind(now - days as i64 * 86400)
+                .execute(&mut *conn)
+                .await?
+                .rows_affected();
+        }
+        if summary.removed() > 0 {
+            sqlx::query("VACUUM").execute(conn).await?;
+        }
+        Ok(summary)
+    }
+}
+
 pub mod handler {
     use super::Event;
     use super::types::EventHelper;
-    use super::{DatabaseResult, check_database, create_new_database, insert_database_version};
-    use log::error;
-    use sqlx::sqlite::SqliteConnectOptions;
-    use sqlx::{ConnectOptions, SqliteConnection};
+    use super::{check_database, create_new_database, insert_database_version, migration};
+    use crate::configure::config::Tracker;
+    use crate::types::TrackerEvent;
+    use log::{error, info, trace, warn};
+    use sqlx::sqlite::{SqliteConnectOptions, SqliteJournalMode, SqliteSynchronous};
+    use sqlx::{ConnectOptions, Connection, SqliteConnection};
+    use std::time::Duration;
     use tokio::sync::mpsc;
     use tokio::task::JoinHandle;
 
+    /// Write buffered events in single transaction, buffer will be cleared
+    pub(super) async fn flush(
+        conn: &mut SqliteConnection,
+        buffer: &mut Vec<TrackerEvent>,
+    ) -> super::DatabaseResult<()> {
+        if buffer.is_empty() {
+            return Ok(());
+        }
+        let mut transaction = conn.begin().await?;
+        for event in buffer.iter() {
+            super::current::insert(&mut transaction, event)
+                .await
+                .inspect_err(|e| error!("Unable insert to database: {e:?}"))
+                .ok();
+        }
+        transaction.commit().await?;
+        trace!("Flushed {} events", buffer.len());
+        buffer.clear();
+        Ok(())
+    }
+
+    /// Maximum events kept in buffer while database is unavailable
+    pub(super) const MAX_PENDING: usize = 8192;
+    /// Maximum delay of retry when flush failed
+    const MAX_BACKOFF: Duration = Duration::from_secs(60);
+
+    /// Buffered events with flush deadline, retry with backoff if flush failed
+    #[derive(Debug)]
+    pub(super) struct Pending {
+        buffer: Vec<TrackerEvent>,
+        /// Flush deadline of first buffered event, or next retry
+        deadline: tokio::time::Instant,
+        failures: u32,
+        /// Events dropped since buffer is full
+        dropped: u64,
+    }
+
+    impl Pending {
+        pub(super) fn new(capacity: usize) -> Self {
+            Self {
+                buffer: Vec::with_capacity(capacity),
+                deadline: tokio::time::Instant::now(),
+                failures: 0,
+                dropped: 0,
+            }
+        }
+
+        pub(super) fn len(&self) -> usize {
+            self.buffer.len()
+        }
+
+        pub(super) fn deadline(&self) -> tokio::time::Instant {
+            self.deadline
+        }
+
+        /// Buffer event, return true if buffer reached `flush_size` and should be flushed now
+        pub(super) fn push(
+            &mut self,
+            event: TrackerEvent,
+            flush_interval: Duration,
+            flush_size: usize,
+        ) -> bool {
+            if self.buffer.len() >= MAX_PENDING {
+                if self.dropped == 0 {
+                    warn!("Tracker buffer is full, drop new events until database is available");
+                }
+                self.dropped += 1;
+                return false;
+            }
+            if self.buffer.is_empty() {
+                self.deadline = tokio::time::Instant::now() + flush_interval;
+            }
+            self.buffer.push(event);
+            // Wait for retry deadline if last flush failed
+            self.failures == 0 && self.buffer.len() >= flush_size
+        }
+
+        /// Flush buffer, postpone next retry with exponential backoff if failed
+        pub(super) async fn flush(&mut self, conn: &mut SqliteConnection) {
+            match flush(conn, &mut self.buffer).await {
+                Ok(()) => {
+                    self.failures = 0;
+                    if self.dropped > 0 {
+                        warn!(
+                            "Dropped {} events while tracker buffer is full",
+                            self.dropped
+                        );
+                        self.dropped = 0;
+                    }
+                }
+                Err(e) => {
+                    let delay = Duration::from_secs(1 << self.failures.min(6)).min(MAX_BACKOFF);
+                    self.failures += 1;
+                    self.deadline = tokio::time::Instant::now() + delay;
+                    error!(
+                        "Unable flush {} events, retry after {}s: {e:?}",
+                        self.buffer.len(),
+                        delay.as_secs()
+                    );
+                }
+            }
+        }
+    }
+
     #[derive(Debug)]
     pub struct DatabaseHelper {
         handler: JoinHandle<anyhow::Result<()>>,
     }
 
     impl DatabaseHelper {
-        pub async fn new(filename: Option<String>) -> DatabaseResult<(Self, EventHelper)> {
+        pub async fn new(
+            filename: Option<String>,
+            option: Tracker,
+        ) -> anyhow::Result<(Self, EventHelper)> {
             match filename {
                 None => Self::create_empty().await,
-                Some(filename) => Self::create(filename).await,
+                Some(filename) => Self::create(filename, option).await,
             }
         }
 
-        async fn create(filename: String) -> DatabaseResult<(Self, EventHelper)> {
+        async fn create(filename: String, option: Tracker) -> anyhow::Result<(Self, EventHelper)> {
             let mut conn = SqliteConnectOptions::new()
                 .filename(filename)
                 .create_if_missing(true)
+                .journal_mode(SqliteJournalMode::Wal)
+                .synchronous(SqliteSynchronous::Normal)
                 .connect()
                 .await?;
             if !check_database(&mut conn).await? {
                 create_new_database(&mut conn).await?;
                 insert_database_version(&mut conn).await?;
+            } else if migration::migrate(&mut conn).await? {
+                info!("Tracker database upgraded to version {}", super::VERSION);
             }
+            super::current::close_sessions(&mut conn, "interrupted").await?;
 
             let (sender, receiver) = mpsc::channel(2048);
 
             Ok((
-                tokio::spawn(Self::server(conn, receiver)).into(),
+                tokio::spawn(Self::server(conn, receiver, option)).into(),
                 sender.into(),
             ))
         }
 
+        async fn maintenance(conn: &mut SqliteConnection, option: &Tracker) {
+            match super::retention::run(conn, option, kstool::time::get_current_second() as i64)
+                .await
+            {
+                Ok(summary) if summary.removed() > 0 => {
+                    info!("Tracker maintenance finished: {summary}")
+                }
+                Ok(_) => {}
+                Err(e) => error!("Unable run tracker maintenance: {e:?}"),
+            }
+        }
+
         async fn server(
             mut conn: SqliteConnection,
             mut receiver: mpsc::Receiver<Event>,
+            option: Tracker,
         ) -> anyhow::Result<()> {
-            while let Some(event) = receiver.recv().await {
+            let retention = option.retention().is_some() || option.rollup_retention().is_some();
+            let mut interval =
+                tokio::time::interval(Duration::from_secs(option.maintenance_interval()));
+            let flush_interval = Duration::from_millis(option.flush_interval());
+            let mut pending = Pending::new(option.flush_size());
+            loop {
+                let event = tokio::select! {
+                    event = receiver.recv() => event,
+                    _ = tokio::time::sleep_until(pending.deadline()), if pending.len() > 0 => {
+                        pending.flush(&mut conn).await;
+                        continue;
+                    }
+                    _ = interval.tick(), if retention => {
+                        pending.flush(&mut conn).await;
+                        Self::maintenance(&mut conn, &option).await;
+                        continue;
+                    }
+                };
                 match event {
-                    Event::Insert(client_id, user_id, nickname, channel) => {
-                        super::current::insert(&mut conn, client_id, user_id, nickname, channel)
+                    Some(Event::Insert(event)) => {
+                        if pending.push(event, flush_interval, option.flush_size()) {
+                            pending.flush(&mut conn).await;
+                        }
+                    }
+                    Some(Event::Terminate) | None => {
+                        pending.flush(&mut conn).await;
+                        super::current::close_sessions(&mut conn, "terminated")
                             .await
-                            .inspect_err(|e| error!("Unable insert to database: {e:?}"))
+                            .inspect_err(|e| error!("Unable close sessions: {e:?}"))
                             .ok();
-                    }
-                    Event::Terminate => {
                         break;
                     }
                 }
@@ -193,15 +677,16 @@ pub mod handler {
             Ok(())
         }
 
-        async fn create_empty() -> DatabaseResult<(Self, EventHelper)> {
+        async fn create_empty() -> anyhow::Result<(Self, EventHelper)> {
             Ok((tokio::spawn(async { Ok(()) }).into(), None.into()))
         }
 
         pub async fn safe_new(
             filename: Option<String>,
-            error_handler: fn(&sqlx::Error) -> (),
+            option: Tracker,
+            error_handler: fn(&anyhow::Error) -> (),
         ) -> (Self, EventHelper) {
-            match Self::new(filename).await.inspect_err(error_handler) {
+            match Self::new(filename, option).await.inspect_err(error_handler) {
                 Ok(ret) => ret,
                 Err(_) => Self::create_empty().await.unwrap(),
             }
@@ -219,9 +704,993 @@ pub mod handler {
     }
 }
 
+pub mod stats {
+    use super::{DatabaseResult, VERSION, migration::query_database_version};
+    use crate::policy::nickname::{similarity, skeleton};
+    use crate::template::format_duration;
+    use anyhow::anyhow;
+    use serde::Serialize;
+    use sqlx::sqlite::SqliteConnectOptions;
+    use sqlx::{ConnectOptions, SqliteConnection};
+    use std::collections::HashMap;
+    use std::fmt::{Display, Formatter};
+
+    /// Open tracker database in read only mode
+    pub async fn open(filename: &str) -> anyhow::Result<SqliteConnection> {
+        let mut conn = SqliteConnectOptions::new()
+            .filename(filename)
+            .read_only(true)
+            .connect()
+            .await?;
+        let version = query_database_version(&mut conn).await?;
+        if version.as_deref() != Some(VERSION) {
+            return Err(anyhow!(
+                "Database version {version:?} is not supported, start service to upgrade database"
+            ));
+        }
+        Ok(conn)
+    }
+
+    fn format_time(timestamp: i64) -> String {
+        crate::template::format_timestamp(timestamp)
+    }
+
+    fn escape_csv(s: &str) -> String {
+        if s.contains([',', '"', '\n', '\r']) {
+            format!("\"{}\"", s.replace('"', "\"\""))
+        } else {
+            s.to_string()
+        }
+    }
+
+    pub trait CsvRecord {
+        const HEADER: &'static str;
+        fn to_csv(&self) -> String;
+    }
+
+    #[derive(Clone, Debug, Serialize)]
+    pub struct OnlineTime {
+        unique_identifier: String,
+        nickname: String,
+        seconds: i64,
+        sessions: i64,
+    }
+
+    #[derive(Clone, Debug, Serialize)]
+    pub struct DailySessions {
+        date: String,
+        sessions: i64,
+        clients: i64,
+    }
+
+    #[derive(Clone, Debug, Serialize)]
+    pub struct UserStats {
+        unique_identifier: String,
+        nickname: String,
+        seconds: i64,
+        sessions: i64,
+        first_seen: i64,
+        last_seen: i64,
+        daily: Vec<DailySessions>,
+    }
+
+    #[derive(Clone, Debug, Serialize)]
+    pub struct Peak {
+        timestamp: i64,
+        clients: i64,
+    }
+
+    #[derive(Clone, Debug, Serialize)]
+    pub struct HourlyActivity {
+        hour: i64,
+        enters: i64,
+    }
+
+    #[derive(Clone, Debug, Serialize)]
+    pub struct ChannelOccupancy {
+        channel: i64,
+        seconds: i64,
+    }
+
+    #[derive(Clone, Debug, Serialize)]
+    pub struct Seen {
+        unique_identifier: String,
+        nickname: String,
+        timestamp: i64,
+        event: String,
+        channel: Option<i64>,
+        channel_name: Option<String>,
+        online: bool,
+    }
+
+    impl Seen {
+        pub fn channel(&self) -> Option<i64> {
+            self.channel
+        }
+
+        pub fn set_channel_name(&mut self, channel_name: String) {
+            self.channel_name.replace(channel_name);
+        }
+    }
+
+    #[derive(Clone, Debug, Serialize)]
+    pub struct NameHistory {
+        nickname: String,
+        first_seen: i64,
+        last_seen: i64,
+    }
+
+    impl CsvRecord for OnlineTime {
+        const HEADER: &'static str = "unique_identifier,nickname,seconds,sessions";
+        fn to_csv(&self) -> String {
+            format!(
+                "{},{},{},{}",
+                escape_csv(&self.unique_identifier),
+                escape_csv(&self.nickname),
+                self.seconds,
+                self.sessions
+            )
+        }
+    }
+
+    impl CsvRecord for DailySessions {
+        const HEADER: &'static str = "date,sessions,clients";
+        fn to_csv(&self) -> String {
+            format!("{},{},{}", self.date, self.sessions, self.clients)
+        }
+    }
+
+    impl CsvRecord for Peak {
+        const HEADER: &'static str = "timestamp,clients";
+        fn to_csv(&self) -> String {
+            format!("{},{}", self.timestamp, self.clients)
+        }
+    }
+
+    impl CsvRecord for HourlyActivity {
+        const HEADER: &'static str = "hour,enters";
+        fn to_csv(&self) -> String {
+            format!("{},{}", self.hour, self.enters)
+        }
+    }
+
+    impl CsvRecord for ChannelOccupancy {
+        const HEADER: &'static str = "channel,seconds";
+        fn to_csv(&self) -> String {
+            format!("{},{}", self.channel, self.seconds)
+        }
+    }
+
+    impl Display for OnlineTime {
+        fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+            write!(
+                f,
+                "{} {} ({} sessions)",
+                self.nickname,
+                format_duration(self.seconds),
+                self.sessions
+            )
+        }
+    }
+
+    impl Display for UserStats {
+        fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+            write!(
+                f,
+                "{}({})\nOnline: {}\nSessions: {}\nFirst seen: {}\nLast seen: {}",
+                self.nickname,
+                self.unique_identifier,
+                format_duration(self.seconds),
+                self.sessions,
+                format_time(self.first_seen),
+                format_time(self.last_seen),
+            )?;
+            for day in &self.daily {
+                write!(f, "\n{}: {}", day.date, day.sessions)?;
+            }
+            Ok(())
+        }
+    }
+
+    impl Display for Seen {
+        fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+            write!(
+                f,
+                "{}({})\n{} {}",
+                self.nickname,
+                self.unique_identifier,
+                if self.online {
+                    "Online, last active:"
+                } else {
+                    "Last seen:"
+                },
+                format_time(self.timestamp),
+            )?;
+            match (&self.channel_name, self.channel) {
+                (Some(name), Some(channel)) => write!(f, "\nChannel: {name}({channel})"),
+                (None, Some(channel)) => write!(f, "\nChannel: {channel}"),
+                _ => Ok(()),
+            }
+        }
+    }
+
+    impl Display for NameHistory {
+        fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+            write!(
+                f,
+                "{} ({} ~ {})",
+                self.nickname,
+                format_time(self.first_seen),
+                format_time(self.last_seen)
+            )
+        }
+    }
+
+    impl Display for HourlyActivity {
+        fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+            write!(f, "{:02}:00 ({})", self.hour, self.enters)
+        }
+    }
+
+    impl Display for Peak {
+        fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+            write!(
+                f,
+                "Peak: {} clients at {}",
+                self.clients,
+                format_time(self.timestamp)
+            )
+        }
+    }
+
+    /// Clients with most online time since `since`
+    pub async fn top_online(
+        conn: &mut SqliteConnection,
+        since: i64,
+        now: i64,
+        limit: i64,
+    ) -> DatabaseResult<Vec<OnlineTime>> {
+        // Bare column `nickname` takes value from row of MAX("join_time") in SQLite
+        sqlx::query_as::<_, (String, Option<String>, i64, i64, i64)>(
+            r#"SELECT "unique_identifier", "nickname", MAX("join_time"),
+                SUM(COALESCE("leave_time", ?) - MAX("join_time", ?)) AS "seconds", COUNT(*)
+            FROM "sessions"
+            WHERE "unique_identifier" IS NOT NULL AND COALESCE("leave_time", ?) >= ?
+            GROUP BY "unique_identifier"
+            ORDER BY "seconds" DESC
+            LIMIT ?"#,
+        )
+        .bind(now)
+        .bind(since)
+        .bind(now)
+        .bind(since)
+        .bind(limit)
+        .fetch_all(conn)
+        .await
+        .map(|rows| {
+            rows.into_iter()
+                .map(
+                    |(unique_identifier, nickname, _, seconds, sessions)| OnlineTime {
+                        unique_identifier,
+                        nickname: nickname.unwrap_or_default(),
+                        seconds,
+                        sessions,
+                    },
+                )
+                .collect()
+        })
+    }
+
+    /// Sessions count per day, filter by unique identifier if specified
+    pub async fn sessions_per_day(
+        conn: &mut SqliteConnection,
+        unique_identifier: Option<&str>,
+        since: i64,
+    ) -> DatabaseResult<Vec<DailySessions>> {
+        sqlx::query_as::<_, (String, i64, i64)>(
+            r#"SELECT date("join_time", 'unixepoch', 'localtime') AS "day", COUNT(*), COUNT(DISTINCT "unique_identifier")
+            FROM "sessions"
+            WHERE "join_time" >= ? AND (? IS NULL OR "unique_identifier" = ?)
+            GROUP BY "day"
+            ORDER BY "day""#,
+        )
+        .bind(since)
+        .bind(unique_identifier)
+        .bind(unique_identifier)
+        .fetch_all(conn)
+        .await
+        .map(|rows| {
+            rows.into_iter()
+                .map(|(date, sessions, clients)| DailySessions {
+                    date,
+                    sessions,
+                    clients,
+                })
+                .collect()
+        })
+    }
+
+    pub async fn user_stats(
+        conn: &mut SqliteConnection,
+        unique_identifier: &str,
+        since: i64,
+        now: i64,
+    ) -> DatabaseResult<Option<UserStats>> {
+        let Some((nickname, seconds, sessions, first_seen, last_seen)) =
+            sqlx::query_as::<_, (Option<String>, i64, i64, i64, i64)>(
+                r#"SELECT (
+                        SELECT "nickname" FROM "sessions" WHERE "unique_identifier" = ?
+                        ORDER BY "join_time" DESC LIMIT 1
+                    ), SUM(COALESCE("leave_time", ?) - "join_time"), COUNT(*),
+                    MIN("join_time"), MAX(COALESCE("leave_time", ?))
+                FROM "sessions"
+                WHERE "unique_identifier" = ?
+                HAVING COUNT(*) > 0"#,
+            )
+            .bind(unique_identifier)
+            .bind(now)
+            .bind(now)
+            .bind(unique_identifier)
+            .fetch_optional(&mut *conn)
+            .await?
+        else {
+            return Ok(None);
+        };
+        Ok(Some(UserStats {
+            unique_identifier: unique_identifier.to_string(),
+            nickname: nickname.unwrap_or_default(),
+            seconds,
+            sessions,
+            first_seen,
+            last_seen,
+            daily: sessions_per_day(conn, Some(unique_identifier), since).await?,
+        }))
+    }
+
+    /// Maximum concurrent clients since `since`
+    pub async fn peak(
+        conn: &mut SqliteConnection,
+        since: i64,
+        now: i64,
+    ) -> DatabaseResult<Option<Peak>> {
+        let (mut online,) = sqlx::query_as::<_, (i64,)>(
+            r#"SELECT COUNT(*) FROM "sessions" WHERE "join_time" < ? AND COALESCE("leave_time", ?) >= ?"#,
+        )
+        .bind(since)
+        .bind(now)
+        .bind(since)
+        .fetch_one(&mut *conn)
+        .await?;
+        let changes = sqlx::query_as::<_, (i64, i64)>(
+            r#"SELECT "join_time" AS "time", 1 AS "change" FROM "sessions" WHERE "join_time" >= ?
+            UNION ALL
+            SELECT "leave_time", -1 FROM "sessions" WHERE "leave_time" >= ? AND "join_time" >= ?
+            UNION ALL
+            SELECT "leave_time", -1 FROM "sessions" WHERE "leave_time" >= ? AND "join_time" < ?
+            ORDER BY "time", "change""#,
+        )
+        .bind(since)
+        .bind(since)
+        .bind(since)
+        .bind(since)
+        .bind(since)
+        .fetch_all(conn)
+        .await?;
+
+        let mut peak = (online > 0).then_some(Peak {
+            timestamp: since,
+            clients: online,
+        });
+        for (timestamp, change) in changes {
+            online += change;
+            if peak.as_ref().is_none_or(|peak| online > peak.clients) {
+                peak = Some(Peak {
+                    timestamp,
+                    clients: online,
+                });
+            }
+        }
+        Ok(peak)
+    }
+
+    /// Enter count group by hour of day (local time)
+    ///
+    /// Rolled up hours and remaining raw events never overlap, since retention
+    /// removes every event it rolls up.
+    pub async fn busiest_hours(
+        conn: &mut SqliteConnection,
+        since: i64,
+    ) -> DatabaseResult<Vec<HourlyActivity>> {
+        sqlx::query_as::<_, (i64, i64)>(
+            r#"SELECT CAST(strftime('%H', "timestamp", 'unixepoch', 'localtime') AS INTEGER) AS "hour", SUM("count") AS "enters"
+            FROM (
+                SELECT "hour" AS "timestamp", "enters" AS "count" FROM "hourly"
+                WHERE "hour" >= ? AND "enters" > 0
+                UNION ALL
+                SELECT "timestamp", 1 AS "count" FROM "events"
+                WHERE "event" = 'enter' AND "timestamp" >= ?
+            )
+            GROUP BY "hour"
+            ORDER BY "enters" DESC"#,
+        )
+        .bind(since / 3600 * 3600)
+        .bind(since)
+        .fetch_all(conn)
+        .await
+        .map(|rows| {
+            rows.into_iter()
+                .map(|(hour, enters)| HourlyActivity { hour, enters })
+                .collect()
+        })
+    }
+
+    /// Total seconds clients stayed in each channel
+    pub async fn channel_occupancy(
+        conn: &mut SqliteConnection,
+        since: i64,
+    ) -> DatabaseResult<Vec<ChannelOccupancy>> {
+        sqlx::query_as::<_, (i64, i64)>(
+            r#"SELECT "channel", SUM("next" - "timestamp") AS "seconds"
+            FROM (
+                SELECT "channel", "timestamp",
+                    LEAD("timestamp") OVER (PARTITION BY "client_id" ORDER BY "timestamp", "rowid") AS "next"
+                FROM "events"
+                WHERE "timestamp" >= ?
+            )
+            WHERE "channel" IS NOT NULL AND "next" IS NOT NULL
+            GROUP BY "channel"
+            ORDER BY "seconds" DESC"#,
+        )
+        .bind(since)
+        .fetch_all(conn)
+        .await
+        .map(|rows| {
+            rows.into_iter()
+                .map(|(channel, seconds)| ChannelOccupancy { channel, seconds })
+                .collect()
+        })
+    }
+
+    /// Minimum score of fuzzy nickname matching
+    const FUZZY_THRESHOLD: f64 = 0.6;
+
+    /// Score of nickname against skeleton of query, substring match scores at least `0.9`
+    fn match_score(query: &str, nickname: &str) -> f64 {
+        let nickname = skeleton(nickname);
+        let score = similarity(query, &nickname);
+        if query.chars().count() >= 3 && nickname.contains(query) {
+            score.max(0.9)
+        } else {
+            score
+        }
+    }
+
+    /// Last event of client with its last known channel
+    pub async fn last_seen(
+        conn: &mut SqliteConnection,
+        unique_identifier: &str,
+    ) -> DatabaseResult<Option<Seen>> {
+        sqlx::query_as::<_, (Option<String>, i64, String, Option<i64>, bool)>(
+            r#"SELECT "nickname", "timestamp", "event",
+                (SELECT "channel" FROM "events" WHERE "unique_identifier" = ? AND "channel" IS NOT NULL ORDER BY "timestamp" DESC LIMIT 1),
+                EXISTS(SELECT 1 FROM "sessions" WHERE "unique_identifier" = ? AND "leave_time" IS NULL)
+            FROM "events"
+            WHERE "unique_identifier" = ?
+            ORDER BY "timestamp" DESC, "rowid" DESC
+            LIMIT 1"#,
+        )
+        .bind(unique_identifier)
+        .bind(unique_identifier)
+        .bind(unique_identifier)
+        .fetch_optional(conn)
+        .await
+        .map(|row| {
+            row.map(|(nickname, timestamp, event, channel, online)| Seen {
+                unique_identifier: unique_identifier.to_string(),
+                nickname: nickname.unwrap_or_default(),
+                timestamp,
+                event,
+                channel,
+                channel_name: None,
+                online,
+            })
+        })
+    }
+
+    /// Lookup client by unique identifier, fallback to fuzzy nickname matching,
+    /// best match first.
+    pub async fn seen(
+        conn: &mut SqliteConnection,
+        query: &str,
+        limit: usize,
+    ) -> DatabaseResult<Vec<Seen>> {
+        if let Some(seen) = last_seen(conn, query).await? {
+            return Ok(vec![seen]);
+        }
+        let query = skeleton(query);
+        if query.is_empty() {
+            return Ok(vec![]);
+        }
+        let rows = sqlx::query_as::<_, (String, String)>(
+            r#"SELECT DISTINCT "unique_identifier", "nickname" FROM "events"
+            WHERE "unique_identifier" IS NOT NULL AND "nickname" IS NOT NULL"#,
+        )
+        .fetch_all(&mut *conn)
+        .await?;
+
+        let mut candidates: HashMap<String, f64> = HashMap::new();
+        for (unique_identifier, nickname) in rows {
+            let score = match_score(&query, &nickname);
+            if score < FUZZY_THRESHOLD {
+                continue;
+            }
+            let entry = candidates.entry(unique_identifier).or_default();
+            *entry = entry.max(score);
+        }
+        let mut candidates = candidates.into_iter().collect::<Vec<_>>();
+        candidates.sort_by(|a, b| b.1.total_cmp(&a.1).then_with(|| a.0.cmp(&b.0)));
+
+        let mut result = vec![];
+        for (unique_identifier, _) in candidates.into_iter().take(limit) {
+            if let Some(seen) = last_seen(conn, &unique_identifier).await? {
+                result.push(seen);
+            }
+        }
+        Ok(result)
+    }
+
+    /// Nicknames used by client, most recent first
+    pub async fn names(
+        conn: &mut SqliteConnection,
+        unique_identifier: &str,
+    ) -> DatabaseResult<Vec<NameHistory>> {
+        sqlx::query_as::<_, (String, i64, i64)>(
+            r#"SELECT "nickname", MIN("timestamp"), MAX("timestamp") AS "last_seen"
+            FROM "events"
+            WHERE "unique_identifier" = ? AND "nickname" IS NOT NULL
+            GROUP BY "nickname"
+            ORDER BY "last_seen" DESC"#,
+        )
+        .bind(unique_identifier)
+        .fetch_all(conn)
+        .await
+        .map(|rows| {
+            rows.into_iter()
+                .map(|(nickname, first_seen, last_seen)| NameHistory {
+                    nickname,
+                    first_seen,
+                    last_seen,
+                })
+                .collect()
+        })
+    }
+}
+
 use sqlx::SqliteConnection;
 
 pub use handler::DatabaseHelper;
 pub use types::Event;
-pub use v1 as current;
-pub use v1::VERSION;
+pub use v4 as current;
+pub use v4::VERSION;
+
+#[cfg(test)]
+mod test {
+    use super::migration::{migrate, query_database_version};
+    use super::stats::{self, CsvRecord};
+    use super::{VERSION, create_new_database, handler, retention, v1};
+    use crate::configure::config::Tracker;
+    use crate::types::{EventType, TrackerEvent};
+    use sqlx::sqlite::SqliteConnectOptions;
+    use sqlx::{ConnectOptions, SqliteConnection};
+    use std::str::FromStr;
+
+    async fn create_v1_database(fixture: &str) -> SqliteConnection {
+        let mut conn = SqliteConnectOptions::from_str("sqlite::memory:")
+            .unwrap()
+            .connect()
+            .await
+            .unwrap();
+        sqlx::query(v1::CREATE_TABLE)
+            .execute(&mut conn)
+            .await
+            .unwrap();
+        sqlx::query(fixture).execute(&mut conn).await.unwrap();
+        conn
+    }
+
+    const FIXTURE: &str = r#"
+        INSERT INTO "meta" VALUES ('version', '1');
+        INSERT INTO "users" VALUES (1000, 5, '12', 'Alice', 1);
+        INSERT INTO "users" VALUES (1010, 6, 'VLp6Uq2Fh2RZjUDq8dLdUyOLsW0=', 'Bob', 2);
+        INSERT INTO "users" VALUES (1020, 6, 'VLp6Uq2Fh2RZjUDq8dLdUyOLsW0=', 'Bob', 3);
+        INSERT INTO "users" VALUES (1030, 5, NULL, 'Alice', NULL);
+        INSERT INTO "users" VALUES (1040, 5, 'AliceUid0000000000000000000=', 'Alice', 2);
+        INSERT INTO "users" VALUES (1050, 5, 'AliceUid0000000000000000000=', 'Alice', 1);
+        INSERT INTO "users" VALUES (1060, 5, NULL, 'Alice', NULL);
+    "#;
+
+    #[tokio::test]
+    async fn test_migrate_v1() {
+        let mut conn = create_v1_database(FIXTURE).await;
+        assert!(migrate(&mut conn).await.unwrap());
+        assert_eq!(
+            query_database_version(&mut conn).await.unwrap().as_deref(),
+            Some(VERSION)
+        );
+
+        let events = sqlx::query_as::<_, (i64, String, i64, Option<i64>, Option<String>, Option<i64>)>(
+            r#"SELECT "timestamp", "event", "client_id", "database_id", "unique_identifier", "channel" FROM "events" ORDER BY "timestamp""#,
+        )
+        .fetch_all(&mut conn)
+        .await
+        .unwrap();
+        assert_eq!(
+            events.iter().map(|e| e.1.as_str()).collect::<Vec<_>>(),
+            ["enter", "enter", "move", "leave", "enter", "move", "leave"]
+        );
+        assert_eq!(
+            events[0],
+            (1000, "enter".into(), 5, Some(12), None, Some(1))
+        );
+        assert_eq!(events[1].4.as_deref(), Some("VLp6Uq2Fh2RZjUDq8dLdUyOLsW0="));
+        assert_eq!(events[1].3, None);
+        assert_eq!(events[3], (1030, "leave".into(), 5, None, None, None));
+
+        let sessions = sqlx::query_as::<_, (i64, Option<String>, i64, Option<i64>, Option<String>)>(
+            r#"SELECT "client_id", "nickname", "join_time", "leave_time", "leave_reason" FROM "sessions" ORDER BY "join_time""#,
+        )
+        .fetch_all(&mut conn)
+        .await
+        .unwrap();
+        assert_eq!(
+            sessions,
+            [
+                (
+                    5,
+                    Some("Alice".into()),
+                    1000,
+                    Some(1030),
+                    Some("leave".into())
+                ),
+                (6, Some("Bob".into()), 1010, None, None),
+                (
+                    5,
+                    Some("Alice".into()),
+                    1040,
+                    Some(1060),
+                    Some("leave".into())
+                ),
+            ]
+        );
+
+        let (users,) = sqlx::query_as::<_, (i32,)>(
+            r#"SELECT COUNT(*) FROM "sqlite_master" WHERE "type" = 'table' AND "name" = 'users'"#,
+        )
+        .fetch_one(&mut conn)
+        .await
+        .unwrap();
+        assert_eq!(users, 0);
+
+        // Already latest version
+        assert!(!migrate(&mut conn).await.unwrap());
+    }
+
+    #[tokio::test]
+    async fn test_migrate_v1_interrupted() {
+        let mut conn = create_v1_database(
+            r#"
+            INSERT INTO "meta" VALUES ('version', '1');
+            INSERT INTO "users" VALUES (1100, 7, 'CarolUid', 'Carol', 1);
+            INSERT INTO "users" VALUES (1200, 7, 'DaveUid', 'Dave', 1);
+            INSERT INTO "users" VALUES (1300, 7, NULL, 'Dave', NULL);
+            INSERT INTO "users" VALUES (1400, 8, 'EveUid', 'Eve', 1);
+            INSERT INTO "users" VALUES (1500, 8, '20', 'Eve', 1);
+            INSERT INTO "users" VALUES (1550, 8, 'EveUid', 'Eve', 2);
+            INSERT INTO "users" VALUES (1600, 8, NULL, 'Eve', NULL);
+            "#,
+        )
+        .await;
+        assert!(migrate(&mut conn).await.unwrap());
+
+        let events =
+            sqlx::query_as::<_, (String,)>(r#"SELECT "event" FROM "events" ORDER BY "timestamp""#)
+                .fetch_all(&mut conn)
+                .await
+                .unwrap();
+        assert_eq!(
+            events.iter().map(|e| e.0.as_str()).collect::<Vec<_>>(),
+            // Missed leave of Carol, then observer restarted while Eve online
+            ["enter", "enter", "leave", "enter", "enter", "move", "leave"]
+        );
+
+        let sessions = sqlx::query_as::<_, (i64, i64, Option<i64>, Option<String>)>(
+            r#"SELECT "client_id", "join_time", "leave_time", "leave_reason" FROM "sessions" ORDER BY "join_time""#,
+        )
+        .fetch_all(&mut conn)
+        .await
+        .unwrap();
+        assert_eq!(
+            sessions,
+            [
+                (7, 1100, Some(1200), Some("interrupted".into())),
+                (7, 1200, Some(1300), Some("leave".into())),
+                (8, 1400, Some(1500), Some("interrupted".into())),
+                (8, 1500, Some(1600), Some("leave".into())),
+            ]
+        );
+    }
+
+    #[tokio::test]
+    async fn test_migrate_rollback() {
+        let mut conn = create_v1_database(
+            r#"INSERT INTO "meta" VALUES ('version', '1'); DROP TABLE "users";"#,
+        )
+        .await;
+        assert!(migrate(&mut conn).await.is_err());
+        assert_eq!(
+            query_database_version(&mut conn).await.unwrap().as_deref(),
+            Some("1")
+        );
+        let (events,) = sqlx::query_as::<_, (i32,)>(
+            r#"SELECT COUNT(*) FROM "sqlite_master" WHERE "type" = 'table' AND "name" = 'events'"#,
+        )
+        .fetch_one(&mut conn)
+        .await
+        .unwrap();
+        assert_eq!(events, 0);
+    }
+
+    #[tokio::test]
+    async fn test_stats() {
+        let mut conn = SqliteConnectOptions::from_str("sqlite::memory:")
+            .unwrap()
+            .connect()
+            .await
+            .unwrap();
+        create_new_database(&mut conn).await.unwrap();
+        sqlx::query(
+            r#"
+            INSERT INTO "sessions" ("client_id", "unique_identifier", "nickname", "join_time", "leave_time") VALUES (5, 'A', 'Alice', 100, 400);
+            INSERT INTO "sessions" ("client_id", "unique_identifier", "nickname", "join_time", "leave_time") VALUES (6, 'B', 'Bob', 200, 300);
+            INSERT INTO "sessions" ("client_id", "unique_identifier", "nickname", "join_time", "leave_time") VALUES (7, 'A', 'Alice2', 350, NULL);
+            INSERT INTO "events" ("timestamp", "event", "client_id", "channel") VALUES (100, 'enter', 5, 1);
+            INSERT INTO "events" ("timestamp", "event", "client_id", "channel") VALUES (250, 'move', 5, 2);
+            INSERT INTO "events" ("timestamp", "event", "client_id") VALUES (400, 'leave', 5);
+            "#,
+        )
+        .execute(&mut conn)
+        .await
+        .unwrap();
+
+        let top = stats::top_online(&mut conn, 0, 500, 10).await.unwrap();
+        assert_eq!(top.len(), 2);
+        assert_eq!(top[0].to_string(), "Alice2 7m (2 sessions)");
+
+        let peak = stats::peak(&mut conn, 0, 500).await.unwrap().unwrap();
+        assert_eq!(
+            peak.to_string().split_once(" at").unwrap().0,
+            "Peak: 2 clients"
+        );
+
+        let mut channels = stats::channel_occupancy(&mut conn, 0)
+            .await
+            .unwrap()
+            .iter()
+            .map(CsvRecord::to_csv)
+            .collect::<Vec<_>>();
+        channels.sort();
+        assert_eq!(channels, vec!["1,150", "2,150"]);
+
+        let user = stats::user_stats(&mut conn, "A", 0, 500)
+            .await
+            .unwrap()
+            .unwrap()
+            .to_string();
+        assert!(user.starts_with("Alice2(A)\nOnline: 7m\nSessions: 2"));
+        assert!(
+            stats::user_stats(&mut conn, "C", 0, 500)
+                .await
+                .unwrap()
+                .is_none()
+        );
+    }
+
+    #[tokio::test]
+    async fn test_retention() {
+        let mut conn = SqliteConnectOptions::from_str("sqlite::memory:")
+            .unwrap()
+            .connect()
+            .await
+            .unwrap();
+        create_new_database(&mut conn).await.unwrap();
+        sqlx::query(
+            r#"
+            INSERT INTO "events" ("timestamp", "event", "client_id", "unique_identifier") VALUES (3600, 'enter', 5, 'A');
+            INSERT INTO "events" ("timestamp", "event", "client_id", "unique_identifier") VALUES (3700, 'move', 5, 'A');
+            INSERT INTO "events" ("timestamp", "event", "client_id", "unique_identifier") VALUES (3800, 'enter', 6, 'B');
+            INSERT INTO "events" ("timestamp", "event", "client_id", "unique_identifier") VALUES (7300, 'kick', 5, 'A');
+            INSERT INTO "events" ("timestamp", "event", "client_id", "unique_identifier") VALUES (90000, 'leave', 6, 'B');
+            INSERT INTO "sessions" ("client_id", "unique_identifier", "join_time", "leave_time") VALUES (5, 'A', 3600, 7300);
+            INSERT INTO "sessions" ("client_id", "unique_identifier", "join_time", "leave_time") VALUES (6, 'B', 3800, 90000);
+            "#,
+        )
+        .execute(&mut conn)
+        .await
+        .unwrap();
+
+        let option: Tracker = toml::from_str("retention = 1\nrollup-retention = 2").unwrap();
+        let summary = retention::run(&mut conn, &option, 86400 + 10900)
+            .await
+            .unwrap();
+        assert_eq!(
+            summary.to_string(),
+            "rolled up 2 hours, removed 4 events, 1 sessions and 0 hourly rollups"
+        );
+        let hourly = sqlx::query_as::<_, (i64, i64, i64, i64, i64)>(
+            r#"SELECT "hour", "enters", "leaves", "moves", "clients" FROM "hourly" ORDER BY "hour""#,
+        )
+        .fetch_all(&mut conn)
+        .await
+        .unwrap();
+        assert_eq!(hourly, vec![(3600, 2, 0, 1, 2), (7200, 0, 1, 0, 1)]);
+
+        // Rolled up hours are counted together with raw events
+        sqlx::query(
+            r#"INSERT INTO "events" ("timestamp", "event", "client_id", "unique_identifier") VALUES (176450, 'enter', 7, 'C')"#,
+        )
+        .execute(&mut conn)
+        .await
+        .unwrap();
+        let busiest = stats::busiest_hours(&mut conn, 0).await.unwrap();
+        assert_eq!(busiest.len(), 1);
+        assert!(busiest[0].to_csv().ends_with(",3"));
+
+        // Rollup expired
+        let summary = retention::run(&mut conn, &option, 86400 * 2 + 10900)
+            .await
+            .unwrap();
+        assert_eq!(
+            summary.to_string(),
+            "rolled up 1 hours, removed 1 events, 1 sessions and 2 hourly rollups"
+        );
+    }
+
+    #[tokio::test]
+    async fn test_flush() {
+        let mut conn = SqliteConnectOptions::from_str("sqlite::memory:")
+            .unwrap()
+            .connect()
+            .await
+            .unwrap();
+        create_new_database(&mut conn).await.unwrap();
+        let mut buffer = vec![
+            TrackerEvent::new(EventType::Enter, 5).with_client(1, "A", "Alice"),
+            TrackerEvent::new(EventType::Leave, 5).with_client(1, "A", "Alice"),
+        ];
+        handler::flush(&mut conn, &mut buffer).await.unwrap();
+        assert!(buffer.is_empty());
+
+        let (events,) = sqlx::query_as::<_, (i64,)>(r#"SELECT COUNT(*) FROM "events""#)
+            .fetch_one(&mut conn)
+            .await
+            .unwrap();
+        assert_eq!(events, 2);
+        let (sessions,) = sqlx::query_as::<_, (i64,)>(
+            r#"SELECT COUNT(*) FROM "sessions" WHERE "leave_time" IS NOT NULL"#,
+        )
+        .fetch_one(&mut conn)
+        .await
+        .unwrap();
+        assert_eq!(sessions, 1);
+    }
+
+    #[tokio::test]
+    async fn test_flush_failure() {
+        let mut conn = SqliteConnectOptions::from_str("sqlite::memory:")
+            .unwrap()
+            .connect()
+            .await
+            .unwrap();
+        create_new_database(&mut conn).await.unwrap();
+        // Transaction opened outside sqlx, so flush is unable to begin transaction
+        sqlx::query("BEGIN").execute(&mut conn).await.unwrap();
+
+        let interval = std::time::Duration::from_millis(1000);
+        let mut pending = handler::Pending::new(2);
+        let event = TrackerEvent::new(EventType::Enter, 5).with_client(1, "A", "Alice");
+        assert!(!pending.push(event.clone(), interval, 2));
+        assert!(pending.push(event.clone(), interval, 2));
+        pending.flush(&mut conn).await;
+        assert_eq!(pending.len(), 2);
+        let retry = pending.deadline();
+        assert!(retry > tokio::time::Instant::now());
+
+        // Wait for retry deadline instead of flushing every event
+        assert!(!pending.push(event.clone(), interval, 2));
+        pending.flush(&mut conn).await;
+        assert!(pending.deadline() > retry);
+
+        for _ in 0..handler::MAX_PENDING {
+            pending.push(event.clone(), interval, 2);
+        }
+        assert_eq!(pending.len(), handler::MAX_PENDING);
+
+        sqlx::query("ROLLBACK").execute(&mut conn).await.unwrap();
+        pending.flush(&mut conn).await;
+        assert_eq!(pending.len(), 0);
+        let (events,) = sqlx::query_as::<_, (i64,)>(r#"SELECT COUNT(*) FROM "events""#)
+            .fetch_one(&mut conn)
+            .await
+            .unwrap();
+        assert_eq!(events, handler::MAX_PENDING as i64);
+    }
+
+    #[tokio::test]
+    async fn test_seen() {
+        let mut conn = SqliteConnectOptions::from_str("sqlite::memory:")
+            .unwrap()
+            .connect()
+            .await
+            .unwrap();
+        create_new_database(&mut conn).await.unwrap();
+        sqlx::query(
+            r#"
+            INSERT INTO "events" ("timestamp", "event", "client_id", "unique_identifier", "nickname", "channel") VALUES (100, 'enter', 5, 'A', 'Alice', 1);
+            INSERT INTO "events" ("timestamp", "event", "client_id", "unique_identifier", "nickname", "channel") VALUES (200, 'move', 5, 'A', 'Alice', 2);
+            INSERT INTO "events" ("timestamp", "event", "client_id", "unique_identifier", "nickname") VALUES (300, 'leave', 5, 'A', 'Alice');
+            INSERT INTO "events" ("timestamp", "event", "client_id", "unique_identifier", "nickname", "channel") VALUES (400, 'enter', 6, 'A', 'Al1ce_away', 1);
+            INSERT INTO "events" ("timestamp", "event", "client_id", "unique_identifier", "nickname", "channel") VALUES (500, 'enter', 7, 'B', 'Bob', 3);
+            INSERT INTO "sessions" ("client_id", "unique_identifier", "join_time") VALUES (7, 'B', 500);
+            "#,
+        )
+        .execute(&mut conn)
+        .await
+        .unwrap();
+
+        let result = stats::seen(&mut conn, "A", 5).await.unwrap();
+        assert_eq!(result.len(), 1);
+        assert_eq!(result[0].channel(), Some(1));
+        assert!(
+            result[0]
+                .to_string()
+                .starts_with("Al1ce_away(A)\nLast seen:")
+        );
+
+        let result = stats::seen(&mut conn, "alise", 5).await.unwrap();
+        assert_eq!(result.len(), 1);
+        assert_eq!(result[0].channel(), Some(1));
+
+        let result = stats::seen(&mut conn, "B0B", 5).await.unwrap();
+        assert!(result[0].to_string().starts_with("Bob(B)\nOnline"));
+        assert!(stats::seen(&mut conn, "Carol", 5).await.unwrap().is_empty());
+
+        let names = stats::names(&mut conn, "A").await.unwrap();
+        assert_eq!(
+            names
+                .iter()
+                .map(|name| name.to_string())
+                .collect::<Vec<_>>(),
+            vec![
+                format!(
+                    "Al1ce_away ({0} ~ {0})",
+                    crate::template::format_timestamp(400)
+                ),
+                format!(
+                    "Alice ({} ~ {})",
+                    crate::template::format_timestamp(100),
+                    crate::template::format_timestamp(300)
+                ),
+            ]
+        );
+    }
+
+    #[tokio::test]
+    async fn test_refuse_newer() {
+        let mut conn = create_v1_database(r#"INSERT INTO "meta" VALUES ('version', '99')"#).await;
+        assert!(migrate(&mut conn).await.is_err());
+        assert_eq!(
+            query_database_version(&mut conn).await.unwrap().as_deref(),
+            Some("99")
+        );
+    }
+}
@@ -0,0 +1,121 @@
+use crate::configure::config::CloneDetection;
+use std::collections::{HashMap, HashSet, VecDeque};
+use std::fmt::{Display, Formatter};
+use std::sync::Mutex;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum CloneAlert {
+    PerIp(usize),
+    PerUid(usize),
+    Window(usize),
+}
+
+impl Display for CloneAlert {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            CloneAlert::PerIp(count) => write!(f, "{count} connections from same IP"),
+            CloneAlert::PerUid(count) => write!(f, "{count} connections with same UID"),
+            CloneAlert::Window(count) => write!(f, "{count} UIDs from same IP in short time"),
+        }
+    }
+}
+
+#[derive(Debug, Default)]
+pub struct CloneDetector {
+    policy: CloneDetection,
+    /// Recent join time with UID, grouped by IP
+    recent: Mutex<HashMap<String, VecDeque<(u64, String)>>>,
+}
+
+impl CloneDetector {
+    pub fn new(policy: &CloneDetection) -> Self {
+        Self {
+            policy: policy.clone(),
+            recent: Default::default(),
+        }
+    }
+
+    pub fn enabled(&self) -> bool {
+        self.policy.enable()
+    }
+
+    pub fn policy(&self) -> &CloneDetection {
+        &self.policy
+    }
+
+    fn check_window(&self, ip: &str, uid: &str, now: u64) -> usize {
+        let mut recent = self.recent.lock().unwrap();
+        let expired = now.saturating_sub(self.policy.window());
+        if recent.len() > 1024 {
+            recent.retain(|_, joins| joins.back().is_some_and(|(time, _)| *time >= expired));
+        }
+        let joins = recent.entry(ip.to_string()).or_default();
+        while joins.front().is_some_and(|(time, _)| *time < expired) {
+            joins.pop_front();
+        }
+        joins.push_back((now, uid.to_string()));
+        joins
+            .iter()
+            .map(|(_, uid)| uid.as_str())
+            .collect::<HashSet<_>>()
+            .len()
+    }
+
+    /// Check new connection, `connected` should contain IP and UID of every connected
+    /// client (including the new one).
+    pub fn check<'a>(
+        &self,
+        ip: &str,
+        uid: &str,
+        now: u64,
+        connected: impl Iterator<Item = (&'a str, &'a str)> + Clone,
+    ) -> Option<CloneAlert> {
+        if !self.enabled() || ip.is_empty() || self.policy.exempt_ip().iter().any(|e| e.eq(ip)) {
+            return None;
+        }
+
+        let uids = self.check_window(ip, uid, now);
+        if let Some(max) = self.policy.max_per_ip() {
+            let count = connected.clone().filter(|(addr, _)| addr.eq(&ip)).count();
+            if count > max {
+                return Some(CloneAlert::PerIp(count));
+            }
+        }
+        if let Some(max) = self.policy.max_per_uid() {
+            let count = connected.filter(|(_, id)| id.eq(&uid)).count();
+            if count > max {
+                return Some(CloneAlert::PerUid(count));
+            }
+        }
+        self.policy
+            .max_uids_per_window()
+            .filter(|max| uids > *max)
+            .map(|_| CloneAlert::Window(uids))
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::{CloneAlert, CloneDetector};
+    use crate::configure::config::CloneDetection;
+
+    #[test]
+    fn test_detector() {
+        let policy: CloneDetection =
+            toml::from_str("enable = true\nmax-per-ip = 2\nmax-uids-per-window = 2\nwindow = 10")
+                .unwrap();
+        let detector = CloneDetector::new(&policy);
+        let connected = [("1.1.1.1", "a"), ("1.1.1.1", "a"), ("1.1.1.1", "b")];
+        assert_eq!(
+            detector.check("1.1.1.1", "b", 0, connected.into_iter()),
+            Some(CloneAlert::PerIp(3))
+        );
+        assert_eq!(detector.check("2.2.2.2", "a", 0, [].into_iter()), None);
+        assert_eq!(detector.check("2.2.2.2", "b", 5, [].into_iter()), None);
+        assert_eq!(
+            detector.check("2.2.2.2", "c", 10, [].into_iter()),
+            Some(CloneAlert::Window(3))
+        );
+        assert_eq!(detector.check("2.2.2.2", "d", 30, [].into_iter()), None);
+    }
+}
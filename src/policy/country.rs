@@ -0,0 +1,73 @@
+use crate::configure::config::CountryPolicy;
+
+#[derive(Clone, Debug, Default)]
+pub struct CountryChecker {
+    policy: CountryPolicy,
+}
+
+impl CountryChecker {
+    pub fn new(policy: &CountryPolicy) -> Self {
+        Self {
+            policy: policy.clone(),
+        }
+    }
+
+    pub fn enabled(&self) -> bool {
+        self.policy.enable()
+    }
+
+    pub fn policy(&self) -> &CountryPolicy {
+        &self.policy
+    }
+
+    fn exempt(&self, uid: &str, server_groups: &[i64]) -> bool {
+        self.policy.exempt_uid().iter().any(|exempt| exempt.eq(uid))
+            || server_groups
+                .iter()
+                .any(|group| self.policy.exempt_group().contains(group))
+    }
+
+    /// Return `true` if the client should be handled by country policy.
+    pub fn check(&self, uid: &str, server_groups: &[i64], country: &str) -> bool {
+        // Country is unknown for client from local network
+        if !self.enabled() || country.is_empty() || self.exempt(uid, server_groups) {
+            return false;
+        }
+        if !self.policy.allow().is_empty() {
+            return !self
+                .policy
+                .allow()
+                .iter()
+                .any(|allow| allow.eq_ignore_ascii_case(country));
+        }
+        self.policy
+            .deny()
+            .iter()
+            .any(|deny| deny.eq_ignore_ascii_case(country))
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::CountryChecker;
+    use crate::configure::config::CountryPolicy;
+
+    #[test]
+    fn test_checker() {
+        let policy: CountryPolicy =
+            toml::from_str("enable = true\nallow = [\"JP\", \"CN\"]\nexempt-group = [6]").unwrap();
+        let checker = CountryChecker::new(&policy);
+        assert!(!checker.check("user=", &[8], "jp"));
+        assert!(checker.check("user=", &[8], "US"));
+        assert!(!checker.check("user=", &[], ""));
+        assert!(!checker.check("user=", &[8, 6], "US"));
+
+        let policy: CountryPolicy = toml::from_str("enable = true\ndeny = [\"US\"]").unwrap();
+        let checker = CountryChecker::new(&policy);
+        assert!(checker.check("user=", &[], "US"));
+        assert!(!checker.check("user=", &[], "JP"));
+
+        let policy: CountryPolicy = toml::from_str("enable = true\naction = \"move\"").unwrap();
+        assert!(policy.validate().is_err());
+    }
+}
@@ -0,0 +1,64 @@
+pub mod clone;
+pub mod country;
+pub mod nickname;
+pub mod raid;
+
+use crate::configure::Config;
+use crate::configure::config::PolicyAction;
+use crate::socketlib::SocketConn;
+use crate::types::QueryResult;
+
+pub use clone::CloneDetector;
+pub use country::CountryChecker;
+pub use nickname::NicknameChecker;
+pub use raid::RaidGuard;
+
+/// Compiled policies of one configure, built once when observer thread start.
+#[derive(Debug, Default)]
+pub struct PolicySet {
+    nickname: NicknameChecker,
+    country: CountryChecker,
+    clone: CloneDetector,
+    raid: RaidGuard,
+}
+
+impl PolicySet {
+    pub fn new(config: &Config) -> anyhow::Result<Self> {
+        Ok(Self {
+            nickname: NicknameChecker::new(config.nickname_policy())?,
+            country: CountryChecker::new(config.country_policy()),
+            clone: CloneDetector::new(config.clone_detection()),
+            raid: RaidGuard::new(config.raid_protection()),
+        })
+    }
+
+    pub fn nickname(&self) -> &NicknameChecker {
+        &self.nickname
+    }
+
+    pub fn country(&self) -> &CountryChecker {
+        &self.country
+    }
+
+    pub fn clone_detector(&self) -> &CloneDetector {
+        &self.clone
+    }
+
+    pub fn raid(&self) -> &RaidGuard {
+        &self.raid
+    }
+}
+
+pub async fn execute_action(
+    conn: &mut SocketConn,
+    action: PolicyAction,
+    client_id: i64,
+    reason: &str,
+    ban_duration: u64,
+) -> QueryResult<()> {
+    match action {
+        PolicyAction::Poke => conn.poke_client(client_id, reason).await,
+        PolicyAction::Kick => conn.kick_client(client_id, reason).await,
+        PolicyAction::Ban => conn.ban_client(client_id, ban_duration, reason).await,
+    }
+}
@@ -0,0 +1,208 @@
+use crate::configure::config::NicknamePolicy;
+use anyhow::anyhow;
+use regex::Regex;
+use std::fmt::{Display, Formatter};
+use unicode_normalization::UnicodeNormalization;
+
+#[derive(Clone, Debug)]
+pub enum Violation {
+    Denied(String),
+    TooLong(usize),
+    Impersonation(String),
+}
+
+impl Display for Violation {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Violation::Denied(pattern) => write!(f, "matched deny pattern {pattern:?}"),
+            Violation::TooLong(length) => write!(f, "too long ({length} characters)"),
+            Violation::Impersonation(staff) => write!(f, "too similar to staff {staff:?}"),
+        }
+    }
+}
+
+#[derive(Clone, Debug, Default)]
+pub struct NicknameChecker {
+    policy: NicknamePolicy,
+    deny: Vec<Regex>,
+    max_length: Option<usize>,
+    /// Staff nickname with its skeleton
+    staff: Vec<(String, String)>,
+    staff_uid: Vec<String>,
+    similarity: f64,
+}
+
+impl NicknameChecker {
+    pub fn new(policy: &NicknamePolicy) -> anyhow::Result<Self> {
+        if !policy.enable() {
+            return Ok(Self::default());
+        }
+        let deny = policy
+            .deny()
+            .iter()
+            .map(|pattern| {
+                Regex::new(pattern).map_err(|e| anyhow!("Invalid deny pattern {pattern:?}: {e}"))
+            })
+            .collect::<anyhow::Result<Vec<_>>>()?;
+        Ok(Self {
+            policy: policy.clone(),
+            deny,
+            max_length: policy.max_length(),
+            staff: policy
+                .staff()
+                .iter()
+                .map(|name| (name.clone(), skeleton(name)))
+                .collect(),
+            staff_uid: policy.staff_uid().to_vec(),
+            similarity: policy.similarity(),
+        })
+    }
+
+    pub fn enabled(&self) -> bool {
+        self.policy.enable()
+    }
+
+    pub fn policy(&self) -> &NicknamePolicy {
+        &self.policy
+    }
+
+    pub fn check(&self, uid: &str, nickname: &str) -> Option<Violation> {
+        if !self.enabled() || self.staff_uid.iter().any(|staff| staff.eq(uid)) {
+            return None;
+        }
+
+        if let Some(max_length) = self.max_length {
+            let length = nickname.chars().count();
+            if length > max_length {
+                return Some(Violation::TooLong(length));
+            }
+        }
+
+        let normalized = normalize(nickname);
+        if let Some(pattern) = self
+            .deny
+            .iter()
+            .find(|pattern| pattern.is_match(nickname) || pattern.is_match(&normalized))
+        {
+            return Some(Violation::Denied(pattern.to_string()));
+        }
+
+        let skeleton = skeleton(nickname);
+        self.staff
+            .iter()
+            .find(|(_, staff)| similarity(staff, &skeleton) >= self.similarity)
+            .map(|(name, _)| Violation::Impersonation(name.clone()))
+    }
+}
+
+fn confusable(c: char) -> Option<char> {
+    Some(match c {
+        'а' | 'α' | '4' | '@' => 'a',
+        'в' | 'β' | '8' => 'b',
+        'с' | 'ϲ' => 'c',
+        'е' | 'ε' | '3' => 'e',
+        'н' | 'η' => 'h',
+        'і' | 'ι' | '1' | '|' | '!' | 'ӏ' => 'i',
+        'ј' => 'j',
+        'к' | 'κ' => 'k',
+        'м' | 'μ' => 'm',
+        'о' | 'ο' | '0' => 'o',
+        'р' | 'ρ' => 'p',
+        'ѕ' | '5' | '$' => 's',
+        'т' | 'τ' | '7' => 't',
+        'υ' | 'ν' => 'v',
+        'х' | 'χ' => 'x',
+        'у' | 'γ' => 'y',
+        '2' => 'z',
+        _ => return None,
+    })
+}
+
+/// NFKC, lowercase and fold common confusable characters into latin letters.
+pub fn normalize(nickname: &str) -> String {
+    nickname
+        .nfkc()
+        .flat_map(char::to_lowercase)
+        .map(|c| confusable(c).unwrap_or(c))
+        .collect()
+}
+
+/// Reduce nickname to a comparable form, which also folds look-alike letter
+/// sequences and drops everything which is not alphanumeric.
+pub fn skeleton(nickname: &str) -> String {
+    normalize(nickname)
+        .chars()
+        .filter(|c| c.is_alphanumeric())
+        .map(|c| if c == 'l' { 'i' } else { c })
+        .collect::<String>()
+        .replace("rn", "m")
+        .replace("vv", "w")
+}
+
+fn levenshtein(a: &[char], b: &[char]) -> usize {
+    let mut previous = (0..=b.len()).collect::<Vec<_>>();
+    let mut current = vec![0; b.len() + 1];
+    for (i, ca) in a.iter().enumerate() {
+        current[0] = i + 1;
+        for (j, cb) in b.iter().enumerate() {
+            current[j + 1] = (previous[j] + usize::from(ca != cb))
+                .min(previous[j + 1] + 1)
+                .min(current[j] + 1);
+        }
+        std::mem::swap(&mut previous, &mut current);
+    }
+    previous[b.len()]
+}
+
+/// Normalized similarity between two strings, `1.0` means identical.
+pub fn similarity(a: &str, b: &str) -> f64 {
+    let a = a.chars().collect::<Vec<_>>();
+    let b = b.chars().collect::<Vec<_>>();
+    let length = a.len().max(b.len());
+    if length == 0 {
+        return 0.0;
+    }
+    1.0 - levenshtein(&a, &b) as f64 / length as f64
+}
+
+#[cfg(test)]
+mod test {
+    use super::{NicknameChecker, Violation, similarity, skeleton};
+    use crate::configure::config::NicknamePolicy;
+
+    const TEST_POLICY: &str = r#"
+        enable = true
+        deny = ["(?i)admin"]
+        max-length = 16
+        staff = ["KunoiSayami"]
+        staff-uid = ["staff="]
+        "#;
+
+    #[test]
+    fn test_skeleton() {
+        assert_eq!(skeleton("Kunoi Sаyami"), skeleton("kunoisayami"));
+        assert_eq!(skeleton("B1LL"), skeleton("bill"));
+        assert_eq!(skeleton("ＡＢＣ"), "abc");
+        assert_eq!(similarity("abc", "abc"), 1.0);
+    }
+
+    #[test]
+    fn test_checker() {
+        let policy: NicknamePolicy = toml::from_str(TEST_POLICY).unwrap();
+        let checker = NicknameChecker::new(&policy).unwrap();
+        assert!(checker.check("user=", "normal user").is_none());
+        assert!(checker.check("staff=", "KunoiSayami").is_none());
+        assert!(matches!(
+            checker.check("user=", "Kun0i_Sayarni"),
+            Some(Violation::Impersonation(_))
+        ));
+        assert!(matches!(
+            checker.check("user=", "server 4dmin"),
+            Some(Violation::Denied(_))
+        ));
+        assert!(matches!(
+            checker.check("user=", "a very long nickname"),
+            Some(Violation::TooLong(20))
+        ));
+    }
+}
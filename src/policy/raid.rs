@@ -0,0 +1,127 @@
+use crate::configure::config::RaidProtection;
+use std::collections::VecDeque;
+use std::sync::Mutex;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum RaidStatus {
+    Normal,
+    /// Lockdown is triggered by this join, with fresh join count in window
+    Triggered(usize),
+    Locked,
+}
+
+/// Server settings changed by lockdown, restored when lockdown ends
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct Restore {
+    /// Server password is set by lockdown
+    pub password: bool,
+    /// Channel id with previous needed join power, `None` if permission was not set
+    pub join_power: Vec<(i64, Option<i64>)>,
+}
+
+#[derive(Debug, Default)]
+struct RaidState {
+    joins: VecDeque<u64>,
+    locked_at: Option<u64>,
+    restore: Restore,
+}
+
+#[derive(Debug, Default)]
+pub struct RaidGuard {
+    policy: RaidProtection,
+    state: Mutex<RaidState>,
+}
+
+impl RaidGuard {
+    pub fn new(policy: &RaidProtection) -> Self {
+        Self {
+            policy: policy.clone(),
+            state: Default::default(),
+        }
+    }
+
+    pub fn enabled(&self) -> bool {
+        self.policy.enable()
+    }
+
+    pub fn policy(&self) -> &RaidProtection {
+        &self.policy
+    }
+
+    /// Sliding window counter of fresh UIDs
+    pub fn on_enter(&self, total_connections: i64, now: u64) -> RaidStatus {
+        if !self.enabled() {
+            return RaidStatus::Normal;
+        }
+        let mut state = self.state.lock().unwrap();
+        if state.locked_at.is_some() {
+            return RaidStatus::Locked;
+        }
+        if total_connections > self.policy.fresh_connections() {
+            return RaidStatus::Normal;
+        }
+        let expired = now.saturating_sub(self.policy.window());
+        while state.joins.front().is_some_and(|time| *time < expired) {
+            state.joins.pop_front();
+        }
+        state.joins.push_back(now);
+        let count = state.joins.len();
+        if count < self.policy.threshold() {
+            return RaidStatus::Normal;
+        }
+        state.joins.clear();
+        state.locked_at = Some(now);
+        RaidStatus::Triggered(count)
+    }
+
+    pub fn set_restore(&self, restore: Restore) {
+        self.state.lock().unwrap().restore = restore;
+    }
+
+    /// End lockdown, return settings to restore if server is in lockdown
+    pub fn end(&self) -> Option<Restore> {
+        let mut state = self.state.lock().unwrap();
+        state.locked_at.take()?;
+        Some(std::mem::take(&mut state.restore))
+    }
+
+    pub fn expired(&self, now: u64) -> bool {
+        let Some(duration) = self.policy.duration() else {
+            return false;
+        };
+        self.state
+            .lock()
+            .unwrap()
+            .locked_at
+            .is_some_and(|locked_at| now.saturating_sub(locked_at) >= duration)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::{RaidGuard, RaidStatus, Restore};
+    use crate::configure::config::RaidProtection;
+
+    #[test]
+    fn test_guard() {
+        let policy: RaidProtection =
+            toml::from_str("enable = true\nthreshold = 3\nwindow = 10\nduration = 60").unwrap();
+        let guard = RaidGuard::new(&policy);
+        assert_eq!(guard.on_enter(1, 0), RaidStatus::Normal);
+        assert_eq!(guard.on_enter(20, 1), RaidStatus::Normal);
+        assert_eq!(guard.on_enter(1, 2), RaidStatus::Normal);
+        assert_eq!(guard.on_enter(1, 20), RaidStatus::Normal);
+        assert_eq!(guard.on_enter(1, 21), RaidStatus::Normal);
+        assert_eq!(guard.on_enter(1, 22), RaidStatus::Triggered(3));
+        assert_eq!(guard.on_enter(20, 23), RaidStatus::Locked);
+        assert!(!guard.expired(30));
+        assert!(guard.expired(82));
+        let restore = Restore {
+            password: true,
+            join_power: vec![(2, None), (3, Some(20))],
+        };
+        guard.set_restore(restore.clone());
+        assert_eq!(guard.end(), Some(restore));
+        assert_eq!(guard.end(), None);
+    }
+}
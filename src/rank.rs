@@ -0,0 +1,124 @@
+use crate::configure::config::Rank;
+use crate::plugins::KVMap;
+use crate::template::format_duration;
+
+fn build_online_time_key(server_id: &str, unique_identifier: &str) -> String {
+    format!("ts_online_time_{server_id}_{unique_identifier}")
+}
+
+fn build_rank_key(server_id: &str, unique_identifier: &str) -> String {
+    format!("ts_rank_{server_id}_{unique_identifier}")
+}
+
+async fn get_number(kv_map: &mut Box<dyn KVMap>, key: String) -> anyhow::Result<Option<u64>> {
+    Ok(kv_map.get(key).await?.and_then(|v| v.parse().ok()))
+}
+
+/// Get accumulated online seconds of client
+pub async fn online_time(
+    kv_map: &mut Box<dyn KVMap>,
+    server_id: &str,
+    unique_identifier: &str,
+) -> anyhow::Result<u64> {
+    Ok(
+        get_number(kv_map, build_online_time_key(server_id, unique_identifier))
+            .await?
+            .unwrap_or_default(),
+    )
+}
+
+/// Add seconds to online time counter, return new total
+pub async fn add_online_time(
+    kv_map: &mut Box<dyn KVMap>,
+    server_id: &str,
+    unique_identifier: &str,
+    seconds: u64,
+) -> anyhow::Result<u64> {
+    let total = online_time(kv_map, server_id, unique_identifier).await? + seconds;
+    if seconds > 0 {
+        kv_map
+            .set(
+                build_online_time_key(server_id, unique_identifier),
+                total.to_string(),
+            )
+            .await?;
+    }
+    Ok(total)
+}
+
+/// Get hours of highest tier which already granted to client
+pub async fn reached(
+    kv_map: &mut Box<dyn KVMap>,
+    server_id: &str,
+    unique_identifier: &str,
+) -> anyhow::Result<Option<u64>> {
+    get_number(kv_map, build_rank_key(server_id, unique_identifier)).await
+}
+
+pub async fn set_reached(
+    kv_map: &mut Box<dyn KVMap>,
+    server_id: &str,
+    unique_identifier: &str,
+    hours: u64,
+) -> anyhow::Result<()> {
+    kv_map
+        .set(
+            build_rank_key(server_id, unique_identifier),
+            hours.to_string(),
+        )
+        .await
+        .map(|_| ())
+}
+
+/// Describe current rank and progress to next tier
+pub fn progress(rank: &Rank, seconds: u64) -> String {
+    let mut output = format!(
+        "Online time: {}\nRank: {}",
+        format_duration(seconds as i64),
+        rank.tier(seconds).map(|tier| tier.name()).unwrap_or("None")
+    );
+    match rank.next_tier(seconds) {
+        Some(tier) => output.push_str(&format!(
+            "\nNext rank: {} in {}",
+            tier.name(),
+            format_duration((tier.hours() * 3600 - seconds) as i64)
+        )),
+        None => output.push_str("\nHighest rank reached"),
+    }
+    output
+}
+
+#[cfg(test)]
+mod test {
+    use super::progress;
+    use crate::configure::config::Rank;
+
+    #[test]
+    fn test_rank() {
+        let rank: Rank = toml::from_str(
+            r#"
+enable = true
+[[tier]]
+hours = 100
+group-id = 8
+name = "Veteran"
+[[tier]]
+hours = 10
+group-id = 7
+name = "Regular"
+"#,
+        )
+        .unwrap();
+        assert!(rank.tier(9 * 3600).is_none());
+        assert_eq!(rank.tier(10 * 3600).unwrap().group_id(), 7);
+        assert_eq!(rank.tier(200 * 3600).unwrap().name(), "Veteran");
+        assert_eq!(
+            progress(&rank, 12 * 3600 + 300),
+            "Online time: 12h 5m\nRank: Regular\nNext rank: Veteran in 87h 55m"
+        );
+        assert_eq!(
+            progress(&rank, 100 * 3600),
+            "Online time: 100h 0m\nRank: Veteran\nHighest rank reached"
+        );
+    }
+}
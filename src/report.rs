@@ -0,0 +1,63 @@
+use crate::plugins::tracker::stats::{self, CsvRecord};
+use clap::{ArgMatches, Command, arg, value_parser};
+use serde::Serialize;
+
+pub fn command() -> Command {
+    Command::new("report")
+        .about("Generate report from tracker database")
+        .args(&[
+            arg!(<DATABASE> "Tracker database file"),
+            arg!(--kind <KIND> "Report kind")
+                .value_parser(["top", "daily", "peak", "hours", "channels"])
+                .default_value("top"),
+            arg!(--format <FORMAT> "Output format")
+                .value_parser(["csv", "json"])
+                .default_value("csv"),
+            arg!(--days <DAYS> "Report recent days, 0 means all records")
+                .value_parser(value_parser!(i64))
+                .default_value("30"),
+            arg!(--limit <LIMIT> "Maximum clients in top report")
+                .value_parser(value_parser!(i64))
+                .default_value("10"),
+        ])
+}
+
+fn render<T: Serialize + CsvRecord>(rows: &[T], json: bool) -> anyhow::Result<String> {
+    if json {
+        return Ok(serde_json::to_string_pretty(rows)?);
+    }
+    let mut lines = vec![T::HEADER.to_string()];
+    lines.extend(rows.iter().map(CsvRecord::to_csv));
+    Ok(lines.join("\n"))
+}
+
+pub async fn run(matches: &ArgMatches) -> anyhow::Result<()> {
+    let mut conn = stats::open(matches.get_one::<String>("DATABASE").unwrap()).await?;
+    let json = matches.get_one::<String>("format").unwrap().eq("json");
+    let days = *matches.get_one::<i64>("days").unwrap();
+    let now = kstool::time::get_current_second() as i64;
+    let since = if days > 0 { now - days * 86400 } else { 0 };
+
+    let output = match matches.get_one::<String>("kind").unwrap().as_str() {
+        "top" => render(
+            &stats::top_online(&mut conn, since, now, *matches.get_one("limit").unwrap()).await?,
+            json,
+        )?,
+        "daily" => render(
+            &stats::sessions_per_day(&mut conn, None, since).await?,
+            json,
+        )?,
+        "peak" => render(
+            &stats::peak(&mut conn, since, now)
+                .await?
+                .into_iter()
+                .collect::<Vec<_>>(),
+            json,
+        )?,
+        "hours" => render(&stats::busiest_hours(&mut conn, since).await?, json)?,
+        "channels" => render(&stats::channel_occupancy(&mut conn, since).await?, json)?,
+        _ => unreachable!(),
+    };
+    println!("{output}");
+    Ok(())
+}
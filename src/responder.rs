@@ -0,0 +1,78 @@
+use crate::configure::Config;
+use crate::telegram::escape_html;
+use crate::types::{ConfigMappedUserState, SafeUserState};
+
+/// Commands which every chat bot supports
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Command {
+    Ping,
+    List,
+}
+
+impl Command {
+    /// Parse command from message text, e.g. `/list` or `/list@bot`
+    pub fn parse(text: &str) -> Option<Self> {
+        let command = text.split_whitespace().next()?.strip_prefix('/')?;
+        let command = command.split_once('@').map_or(command, |(cmd, _)| cmd);
+        match command.to_lowercase().as_str() {
+            "ping" => Some(Self::Ping),
+            "list" => Some(Self::List),
+            _ => None,
+        }
+    }
+}
+
+/// Build user state of each configure, state is only tracked if any bot responds to it or
+/// telegram status message is enabled
+pub fn user_state_map(configs: &[(String, Config)]) -> ConfigMappedUserState {
+    configs
+        .iter()
+        .map(|(_, config)| {
+            let responsible = (!config.telegram().api_key().is_empty()
+                && (config.telegram().responsible() || config.telegram().status().enable()))
+                || config.matrix().is_some_and(|matrix| matrix.responsible());
+            (
+                config.get_id(),
+                if responsible {
+                    SafeUserState::create(config.misc().list_empty_channel())
+                } else {
+                    SafeUserState::create_none()
+                },
+            )
+        })
+        .collect()
+}
+
+pub fn ping(chat_id: &str) -> String {
+    format!(
+        "Chat id: <code>{id}</code>\nVersion: {version}",
+        id = escape_html(chat_id),
+        version = env!("CARGO_PKG_VERSION")
+    )
+}
+
+/// List online clients of configures
+pub async fn list(configs: &[String], channel_map: &ConfigMappedUserState) -> String {
+    let mut v = vec![];
+    for config in configs {
+        let Some(map) = channel_map.get(config).filter(|s| s.enabled()) else {
+            continue;
+        };
+        v.push(format!("{config}\n{}", map.read().await.unwrap()));
+    }
+    v.join("\n\n")
+}
+
+#[cfg(test)]
+mod test {
+    use super::Command;
+
+    #[test]
+    fn test_command() {
+        assert_eq!(Command::parse("/ping"), Some(Command::Ping));
+        assert_eq!(Command::parse("  /LIST@tsbot extra"), Some(Command::List));
+        assert_eq!(Command::parse("/stats"), None);
+        assert_eq!(Command::parse("list"), None);
+        assert_eq!(Command::parse(""), None);
+    }
+}
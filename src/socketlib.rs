@@ -1,6 +1,6 @@
 use crate::types::{
-    Channel, Client, ClientInfo, CreateChannel, DatabaseId, QueryError, QueryResult, ServerInfo,
-    WhoAmI,
+    Channel, ChannelPermission, Client, ClientInfo, CreateChannel, DatabaseId, QueryError,
+    QueryResult, ServerInfo, WhoAmI,
 };
 use crate::types::{FromQueryString, QueryStatus};
 use anyhow::anyhow;
@@ -13,6 +13,8 @@ const BUFFER_SIZE: usize = 512;
 
 pub struct SocketConn {
     conn: TcpStream,
+    /// Notifications received while waiting for command response
+    notifies: Vec<String>,
 }
 
 impl SocketConn {
@@ -101,9 +103,31 @@ impl SocketConn {
         self.write_data(payload).await?;
         self.read_data()
             .await?
+            .map(|data| self.stash_notifies(data))
             .ok_or_else(|| anyhow!("Return data is None"))
     }
 
+    fn stash_notifies(&mut self, data: String) -> String {
+        if !data.contains("notify") {
+            return data;
+        }
+        let mut ret = String::new();
+        for line in data.split("\n\r") {
+            if line.trim_start().starts_with("notify") {
+                self.notifies.push(line.trim().to_string());
+            } else if !line.is_empty() {
+                ret.push_str(line);
+                ret.push_str("\n\r");
+            }
+        }
+        ret
+    }
+
+    /// Take notifications which received during command execution.
+    pub fn take_notifies(&mut self) -> Vec<String> {
+        std::mem::take(&mut self.notifies)
+    }
+
     async fn basic_operation(&mut self, payload: &str) -> QueryResult<()> {
         let data = self.write_and_read(payload).await?;
         Self::decode_status(data)?;
@@ -140,9 +164,20 @@ impl SocketConn {
     }
 
     fn escape(s: &str) -> String {
-        s.replace('\\', "\\\\")
-            .replace(' ', "\\s")
-            .replace('/', "\\/")
+        let mut output = String::with_capacity(s.len());
+        for c in s.chars() {
+            match c {
+                '\\' => output.push_str("\\\\"),
+                ' ' => output.push_str("\\s"),
+                '/' => output.push_str("\\/"),
+                '|' => output.push_str("\\p"),
+                '\n' => output.push_str("\\n"),
+                '\r' => output.push_str("\\r"),
+                '\t' => output.push_str("\\t"),
+                _ => output.push(c),
+            }
+        }
+        output
     }
 
     pub async fn connect(server: &str, port: u16) -> anyhow::Result<Self> {
@@ -152,7 +187,10 @@ impl SocketConn {
 
         //let bufreader = BufReader::new(conn);
         //conn.set_nonblocking(true).unwrap();
-        let mut self_ = Self { conn };
+        let mut self_ = Self {
+            conn,
+            notifies: Vec::new(),
+        };
 
         let content = self_
             .read_data()
@@ -182,7 +220,6 @@ impl SocketConn {
             .map(|mut v| v.remove(0))
     }
 
-    #[allow(unused)]
     pub(crate) async fn send_text_message(
         &mut self,
         client_id: i64,
@@ -196,17 +233,58 @@ impl SocketConn {
         self.basic_operation(&payload).await
     }
 
-    pub(crate) async fn send_text_message_unchecked(
+    /// Send message to channel (`target_mode` 2) or server (`target_mode` 3) chat
+    pub(crate) async fn send_chat_message(
         &mut self,
-        client_id: i64,
+        target_mode: i8,
+        target: i64,
         text: &str,
-    ) -> anyhow::Result<()> {
-        let payload = format!(
-            "sendtextmessage targetmode=1 target={client_id} msg={text}\n\r",
-            client_id = client_id,
-            text = Self::escape(text)
-        );
-        self.write_data(&payload).await
+    ) -> QueryResult<()> {
+        self.basic_operation(&format!(
+            "sendtextmessage targetmode={target_mode} target={target} msg={}\n\r",
+            Self::escape(text)
+        ))
+        .await
+    }
+
+    pub(crate) async fn poke_client(&mut self, client_id: i64, text: &str) -> QueryResult<()> {
+        self.basic_operation(&format!(
+            "clientpoke clid={client_id} msg={}\n\r",
+            Self::escape(text)
+        ))
+        .await
+    }
+
+    pub(crate) async fn kick_client(&mut self, client_id: i64, reason: &str) -> QueryResult<()> {
+        self.basic_operation(&format!(
+            "clientkick clid={client_id} reasonid=5 reasonmsg={}\n\r",
+            Self::escape(reason)
+        ))
+        .await
+    }
+
+    pub(crate) async fn add_server_group(
+        &mut self,
+        group_id: i64,
+        database_id: i64,
+    ) -> QueryResult<()> {
+        self.basic_operation(&format!(
+            "servergroupaddclient sgid={group_id} cldbid={database_id}\n\r"
+        ))
+        .await
+    }
+
+    pub(crate) async fn ban_client(
+        &mut self,
+        client_id: i64,
+        duration: u64,
+        reason: &str,
+    ) -> QueryResult<()> {
+        self.basic_operation(&format!(
+            "banclient clid={client_id} time={duration} banreason={}\n\r",
+            Self::escape(reason)
+        ))
+        .await
     }
 
     pub(crate) async fn query_server_info(&mut self) -> QueryResult<ServerInfo> {
@@ -237,7 +315,8 @@ impl SocketConn {
     }
 
     pub(crate) async fn query_clients(&mut self) -> QueryResult<Vec<Client>> {
-        self.query_operation_non_error("clientlist\n\r").await
+        self.query_operation_non_error("clientlist -uid -ip -country -away -voice -groups\n\r")
+            .await
     }
 
     pub(crate) async fn move_client(
@@ -253,6 +332,64 @@ impl SocketConn {
         self.basic_operation(payload.as_str()).await
     }
 
+    pub(crate) async fn edit_server(&mut self, properties: &[(&str, String)]) -> QueryResult<()> {
+        let payload = format!(
+            "serveredit {}\n\r",
+            properties
+                .iter()
+                .map(|(k, v)| format!("{k}={}", Self::escape(v)))
+                .collect::<Vec<_>>()
+                .join(" ")
+        );
+        self.basic_operation(&payload).await
+    }
+
+    /// Value of channel permission, `None` if permission is not set
+    pub(crate) async fn query_channel_permission(
+        &mut self,
+        channel_id: i64,
+        permission: &str,
+    ) -> QueryResult<Option<i64>> {
+        match self
+            .query_operation::<ChannelPermission>(&format!(
+                "channelpermlist cid={channel_id} -permsid\n\r"
+            ))
+            .await
+        {
+            Ok(permissions) => Ok(permissions
+                .into_iter()
+                .flatten()
+                .find(|p| p.permsid() == permission)
+                .map(|p| p.permvalue())),
+            // Database empty result set
+            Err(e) if e.code() == 1281 => Ok(None),
+            Err(e) => Err(e),
+        }
+    }
+
+    pub(crate) async fn set_channel_permission(
+        &mut self,
+        channel_id: i64,
+        permission: &str,
+        value: i64,
+    ) -> QueryResult<()> {
+        self.basic_operation(&format!(
+            "channeladdperm cid={channel_id} permsid={permission} permvalue={value}\n\r"
+        ))
+        .await
+    }
+
+    pub(crate) async fn delete_channel_permission(
+        &mut self,
+        channel_id: i64,
+        permission: &str,
+    ) -> QueryResult<()> {
+        self.basic_operation(&format!(
+            "channeldelperm cid={channel_id} permsid={permission}\n\r"
+        ))
+        .await
+    }
+
     pub(crate) async fn set_client_channel_group(
         &mut self,
         client_database_id: i64,
@@ -294,9 +431,21 @@ impl SocketConn {
         self.basic_operation("quit\n\r").await
     }
 
-    pub async fn register_observer_events(&mut self) -> QueryResult<()> {
+    /// Register server events, `bridge` is target mode of chat which should be relayed
+    pub async fn register_observer_events(&mut self, bridge: Option<i8>) -> QueryResult<()> {
         self.basic_operation("servernotifyregister event=server\n\r")
             .await?;
+        match bridge {
+            Some(2) => {
+                self.basic_operation("servernotifyregister event=textchannel\n\r")
+                    .await?
+            }
+            Some(3) => {
+                self.basic_operation("servernotifyregister event=textserver\n\r")
+                    .await?
+            }
+            _ => {}
+        }
         self.basic_operation("servernotifyregister event=textprivate\n\r")
             .await
     }
@@ -339,3 +488,16 @@ impl SocketConn {
             .await
     }
 }
+
+#[cfg(test)]
+mod test {
+    use super::SocketConn;
+
+    #[test]
+    fn test_escape() {
+        assert_eq!(
+            SocketConn::escape("a b/c|d\\e\nquit\r\t"),
+            "a\\sb\\/c\\pd\\\\e\\nquit\\r\\t"
+        );
+    }
+}
@@ -1,95 +1,23 @@
 mod types {
-    use crate::types::{NotifyClientEnterView, NotifyClientLeftView};
-    use teloxide::Bot;
+    use crate::notifier::{Event, Notifier};
+    use async_trait::async_trait;
+    use log::warn;
     use teloxide::adaptors::DefaultParseMode;
-    use teloxide::prelude::{ChatId, Request, Requester, RequesterExt};
-    use teloxide::types::ParseMode;
+    use teloxide::payloads::{PinChatMessageSetters, SendMessageSetters};
+    use teloxide::prelude::{ChatId, Requester, RequesterExt};
+    use teloxide::types::{InlineKeyboardMarkup, Message, MessageId, ParseMode};
+    use teloxide::{ApiError, Bot, RequestError};
     use tokio::sync::mpsc;
-    #[derive(Clone, Debug)]
-    #[non_exhaustive]
-    pub(super) enum TelegramData {
-        Enter(String, i64, String, String, String),
-        Left(String, NotifyClientLeftView, String),
-    }
-
-    impl TelegramData {
-        fn from_left(time: String, view: &NotifyClientLeftView, nickname: String) -> Self {
-            Self::Left(time, view.clone(), nickname)
-        }
-        fn from_enter(time: String, view: &NotifyClientEnterView) -> Self {
-            Self::Enter(
-                time,
-                view.client_id(),
-                view.client_unique_identifier().to_string(),
-                view.client_nickname().to_string(),
-                view.client_country().to_string(),
-            )
-        }
-    }
-
-    impl std::fmt::Display for TelegramData {
-        fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
-            match self {
-                TelegramData::Enter(time, client_id, client_identifier, nickname, country) => {
-                    write!(
-                        f,
-                        "[{time}] <b>{nickname}</b>(<code>{client_identifier}</code>:{client_id})[{}] joined",
-                        country_emoji::flag(country).unwrap_or_else(|| country.into())
-                    )
-                }
-                TelegramData::Left(time, view, nickname) => match view.reason_id() {
-                    8 => {
-                        if view.reason().is_empty() {
-                            write!(f, "[{time}] <b>{nickname}</b>({}) left", view.client_id())
-                        } else {
-                            write!(
-                                f,
-                                "[{time}] <b>{nickname}</b>({}) left ({})",
-                                view.client_id(),
-                                view.reason()
-                            )
-                        }
-                    }
-                    3 => write!(
-                        f,
-                        "[{time}] <b>{nickname}</b>({}) connection lost #timeout",
-                        view.client_id()
-                    ),
-                    5 | 6 => {
-                        write!(
-                            f,
-                            "[{time}] <b>{nickname}</b>({client_id}) was #{operation} by <b>{invoker}</b>(<code>{invoker_uid}</code>){reason}",
-                            time = time,
-                            nickname = nickname,
-                            operation = if view.reason_id() == 5 {
-                                "kicked"
-                            } else {
-                                "banned"
-                            },
-                            client_id = view.client_id(),
-                            invoker = view.invoker_name(),
-                            invoker_uid = view.invoker_uid(),
-                            reason = if view.reason().is_empty() {
-                                " with no reason".into()
-                            } else {
-                                format!(": {}", view.reason())
-                            }
-                        )
-                    }
-                    _ => unreachable!("Got unexpected left message: {view:?}"),
-                },
-            }
-        }
-    }
-
     #[derive(Clone, Debug)]
     pub(super) enum CombineData {
-        Send(String, TelegramData),
+        Send(String, Event),
+        /// Send event to telegram users privately
+        Direct(String, Vec<u64>, Event),
         //Terminate,
     }
 
     impl CombineData {
-        pub fn new(config_id: String, data: TelegramData) -> Self {
+        pub fn new(config_id: String, data: Event) -> Self {
             Self::Send(config_id, data)
         }
 
@@ -104,31 +32,22 @@ mod types {
     }
 
     impl TelegramHelper {
-        pub async fn send_left(
-            &self,
-            id: String,
-            time: String,
-            view: &NotifyClientLeftView,
-            nickname: String,
-        ) -> Option<()> {
+        pub(super) async fn send(&self, id: String, event: Event) -> Option<()> {
             self.sender
-                .send(CombineData::new(
-                    id,
-                    TelegramData::from_left(time, view, nickname),
-                ))
+                .send(CombineData::new(id, event))
                 .await
                 .map(|_| ())
                 .ok()
         }
 
-        pub async fn send_enter(
+        pub(super) async fn send_direct(
             &self,
             id: String,
-            time: String,
-            view: &NotifyClientEnterView,
+            users: Vec<u64>,
+            event: Event,
         ) -> Option<()> {
             self.sender
-                .send(CombineData::new(id, TelegramData::from_enter(time, view)))
+                .send(CombineData::Direct(id, users, event))
                 .await
                 .map(|_| ())
                 .ok()
@@ -154,23 +73,24 @@ mod types {
         config_id: String,
     }
 
-    impl BindTelegramHelper {
-        pub async fn send_left(
-            &self,
-            time: String,
-            view: &NotifyClientLeftView,
-            nickname: String,
-        ) -> Option<()> {
-            self.inner
-                .send_left(self.config_id.clone(), time, view, nickname)
-                .await
+    #[async_trait]
+    impl Notifier for BindTelegramHelper {
+        async fn notify(&self, event: &Event) -> Option<()> {
+            self.inner.send(self.config_id.clone(), event.clone()).await
+        }
+
+        fn bridge(&self) -> bool {
+            true
         }
-        pub async fn send_enter(&self, time: String, view: &NotifyClientEnterView) -> Option<()> {
+
+        async fn notify_users(&self, users: &[u64], event: &Event) -> Option<()> {
             self.inner
-                .send_enter(self.config_id.clone(), time, view)
+                .send_direct(self.config_id.clone(), users.to_vec(), event.clone())
                 .await
         }
+    }
 
+    impl BindTelegramHelper {
         fn new(config_id: String, helper: TelegramHelper) -> Self {
             Self {
                 config_id,
@@ -181,6 +101,103 @@ mod types {
 
     pub type BotType = DefaultParseMode<Bot>;
 
+    /// Escape user controlled text for telegram HTML parse mode
+    pub fn escape_html(text: &str) -> String {
+        let mut output = String::with_capacity(text.len());
+        for c in text.chars() {
+            match c {
+                '&' => output.push_str("&amp;"),
+                '<' => output.push_str("&lt;"),
+                '>' => output.push_str("&gt;"),
+                '"' => output.push_str("&quot;"),
+                _ => output.push(c),
+            }
+        }
+        output
+    }
+
+    /// Remove tags and unescape entities, for resending message as plain text
+    pub fn strip_html(text: &str) -> String {
+        let mut output = String::with_capacity(text.len());
+        let mut rest = text;
+        while let Some(start) = rest.find('<') {
+            let Some(length) = rest[start..].find('>') else {
+                break;
+            };
+            output.push_str(&rest[..start]);
+            rest = &rest[start + length + 1..];
+        }
+        output.push_str(rest);
+        output
+            .replace("&lt;", "<")
+            .replace("&gt;", ">")
+            .replace("&quot;", "\"")
+            .replace("&amp;", "&")
+    }
+
+    /// Maximum characters of telegram message
+    pub(super) const MESSAGE_LIMIT: usize = 4096;
+
+    /// Split message by lines to fit length limit, line which is too long will be split by characters
+    pub(super) fn split_message(text: &str, limit: usize) -> Vec<String> {
+        let mut parts = vec![];
+        let mut current = String::new();
+        let mut length = 0;
+        for line in text.split('\n') {
+            let line_length = line.chars().count();
+            if length > 0 && length + 1 + line_length > limit {
+                parts.push(std::mem::take(&mut current));
+                length = 0;
+            }
+            if line_length > limit {
+                let chars = line.chars().collect::<Vec<_>>();
+                let mut chunks = chars
+                    .chunks(limit)
+                    .map(String::from_iter)
+                    .collect::<Vec<_>>();
+                let last = chunks.pop().unwrap_or_default();
+                parts.extend(chunks);
+                length = last.chars().count();
+                current = last;
+                continue;
+            }
+            if length > 0 {
+                current.push('\n');
+                length += 1;
+            }
+            current.push_str(line);
+            length += line_length;
+        }
+        if !current.is_empty() || parts.is_empty() {
+            parts.push(current);
+        }
+        parts
+    }
+
+    /// Send HTML message, resend as plain text if telegram unable parse it
+    pub async fn send_html(
+        bot: &BotType,
+        chat_id: ChatId,
+        text: String,
+        keyboard: Option<InlineKeyboardMarkup>,
+    ) -> Result<Message, RequestError> {
+        let mut request = bot.send_message(chat_id, &text);
+        if let Some(keyboard) = keyboard.clone() {
+            request = request.reply_markup(keyboard);
+        }
+        match request.await {
+            Err(RequestError::Api(ApiError::CantParseEntities(e))) => {
+                warn!("Telegram unable parse message ({e}), resend as plain text");
+                let mut request = bot.inner().send_message(chat_id, strip_html(&text));
+                if let Some(keyboard) = keyboard {
+                    request = request.reply_markup(keyboard);
+                }
+                request.await
+            }
+            ret => ret,
+        }
+    }
+
     #[derive(Clone, Debug)]
     pub(super) struct TelegramBot {
         bot: BotType,
@@ -197,80 +214,123 @@ mod types {
             })
         }
 
-        pub fn send(
+        pub async fn send(&self, message: String) -> Result<Message, RequestError> {
+            send_html(&self.bot, ChatId(self.channel_id), message, None).await
+        }
+
+        pub async fn send_to(
             &self,
+            chat_id: i64,
             message: String,
-        ) -> impl std::future::Future<Output = Result<teloxide::prelude::Message, teloxide::RequestError>>
-        {
-            self.bot
-                .send_message(ChatId(self.channel_id), message)
-                .send()
+        ) -> Result<Message, RequestError> {
+            send_html(&self.bot, ChatId(chat_id), message, None).await
         }
 
-        pub fn into_inner(self) -> BotType {
-            self.bot
+        pub async fn send_with_keyboard(
+            &self,
+            message: String,
+            keyboard: InlineKeyboardMarkup,
+        ) -> Result<Message, RequestError> {
+            send_html(&self.bot, ChatId(self.channel_id), message, Some(keyboard)).await
         }
 
-        pub fn valid(&self) -> bool {
-            self.channel_id != 0
+        pub async fn edit(
+            &self,
+            chat_id: i64,
+            message_id: i32,
+            message: String,
+        ) -> Result<Message, RequestError> {
+            self.bot
+                .edit_message_text(ChatId(chat_id), MessageId(message_id), message)
+                .await
         }
-    }
 
-    pub(super) struct PoolIter<'a> {
-        name: &'a str,
-        iter: std::slice::Iter<'a, String>,
-    }
+        /// Pin message silently
+        pub async fn pin(&self, chat_id: i64, message_id: i32) -> Result<(), RequestError> {
+            self.bot
+                .pin_chat_message(ChatId(chat_id), MessageId(message_id))
+                .disable_notification(true)
+                .await
+                .map(|_| ())
+        }
 
-    impl<'a> From<(&'a str, std::slice::Iter<'a, String>)> for PoolIter<'a> {
-        fn from(value: (&'a str, std::slice::Iter<'a, String>)) -> Self {
-            Self {
-                name: value.0,
-                iter: value.1,
-            }
+        pub fn into_inner(self) -> BotType {
+            self.bot
         }
-    }
-    impl<'a> Iterator for PoolIter<'a> {
-        type Item = (&'a str, &'a String);
 
-        fn next(&mut self) -> Option<Self::Item> {
-            self.iter.next().map(|s| (self.name, s))
+        pub fn valid(&self) -> bool {
+            self.channel_id != 0
         }
     }
 }
 
 mod thread {
-    use super::types::{CombineData, TelegramBot, TelegramData, TelegramHelper};
+    use super::types::{CombineData, MESSAGE_LIMIT, TelegramBot, TelegramHelper, split_message};
     use crate::{
-        configure::Config,
-        types::{ConfigMappedUserState, SafeUserState},
+        configure::{Config, config::Roles},
+        locale::Templates,
+        notifier::{Event, EventFilter},
+        plugins::KVMap,
+        types::{ConfigMappedSender, ConfigMappedUserState},
     };
     use anyhow::anyhow;
     use bot_impl::ResponderPool;
+    use futures_util::future::join_all;
     use log::{debug, error, info, warn};
     use std::collections::HashMap;
     use std::sync::Arc;
     use std::time::Duration;
+    use teloxide::RequestError;
     use tokio::sync::{Notify, broadcast, mpsc};
+    use tokio::time::Instant;
 
     const QUERY_BOT_ERROR: &str = "Query bot error";
 
+    /// Configure which bot should respond
+    pub(super) struct ResponderConfig {
+        config_id: String,
+        allowed_chat: Vec<i64>,
+        bridge: Option<i64>,
+        roles: Roles,
+        /// Chat which unauthorized attempts are audited to
+        target: i64,
+    }
+
+    /// Bot id with configures which bot should respond relationship
+    type BotResponder = HashMap<String, Vec<ResponderConfig>>;
+
+    /// Configure id with tracker database file relationship
+    pub(super) type TrackerMap = HashMap<String, String>;
+
+    /// Configure id with message templates relationship
+    type TemplateMap = HashMap<String, Templates>;
+
+    /// Configure id with event filter relationship
+    type FilterMap = HashMap<String, EventFilter>;
+
+    /// Configure id with events held in quiet hours, event type with rendered message
+    type DigestMap = HashMap<String, Vec<(String, String)>>;
+
+    /// Configure id with status message chat and debounce relationship
+    type StatusMap = HashMap<String, (i64, Duration)>;
+
     pub fn telegram_bootstrap(
         configs: &Vec<(String, Config)>,
         notifier: Arc<Notify>,
-    ) -> anyhow::Result<(
-        tokio::task::JoinHandle<anyhow::Result<()>>,
-        TelegramHelper,
-        ConfigMappedUserState,
-    )> {
+        server_sender: ConfigMappedSender,
+        user_state_map: ConfigMappedUserState,
+        outbox: Option<Box<dyn KVMap>>,
+        status: Option<Box<dyn KVMap>>,
+    ) -> anyhow::Result<(tokio::task::JoinHandle<anyhow::Result<()>>, TelegramHelper)> {
         // A hashmap container configure and bot relationship
         let mut config_map = HashMap::new();
         // A hashmap container bot instance
         let mut bot_map = HashMap::new();
 
-        // A hashmap container channel-client relationship
-        let mut user_state_map = ConfigMappedUserState::new();
-
         let mut bot_responder = HashMap::new();
+        let mut template_map = TemplateMap::new();
+        let mut filter_map = FilterMap::new();
+        let mut status_map = StatusMap::new();
         // A hashmap container bot id with messages relationship (Queue is configure id with unsent message)
         //let mut pool_map: HashMap<String, HashMap<String, MessageQueue<String>>> = HashMap::new();
         for (_, config) in configs {
@@ -279,7 +339,6 @@ mod thread {
             // Check is config available in Telegram
             if config.telegram().api_key().is_empty() {
                 info!("Configure: [{config_id}] token is empty, skipped all send message request.",);
-                user_state_map.insert(config_id, SafeUserState::create_none());
                 continue;
             }
 
@@ -289,19 +348,44 @@ mod thread {
                 continue;
             };
 
-            if config.telegram().responsible() {
-                user_state_map.insert(config_id.clone(), SafeUserState::create());
-                // If responsible, insert to bot responder
+            let bridge = config.bridge().enable().then(|| config.bridge().chat());
+            if config.telegram().responsible() || bridge.is_some() {
+                // If responsible or bridge enabled, insert to bot responder
+                let allowed_chat = if config.telegram().responsible() {
+                    config.telegram().allowed_chat().to_vec()
+                } else {
+                    vec![]
+                };
                 bot_responder
                     .entry(bot_id.to_string())
                     .or_insert_with(Vec::new)
-                    .push((config_id.clone(), config.telegram().allowed_chat().to_vec()));
-            } else {
-                user_state_map.insert(config_id.clone(), SafeUserState::create_none());
+                    .push(ResponderConfig {
+                        config_id: config_id.clone(),
+                        allowed_chat,
+                        bridge,
+                        roles: config.telegram().roles().clone(),
+                        target: config.telegram().target(),
+                    });
             }
 
             // If bot id is correct, insert into configure map
             config_map.insert(config_id.clone(), bot_id.to_string());
+            template_map.insert(config_id.clone(), Templates::new(config.telegram()));
+            filter_map.insert(
+                config_id.clone(),
+                EventFilter::new(config.telegram().filter())
+                    .map_err(|e| anyhow!("Configure: [{config_id}] {e}"))?,
+            );
+            if config.telegram().status().enable() {
+                let status = config.telegram().status();
+                status_map.insert(
+                    config_id.clone(),
+                    (
+                        status.chat().unwrap_or(config.telegram().target()),
+                        Duration::from_secs(status.debounce()),
+                    ),
+                );
+            }
 
             // Check is bot has been created (maybe used by another configure)
             if !bot_map.contains_key(bot_id) {
@@ -314,12 +398,36 @@ mod thread {
                             config.telegram().target(),
                         )
                         .map_err(|e| anyhow!("Parse error: {e:?}"))?,
-                        vec![],
+                        Outbox::default(),
                     ),
                 );
             }
         }
 
+        #[cfg(feature = "tracker")]
+        let tracker_map = configs
+            .iter()
+            .filter(|(_, config)| config.telegram().responsible())
+            .filter_map(|(_, config)| {
+                config
+                    .server()
+                    .track_channel_member()
+                    .clone()
+                    .map(|database| (config.get_id(), database))
+            })
+            .collect();
+        #[cfg(not(feature = "tracker"))]
+        let tracker_map = TrackerMap::new();
+
+        let store = outbox.map(|kv_map| OutboxStore {
+            kv_map,
+            configs: configs
+                .iter()
+                .filter(|(_, config)| config.telegram().outbox())
+                .map(|(_, config)| config.get_id())
+                .collect(),
+        });
+
         let (sender, receiver) = TelegramHelper::new();
 
         let handler = if config_map.is_empty() {
@@ -330,84 +438,401 @@ mod thread {
                 bot_map,
                 config_map,
                 notifier,
-                (user_state_map.clone(), bot_responder),
+                (
+                    user_state_map,
+                    bot_responder,
+                    server_sender,
+                    tracker_map,
+                    (template_map, filter_map),
+                ),
+                store,
+                (status_map, status),
             ))
         };
-        Ok((handler, sender, user_state_map))
+        Ok((handler, sender))
+    }
+
+    /// Maximum events in one message
+    const BATCH_SIZE: usize = 8;
+    /// Maximum delay of exponential backoff
+    const MAX_BACKOFF: Duration = Duration::from_secs(300);
+
+    /// Pending messages of bot with retry state
+    #[derive(Debug, Default)]
+    pub(super) struct Outbox {
+        /// Configure id with rendered message
+        queue: Vec<(String, String)>,
+        /// Don't send until this time
+        retry_at: Option<Instant>,
+        failures: u32,
+        /// Parts of head batch already delivered, batch is split only if single event is too long
+        sent_parts: usize,
+        /// Queue changed since last persisted
+        dirty: bool,
+    }
+
+    impl Outbox {
+        fn push(&mut self, config_id: String, message: String) {
+            self.queue.push((config_id, message));
+            self.dirty = true;
+        }
+
+        fn clear(&mut self) {
+            self.dirty |= !self.queue.is_empty();
+            self.queue.clear();
+            self.sent_parts = 0;
+        }
+
+        fn ready(&self) -> bool {
+            self.retry_at.is_none_or(|time| time <= Instant::now())
+        }
+
+        fn backoff(&mut self) -> Duration {
+            let delay = Duration::from_secs(1 << self.failures.min(9)).min(MAX_BACKOFF);
+            self.failures += 1;
+            self.retry_at = Some(Instant::now() + delay);
+            delay
+        }
+    }
+
+    /// Take events from queue head until reach [`BATCH_SIZE`] or message length limit,
+    /// return events count with message
+    pub(super) fn batch(queue: &[(String, String)]) -> (usize, String) {
+        let mut message = String::new();
+        let mut prev = None;
+        let mut count = 0;
+        for (config_id, text) in queue.iter().take(BATCH_SIZE) {
+            let header = if prev != Some(config_id) {
+                format!("{config_id}\n")
+            } else {
+                String::new()
+            };
+            let length = message.chars().count() + header.chars().count() + text.chars().count();
+            if count > 0 && length + 1 > MESSAGE_LIMIT {
+                break;
+            }
+            if count > 0 {
+                message.push('\n');
+            }
+            message.push_str(&header);
+            message.push_str(text);
+            prev = Some(config_id);
+            count += 1;
+        }
+        (count, message)
+    }
+
+    /// Key of events held in quiet hours in KV database
+    const DIGEST_KEY: &str = "telegram_digest";
+
+    /// Persist pending messages in KV database, which will be replayed at startup
+    struct OutboxStore {
+        kv_map: Box<dyn KVMap>,
+        /// Configures which enabled persistent outbox
+        configs: Vec<String>,
+    }
+
+    impl OutboxStore {
+        fn build_key(bot_id: &str) -> String {
+            format!("telegram_outbox_{bot_id}")
+        }
+
+        async fn load(&mut self, bot_id: &str) -> anyhow::Result<Vec<(String, String)>> {
+            Ok(match self.kv_map.get(Self::build_key(bot_id)).await? {
+                Some(value) => serde_json::from_str(&value)?,
+                None => vec![],
+            })
+        }
+
+        async fn load_digest(&mut self) -> anyhow::Result<DigestMap> {
+            Ok(match self.kv_map.get(DIGEST_KEY.to_string()).await? {
+                Some(value) => serde_json::from_str(&value)?,
+                None => DigestMap::new(),
+            })
+        }
+
+        /// Events held in quiet hours are persisted too, they are lost on crash otherwise
+        async fn save_digest(&mut self, digest_map: &DigestMap) -> anyhow::Result<()> {
+            let digest_map = digest_map
+                .iter()
+                .filter(|(config_id, _)| self.configs.contains(config_id))
+                .collect::<HashMap<_, _>>();
+            if digest_map.is_empty() {
+                return self.kv_map.delete(DIGEST_KEY.to_string()).await;
+            }
+            self.kv_map
+                .set(DIGEST_KEY.to_string(), serde_json::to_string(&digest_map)?)
+                .await
+                .map(|_| ())
+        }
+
+        async fn save(&mut self, bot_id: &str, queue: &[(String, String)]) -> anyhow::Result<()> {
+            let queue = queue
+                .iter()
+                .filter(|(config_id, _)| self.configs.contains(config_id))
+                .collect::<Vec<_>>();
+            if queue.is_empty() {
+                return self.kv_map.delete(Self::build_key(bot_id)).await;
+            }
+            self.kv_map
+                .set(Self::build_key(bot_id), serde_json::to_string(&queue)?)
+                .await
+                .map(|_| ())
+        }
+    }
+
+    async fn send_batch(bot_id: &str, bot: &TelegramBot, outbox: &mut Outbox) {
+        if outbox.queue.is_empty() || !outbox.ready() {
+            return;
+        }
+        if !bot.valid() {
+            outbox.clear();
+            return;
+        }
+
+        let mut sent = 0;
+        while sent < outbox.queue.len() {
+            let (count, message) = batch(&outbox.queue[sent..]);
+            let mut result = Ok(());
+            // Skip parts delivered before last failure, so they are not posted twice
+            for part in split_message(&message, MESSAGE_LIMIT)
+                .into_iter()
+                .skip(outbox.sent_parts)
+            {
+                if let Err(e) = bot.send(part).await {
+                    result = Err(e);
+                    break;
+                }
+                outbox.sent_parts += 1;
+            }
+            match result {
+                Ok(()) => {
+                    outbox.failures = 0;
+                    outbox.sent_parts = 0;
+                    sent += count;
+                }
+                Err(RequestError::RetryAfter(seconds)) => {
+                    warn!("Bot {bot_id} is rate limited, retry after {seconds}");
+                    outbox.retry_at = Some(Instant::now() + seconds.duration());
+                    break;
+                }
+                Err(e @ (RequestError::Network(_) | RequestError::Io(_))) => {
+                    let delay = outbox.backoff();
+                    error!(
+                        "Got error in {bot_id} send telegram message, retry after {}s: {e:?}",
+                        delay.as_secs()
+                    );
+                    break;
+                }
+                Err(e) => {
+                    error!(
+                        "Got error in {bot_id} send telegram message, drop {count} events: {e:?}"
+                    );
+                    outbox.sent_parts = 0;
+                    sent += count;
+                }
+            }
+        }
+        if sent > 0 {
+            outbox.queue.drain(..sent);
+            outbox.dirty = true;
+        }
     }
 
     async fn telegram_thread(
         mut receiver: mpsc::Receiver<CombineData>,
-        mut bot_map: HashMap<String, (TelegramBot, Vec<(String, TelegramData)>)>,
+        mut bot_map: HashMap<String, (TelegramBot, Outbox)>,
         config_map: HashMap<String, String>,
         notifier: Arc<Notify>,
-        (user_state, bot_responder): (
+        (user_state, bot_responder, server_sender, tracker_map, (template_map, filter_map)): (
             ConfigMappedUserState,
-            HashMap<String, Vec<(String, Vec<i64>)>>,
+            BotResponder,
+            ConfigMappedSender,
+            TrackerMap,
+            (TemplateMap, FilterMap),
         ),
+        mut store: Option<OutboxStore>,
+        (status_map, status_kv): (StatusMap, Option<Box<dyn KVMap>>),
     ) -> anyhow::Result<()> {
         if bot_map.is_empty() {
             info!("No configure found, Send to telegram disabled.");
             return Ok(());
         }
-        //let mut queue = Vec::new();
-        let mut pending = Vec::new();
         let mut interval = tokio::time::interval(Duration::from_secs(1));
+        let default_templates = Templates::default();
+        let mut digest_map = DigestMap::new();
+        // Digest map changed since last persisted
+        let mut digest_dirty = false;
+
+        if let Some(store) = &mut store {
+            for (bot_id, (_, outbox)) in &mut bot_map {
+                match store.load(bot_id).await {
+                    Ok(queue) => {
+                        if !queue.is_empty() {
+                            info!(
+                                "Replay {} messages of bot {bot_id} from outbox",
+                                queue.len()
+                            );
+                        }
+                        outbox.queue.extend(
+                            queue
+                                .into_iter()
+                                .filter(|(config_id, _)| config_map.get(config_id) == Some(bot_id)),
+                        );
+                    }
+                    Err(e) => error!("Unable load outbox of bot {bot_id}: {e:?}"),
+                }
+            }
+            match store.load_digest().await {
+                Ok(held) => digest_map.extend(
+                    held.into_iter()
+                        .filter(|(config_id, _)| config_map.contains_key(config_id)),
+                ),
+                Err(e) => error!("Unable load quiet hours digest: {e:?}"),
+            }
+        }
+
+        // Configures which can handle callback query from telegram
+        let interactive = bot_responder
+            .values()
+            .flatten()
+            .filter(|config| !config.allowed_chat.is_empty())
+            .map(|config| config.config_id.clone())
+            .collect::<Vec<_>>();
+        // Configure id with bridge chat relationship
+        let bridge = bot_responder
+            .values()
+            .flatten()
+            .filter_map(|config| config.bridge.map(|chat| (config.config_id.clone(), chat)))
+            .collect::<HashMap<_, _>>();
 
         let (exit_sender, exit_signal) = broadcast::channel(5);
-        let response_pool =
-            ResponderPool::spawn(user_state, bot_responder, bot_map.clone(), exit_signal).await;
+        let status_handles = status::spawn(
+            status_map,
+            status_kv,
+            &user_state,
+            |config_id| {
+                config_map
+                    .get(config_id)
+                    .map(|bot_id| bot_map.get(bot_id).expect(QUERY_BOT_ERROR).0.clone())
+            },
+            &exit_signal,
+        );
+        let response_pool = ResponderPool::spawn(
+            user_state,
+            bot_responder,
+            &bot_map,
+            Arc::new(server_sender),
+            Arc::new(tracker_map),
+            exit_signal,
+        )
+        .await;
         loop {
             tokio::select! {
                 cmd = receiver.recv() => {
-                    let Some(CombineData::Send(config_id, data)) = cmd else {
-                        break;
+                    let (config_id, data) = match cmd {
+                        Some(CombineData::Send(config_id, data)) => (config_id, data),
+                        Some(CombineData::Direct(config_id, users, data)) => {
+                            let Some(bot_id) = config_map.get(&config_id) else {
+                                continue;
+                            };
+                            let (bot, _) = bot_map.get(bot_id).expect(QUERY_BOT_ERROR);
+                            let templates = template_map.get(&config_id).unwrap_or(&default_templates);
+                            let message = format!("{config_id}\n{}", data.render(templates));
+                            for user in users {
+                                // User may not start conversation with bot
+                                bot.send_to(user as i64, message.clone())
+                                    .await
+                                    .inspect_err(|e| warn!("Unable send message to telegram user {user}: {e:?}"))
+                                    .ok();
+                            }
+                            continue;
+                        }
+                        None => break,
+                    };
+                    let Some(bot_id) = config_map.get(&config_id) else {
+                        continue;
                     };
-                    if let Some(bot_id) = config_map.get(&config_id) {
-                        bot_map
-                            .get_mut(bot_id)
-                            .expect(QUERY_BOT_ERROR)
-                            .1
-                            .push((config_id, data));
+                    let (bot, outbox) = bot_map.get_mut(bot_id).expect(QUERY_BOT_ERROR);
+                    let templates = template_map.get(&config_id).unwrap_or(&default_templates);
+                    let message = data.render(templates);
+                    // Chat is relayed to bridge chat directly
+                    if matches!(data, Event::Chat(..)) {
+                        if let Some(chat) = bridge.get(&config_id) {
+                            bot.send_to(*chat, message)
+                                .await
+                                .inspect_err(|e| error!("Got error in {bot_id} relay chat {e:?}"))
+                                .ok();
+                        }
+                        continue;
                     }
-                }
-
-                // Tick by timer
-                _ = interval.tick() => {
-                    for (bot_id, (bot, queue)) in &mut bot_map {
-                        if queue.is_empty() {
+                    if let Some(filter) = filter_map.get(&config_id) {
+                        if !filter.accept(&data) {
                             continue;
                         }
-
-                        if !bot.valid() {
-                            queue.clear();
+                        // Raid alert is never held
+                        if !matches!(data, Event::Raid(..))
+                            && filter.quiet(chrono::Local::now().time())
+                        {
+                            digest_map
+                                .entry(config_id)
+                                .or_default()
+                                .push((data.event_type().to_string(), message));
+                            digest_dirty = true;
                             continue;
                         }
+                    }
+                    // Raid alert should be sent immediately with end lockdown button
+                    if matches!(data, Event::Raid(..))
+                        && bot.valid()
+                        && outbox.ready()
+                        && interactive.contains(&config_id)
+                    {
+                        match bot
+                            .send_with_keyboard(
+                                format!("{config_id}\n{message}"),
+                                bot_impl::lockdown_keyboard(&config_id),
+                            )
+                            .await
+                        {
+                            Ok(_) => continue,
+                            Err(e) => error!("Got error in {bot_id} send raid alert {e:?}"),
+                        }
+                    }
+                    outbox.push(config_id, message);
+                }
 
-                        let mut sent = 0;
-
-                        for chunk in queue.chunks(8) {
-                            let mut prev = &String::new();
-                            for (config_id, data) in chunk {
-                                if !config_id.eq(prev) {
-                                    pending.push(config_id.clone());
-                                    prev = config_id;
-                                }
-                                pending.push(data.to_string());
-                            }
-                            let message = pending.join("\n");
-                            pending.clear();
-
-                            if let Err(e) = bot.send(message).await {
-                                error!("Got error in {bot_id} send telegram message {e:?}");
-                                break;
+                // Tick by timer
+                _ = interval.tick() => {
+                    let now = chrono::Local::now().time();
+                    let ended = digest_map
+                        .keys()
+                        .filter(|config_id| {
+                            !filter_map
+                                .get(*config_id)
+                                .is_some_and(|filter| filter.quiet(now))
+                        })
+                        .cloned()
+                        .collect::<Vec<_>>();
+                    for config_id in ended {
+                        let held = digest_map.remove(&config_id).unwrap_or_default();
+                        push_digest(&mut bot_map, &config_map, config_id, &held);
+                        digest_dirty = true;
+                    }
+                    for (bot_id, (bot, outbox)) in &mut bot_map {
+                        send_batch(bot_id, bot, outbox).await;
+                        if let Some(store) = store.as_mut().filter(|_| outbox.dirty) {
+                            match store.save(bot_id, &outbox.queue).await {
+                                Ok(()) => outbox.dirty = false,
+                                Err(e) => error!("Unable save outbox of bot {bot_id}: {e:?}"),
                             }
-                            sent += chunk.len();
                         }
-                        if sent >= queue.len() {
-                            queue.clear()
-                        } else {
-                            queue.drain(..sent);
+                    }
+                    // Save after outbox, so flushed digest is never lost between them
+                    if let Some(store) = store.as_mut().filter(|_| digest_dirty) {
+                        match store.save_digest(&digest_map).await {
+                            Ok(()) => digest_dirty = false,
+                            Err(e) => error!("Unable save quiet hours digest: {e:?}"),
                         }
                     }
                 }
@@ -418,15 +843,82 @@ mod thread {
         }
         exit_sender.send(true).ok();
 
+        // Held events are sent by outbox, which may be persisted
+        for (config_id, held) in digest_map {
+            push_digest(&mut bot_map, &config_map, config_id, &held);
+        }
+
+        if let Some(store) = &mut store {
+            for (bot_id, (_, outbox)) in bot_map.iter().filter(|(_, (_, outbox))| outbox.dirty) {
+                store
+                    .save(bot_id, &outbox.queue)
+                    .await
+                    .inspect_err(|e| error!("Unable save outbox of bot {bot_id}: {e:?}"))
+                    .ok();
+            }
+            store
+                .save_digest(&DigestMap::new())
+                .await
+                .inspect_err(|e| error!("Unable save quiet hours digest: {e:?}"))
+                .ok();
+        }
+
         match tokio::time::timeout(Duration::from_secs(3), response_pool.wait()).await {
             Ok(ret) => ret?,
             Err(_) => warn!("Responder exit timeout"),
         };
+        if tokio::time::timeout(Duration::from_secs(3), join_all(status_handles))
+            .await
+            .is_err()
+        {
+            warn!("Status message updater exit timeout");
+        }
 
         debug!("Send message daemon exiting...");
         Ok(())
     }
 
+    /// Combine events held in quiet hours into one message
+    pub(super) fn digest(held: &[(String, String)]) -> String {
+        let mut counts: Vec<(&str, usize)> = vec![];
+        for (event_type, _) in held {
+            match counts.iter_mut().find(|(t, _)| t == event_type) {
+                Some((_, count)) => *count += 1,
+                None => counts.push((event_type, 1)),
+            }
+        }
+        format!(
+            "<b>Quiet hours digest</b> ({})\n{}",
+            counts
+                .iter()
+                .map(|(event_type, count)| format!("{event_type}: {count}"))
+                .collect::<Vec<_>>()
+                .join(", "),
+            held.iter()
+                .map(|(_, message)| message.as_str())
+                .collect::<Vec<_>>()
+                .join("\n")
+        )
+    }
+
+    fn push_digest(
+        bot_map: &mut HashMap<String, (TelegramBot, Outbox)>,
+        config_map: &HashMap<String, String>,
+        config_id: String,
+        held: &[(String, String)],
+    ) {
+        if held.is_empty() {
+            return;
+        }
+        let Some((_, outbox)) = config_map
+            .get(&config_id)
+            .and_then(|bot_id| bot_map.get_mut(bot_id))
+        else {
+            return;
+        };
+        outbox.push(config_id, digest(held));
+    }
+
     async fn void_thread(
         mut receiver: mpsc::Receiver<CombineData>,
         notifier: Arc<Notify>,
@@ -446,9 +938,168 @@ mod thread {
         Ok(())
     }
 
+    /// Keep one pinned message per configure which shows online clients
+    mod status {
+        use super::super::types::{MESSAGE_LIMIT, TelegramBot, escape_html, split_message};
+        use super::StatusMap;
+        use crate::plugins::KVMap;
+        use crate::types::{ConfigMappedUserState, SafeUserState};
+        use log::{error, warn};
+        use std::sync::Arc;
+        use std::time::Duration;
+        use teloxide::{ApiError, RequestError};
+        use tokio::sync::{Mutex, broadcast};
+        use tokio::task::JoinHandle;
+
+        type SharedKV = Arc<Mutex<Box<dyn KVMap>>>;
+
+        fn build_key(config_id: &str) -> String {
+            format!("telegram_status_{config_id}")
+        }
+
+        pub(super) fn spawn(
+            status_map: StatusMap,
+            kv_map: Option<Box<dyn KVMap>>,
+            user_state: &ConfigMappedUserState,
+            get_bot: impl Fn(&str) -> Option<TelegramBot>,
+            exit_signal: &broadcast::Receiver<bool>,
+        ) -> Vec<JoinHandle<()>> {
+            let kv_map = kv_map.map(|kv_map| Arc::new(Mutex::new(kv_map)));
+            let mut v = vec![];
+            for (config_id, (chat, debounce)) in status_map {
+                let (Some(bot), Some(state)) = (
+                    get_bot(&config_id),
+                    user_state.get(&config_id).filter(|state| state.enabled()),
+                ) else {
+                    continue;
+                };
+                let updater = StatusMessage {
+                    config_id,
+                    bot,
+                    chat,
+                    kv_map: kv_map.clone(),
+                    message_id: None,
+                    last_text: String::new(),
+                };
+                v.push(tokio::spawn(updater.run(
+                    state.clone(),
+                    debounce,
+                    exit_signal.resubscribe(),
+                )));
+            }
+            v
+        }
+
+        struct StatusMessage {
+            config_id: String,
+            bot: TelegramBot,
+            chat: i64,
+            kv_map: Option<SharedKV>,
+            message_id: Option<i32>,
+            last_text: String,
+        }
+
+        impl StatusMessage {
+            async fn load(&mut self) -> anyhow::Result<()> {
+                if let Some(kv_map) = &self.kv_map {
+                    let value = kv_map.lock().await.get(build_key(&self.config_id)).await?;
+                    self.message_id = value.and_then(|value| value.parse().ok());
+                }
+                Ok(())
+            }
+
+            async fn save(&self, message_id: i32) -> anyhow::Result<()> {
+                if let Some(kv_map) = &self.kv_map {
+                    kv_map
+                        .lock()
+                        .await
+                        .set(build_key(&self.config_id), message_id.to_string())
+                        .await?;
+                }
+                Ok(())
+            }
+
+            async fn render(&self, state: &SafeUserState) -> Option<String> {
+                let state = state.read().await?;
+                let text = format!("<b>{}</b>\n{}", escape_html(&self.config_id), *state);
+                split_message(&text, MESSAGE_LIMIT).into_iter().next()
+            }
+
+            /// Edit pinned message, send and pin new one if it is gone
+            async fn update(&mut self, text: String) -> anyhow::Result<()> {
+                if let Some(message_id) = self.message_id {
+                    match self.bot.edit(self.chat, message_id, text.clone()).await {
+                        Ok(_) | Err(RequestError::Api(ApiError::MessageNotModified)) => {
+                            self.last_text = text;
+                            return Ok(());
+                        }
+                        Err(RequestError::Api(
+                            ApiError::MessageToEditNotFound | ApiError::MessageCantBeEdited,
+                        )) => {
+                            warn!(
+                                "[{}] Status message {message_id} is gone, send new one",
+                                self.config_id
+                            );
+                        }
+                        Err(e) => return Err(e.into()),
+                    }
+                }
+                let message = self.bot.send_to(self.chat, text.clone()).await?;
+                self.message_id = Some(message.id.0);
+                self.last_text = text;
+                self.save(message.id.0).await?;
+                self.bot.pin(self.chat, message.id.0).await?;
+                Ok(())
+            }
+
+            async fn run(
+                mut self,
+                state: SafeUserState,
+                debounce: Duration,
+                mut exit_signal: broadcast::Receiver<bool>,
+            ) {
+                let mut changed = state.subscribe();
+                self.load()
+                    .await
+                    .inspect_err(|e| {
+                        error!("[{}] Unable load status message id: {e:?}", self.config_id)
+                    })
+                    .ok();
+                loop {
+                    if let Some(text) = self
+                        .render(&state)
+                        .await
+                        .filter(|text| !text.eq(&self.last_text))
+                    {
+                        self.update(text)
+                            .await
+                            .inspect_err(|e| {
+                                error!("[{}] Unable update status message: {e:?}", self.config_id)
+                            })
+                            .ok();
+                    }
+                    tokio::select! {
+                        ret = changed.changed() => {
+                            if ret.is_err() {
+                                break;
+                            }
+                        }
+                        _ = exit_signal.recv() => break,
+                    }
+                    // Wait for further changes, avoid hitting rate limit
+                    tokio::select! {
+                        _ = tokio::time::sleep(debounce) => {}
+                        _ = exit_signal.recv() => break,
+                    }
+                    changed.mark_unchanged();
+                }
+            }
+        }
+    }
+
     /* async fn start_response_handler(
            user_state: ConfigMappedUserState,
-           bot_responder: HashMap<String, Vec<(String, Vec<i64>)>>,
+           bot_responder: HashMap<String, Vec<ResponderConfig>>,
            exit_signal: broadcast::Receiver<bool>,
        ) -> anyhow::Result<()> {
            for key in bot_responder.keys() {}
@@ -459,25 +1110,216 @@ mod thread {
     mod bot_impl {
         use std::{collections::HashMap, sync::Arc};
 
-        use log::warn;
+        use anyhow::anyhow;
+        use log::{error, info, warn};
         use teloxide::{
             dispatching::{HandlerExt as _, UpdateFilterExt as _},
             dptree,
+            payloads::AnswerCallbackQuerySetters as _,
             prelude::{Dispatcher, Requester as _},
-            types::{Message, Update},
+            types::{
+                CallbackQuery, ChatId, InlineKeyboardButton, InlineKeyboardMarkup, Message, Update,
+                User,
+            },
             utils::command::BotCommands,
         };
-        use tokio::{sync::broadcast, task::JoinHandle};
+        use tokio::{
+            sync::{broadcast, oneshot},
+            task::JoinHandle,
+        };
 
-        use crate::{telegram::types::BotType, types::ConfigMappedUserState};
+        use crate::{
+            configure::{
+                Config,
+                config::{Role, Roles},
+            },
+            observer::PrivateMessageRequest,
+            responder,
+            telegram::types::{BotType, MESSAGE_LIMIT, escape_html, send_html, split_message},
+            types::{ConfigMappedSender, ConfigMappedUserState, UserState},
+        };
 
-        use super::{TelegramBot, TelegramData};
+        use super::{Outbox, ResponderConfig, TelegramBot, TrackerMap};
+        use crate::moderation::Action;
+        use crate::watch::WatchRequest;
+        use std::sync::Mutex;
+        use std::sync::atomic::{AtomicU32, Ordering};
 
         #[derive(BotCommands, Clone)]
         #[command(rename_rule = "lowercase")]
         enum Command {
             Ping,
             List,
+            Top,
+            Stats(String),
+            Peak,
+            Seen(String),
+            Names(String),
+            Kick(String),
+            Ban(String),
+            Move(String),
+            Poke(String),
+            Msg(String),
+            Unban(String),
+            Watch(String),
+            Unwatch(String),
+            Watches,
+        }
+
+        impl Command {
+            /// Minimum role to run command, `None` if everyone can run it
+            fn role(&self) -> Option<Role> {
+                match self {
+                    Command::Ping => None,
+                    Command::List
+                    | Command::Top
+                    | Command::Stats(_)
+                    | Command::Peak
+                    | Command::Seen(_)
+                    | Command::Names(_)
+                    | Command::Watch(_)
+                    | Command::Unwatch(_)
+                    | Command::Watches => Some(Role::Viewer),
+                    Command::Kick(_) | Command::Move(_) | Command::Poke(_) | Command::Msg(_) => {
+                        Some(Role::Moderator)
+                    }
+                    Command::Ban(_) | Command::Unban(_) => Some(Role::Admin),
+                }
+            }
+
+            /// Moderation command name and arguments
+            fn moderation(&self) -> Option<(&'static str, &str)> {
+                match self {
+                    Command::Kick(args) => Some(("kick", args)),
+                    Command::Ban(args) => Some(("ban", args)),
+                    Command::Move(args) => Some(("move", args)),
+                    Command::Poke(args) => Some(("poke", args)),
+                    Command::Msg(args) => Some(("msg", args)),
+                    Command::Unban(args) => Some(("unban", args)),
+                    _ => None,
+                }
+            }
+
+            /// Presence subscription request, return usage if arguments are invalid
+            fn watch(&self) -> Option<Result<WatchRequest, &'static str>> {
+                match self {
+                    Command::Watch(args) => Some(WatchRequest::parse("watch", args)),
+                    Command::Unwatch(args) => Some(WatchRequest::parse("unwatch", args)),
+                    Command::Watches => Some(Ok(WatchRequest::List)),
+                    _ => None,
+                }
+            }
+        }
+
+        const LOCKDOWN_PREFIX: &str = "lockdown:";
+        const MODERATE_PREFIX: &str = "moderate:";
+        /// Seconds which moderation request should be confirmed in
+        const CONFIRM_TIMEOUT: u64 = 120;
+
+        /// Configure xxhash with configure id relationship, wrapped as tracker map has same type
+        pub struct ConfigHashMap(HashMap<String, String>);
+
+        /// Allowed chat with configure ids relationship
+        type ChatMap = HashMap<i64, Vec<String>>;
+
+        /// Configure id with roles and audit chat relationship
+        type AccessMap = HashMap<String, (Roles, i64)>;
+
+        /// Bridge chat with configure ids relationship
+        struct BridgeMap(HashMap<i64, Vec<String>>);
+
+        /// Maximum length of TeamSpeak text message
+        const TEAMSPEAK_MESSAGE_LIMIT: usize = 1000;
+
+        /// Moderation request which waits for confirmation of requester
+        struct Pending {
+            user_id: u64,
+            action: Action,
+            expire: u64,
+        }
+
+        /// Moderation requests which wait for confirmation
+        #[derive(Default)]
+        struct PendingModeration {
+            next_id: AtomicU32,
+            requests: Mutex<HashMap<u32, Pending>>,
+        }
+
+        impl PendingModeration {
+            fn insert(&self, user_id: u64, action: Action) -> u32 {
+                let now = kstool::time::get_current_second();
+                let id = self.next_id.fetch_add(1, Ordering::Relaxed);
+                let mut requests = self.requests.lock().unwrap();
+                requests.retain(|_, pending| pending.expire > now);
+                requests.insert(
+                    id,
+                    Pending {
+                        user_id,
+                        action,
+                        expire: now + CONFIRM_TIMEOUT,
+                    },
+                );
+                id
+            }
+
+            /// Take request if it is confirmed by requester, error is shown to user
+            fn take(&self, id: u32, user_id: u64) -> Result<Action, &'static str> {
+                let now = kstool::time::get_current_second();
+                let mut requests = self.requests.lock().unwrap();
+                match requests.get(&id) {
+                    Some(pending) if pending.expire <= now => {
+                        requests.remove(&id);
+                        Err("Request expired")
+                    }
+                    Some(pending) if pending.user_id != user_id => {
+                        Err("Only requester can confirm")
+                    }
+                    Some(_) => Ok(requests.remove(&id).unwrap().action),
+                    None => Err("Request expired"),
+                }
+            }
+        }
+
+        /// Describe telegram user in audit log
+        fn invoker(user: &User) -> String {
+            match &user.username {
+                Some(username) => format!("{} (@{username}, {})", user.full_name(), user.id),
+                None => format!("{} ({})", user.full_name(), user.id),
+            }
+        }
+
+        fn moderation_keyboard(id: u32, configs: &[String]) -> InlineKeyboardMarkup {
+            let mut rows = configs
+                .iter()
+                .map(|config_id| {
+                    [InlineKeyboardButton::callback(
+                        if configs.len() == 1 {
+                            "Confirm".to_string()
+                        } else {
+                            format!("Confirm on {config_id}")
+                        },
+                        format!(
+                            "{MODERATE_PREFIX}{id}:{}",
+                            Config::config_xxhash(config_id.as_bytes())
+                        ),
+                    )]
+                })
+                .collect::<Vec<_>>();
+            rows.push([InlineKeyboardButton::callback(
+                "Cancel",
+                format!("{MODERATE_PREFIX}{id}:"),
+            )]);
+            InlineKeyboardMarkup::new(rows)
+        }
+
+        pub fn lockdown_keyboard(config_id: &str) -> InlineKeyboardMarkup {
+            InlineKeyboardMarkup::new([[InlineKeyboardButton::callback(
+                "End lockdown",
+                format!(
+                    "{LOCKDOWN_PREFIX}{}",
+                    Config::config_xxhash(config_id.as_bytes())
+                ),
+            )]])
         }
 
         pub struct ResponderPool {
@@ -494,8 +1336,10 @@ mod thread {
 
             pub async fn spawn(
                 channel_map: ConfigMappedUserState,
-                bots: HashMap<String, Vec<(String, Vec<i64>)>>,
-                bot_map: HashMap<String, (TelegramBot, Vec<(String, TelegramData)>)>,
+                bots: HashMap<String, Vec<ResponderConfig>>,
+                bot_map: &HashMap<String, (TelegramBot, Outbox)>,
+                server_sender: Arc<ConfigMappedSender>,
+                tracker_map: Arc<TrackerMap>,
                 exit_signal: broadcast::Receiver<bool>,
             ) -> Self {
                 let mut v = vec![];
@@ -504,6 +1348,7 @@ mod thread {
                         bot_map.get(&bot_id).unwrap().0.clone(),
                         config_with_chat,
                         channel_map.clone(),
+                        (server_sender.clone(), tracker_map.clone()),
                         exit_signal.resubscribe(),
                     ));
                 }
@@ -518,67 +1363,216 @@ mod thread {
         impl Responder {
             pub fn spawn(
                 bot: TelegramBot,
-                config_with_chat: Vec<(String, Vec<i64>)>,
+                config_with_chat: Vec<ResponderConfig>,
                 channel_map: ConfigMappedUserState,
+                shared: (Arc<ConfigMappedSender>, Arc<TrackerMap>),
                 exit_signal: broadcast::Receiver<bool>,
             ) -> Self {
-                let handle =
-                    tokio::spawn(Self::run(bot, config_with_chat, channel_map, exit_signal));
+                let handle = tokio::spawn(Self::run(
+                    bot,
+                    config_with_chat,
+                    channel_map,
+                    shared,
+                    exit_signal,
+                ));
                 Self { handle }
             }
 
             fn build_chat_map(
-                config_with_chat: Vec<(String, Vec<i64>)>,
-            ) -> HashMap<i64, Vec<String>> {
+                config_with_chat: Vec<ResponderConfig>,
+            ) -> (ChatMap, BridgeMap, AccessMap) {
                 let mut m = HashMap::new();
-                for (config_id, chats) in config_with_chat {
-                    for chat in chats {
+                let mut bridge = HashMap::new();
+                let mut access = HashMap::new();
+                for config in config_with_chat {
+                    for chat in config.allowed_chat {
                         m.entry(chat)
                             .or_insert_with(Vec::new)
-                            .push(config_id.clone());
+                            .push(config.config_id.clone());
+                    }
+                    if let Some(chat) = config.bridge {
+                        bridge
+                            .entry(chat)
+                            .or_insert_with(Vec::new)
+                            .push(config.config_id.clone());
                     }
+                    access.insert(config.config_id, (config.roles, config.target));
                 }
-                m
+                (m, BridgeMap(bridge), access)
             }
 
             async fn run(
                 bot: TelegramBot,
-                config_with_chat: Vec<(String, Vec<i64>)>,
+                config_with_chat: Vec<ResponderConfig>,
                 channel_map: ConfigMappedUserState,
+                (server_sender, tracker_map): (Arc<ConfigMappedSender>, Arc<TrackerMap>),
                 mut exit_signal: broadcast::Receiver<bool>,
             ) -> anyhow::Result<()> {
                 let bot = bot.into_inner();
-                let chat_map = Arc::new(Self::build_chat_map(config_with_chat));
-                let handle_message = Update::filter_message().branch(
-                    dptree::entry()
-                        .filter(|msg: Message| {
-                            //log::debug!("{:?}", msg.chat);
-                            !msg.chat.is_channel()
+                let config_hash = Arc::new(ConfigHashMap(
+                    config_with_chat
+                        .iter()
+                        .map(|config| {
+                            (
+                                Config::config_xxhash(config.config_id.as_bytes()),
+                                config.config_id.clone(),
+                            )
+                        })
+                        .collect(),
+                ));
+                let (chat_map, bridge_map, access) = Self::build_chat_map(config_with_chat);
+                let chat_map = Arc::new(chat_map);
+                let bridge_map = Arc::new(bridge_map);
+                let access = Arc::new(access);
+                let handle_message = Update::filter_message()
+                    .branch(
+                        dptree::entry()
+                            .filter(|msg: Message| {
+                                //log::debug!("{:?}", msg.chat);
+                                !msg.chat.is_channel()
+                            })
+                            .filter_command::<Command>()
+                            .endpoint(
+                                |msg: Message,
+                                 bot: BotType,
+                                 cmd: Command,
+                                 chat_map: Arc<ChatMap>,
+                                 channel_map: ConfigMappedUserState,
+                                 tracker_map: Arc<TrackerMap>,
+                                 pending: Arc<PendingModeration>,
+                                 access: Arc<AccessMap>,
+                                 server_sender: Arc<ConfigMappedSender>| async move {
+                                    let configs = match (cmd.role(), msg.from.as_ref()) {
+                                        (Some(role), Some(user)) => {
+                                            authorize(
+                                                &bot,
+                                                (msg.chat.id, user),
+                                                (&chat_map, &access),
+                                                (role, msg.text().unwrap_or_default()),
+                                            )
+                                            .await
+                                        }
+                                        _ => vec![],
+                                    };
+                                    match cmd {
+                                        Command::Ping => handle_ping(bot, msg).await,
+                                        _ if configs.is_empty() => deny(bot, msg, chat_map).await,
+                                        Command::List => {
+                                            handle_list(bot, msg, configs, channel_map).await
+                                        }
+                                        Command::Top
+                                        | Command::Stats(_)
+                                        | Command::Peak
+                                        | Command::Seen(_)
+                                        | Command::Names(_) => {
+                                            handle_stats(
+                                                bot,
+                                                msg,
+                                                cmd,
+                                                configs,
+                                                (channel_map, tracker_map),
+                                            )
+                                            .await
+                                        }
+                                        Command::Kick(_)
+                                        | Command::Ban(_)
+                                        | Command::Move(_)
+                                        | Command::Poke(_)
+                                        | Command::Msg(_)
+                                        | Command::Unban(_) => {
+                                            handle_moderation(bot, msg, cmd, configs, pending).await
+                                        }
+                                        Command::Watch(_)
+                                        | Command::Unwatch(_)
+                                        | Command::Watches => {
+                                            handle_watch(bot, msg, cmd, configs, server_sender).await
+                                        }
+                                    }
+                                    .inspect_err(|e| log::error!("Handle command error: {e:?}"))
+                                },
+                            ),
+                    )
+                    .branch(
+                        dptree::filter(|msg: Message, bridge_map: Arc<BridgeMap>| {
+                            bridge_map.0.contains_key(&msg.chat.id.0)
                         })
-                        .filter_command::<Command>()
                         .endpoint(
                             |msg: Message,
+                             bridge_map: Arc<BridgeMap>,
+                             server_sender: Arc<ConfigMappedSender>| async move {
+                                handle_bridge(msg, bridge_map, server_sender)
+                                    .await
+                                    .inspect_err(|e| log::error!("Handle bridge error: {e:?}"))
+                            },
+                        ),
+                    );
+
+                let handle_callback = Update::filter_callback_query()
+                    .branch(
+                        dptree::filter(|query: CallbackQuery| {
+                            query
+                                .data
+                                .as_deref()
+                                .is_some_and(|data| data.starts_with(MODERATE_PREFIX))
+                        })
+                        .endpoint(
+                            |query: CallbackQuery,
                              bot: BotType,
-                             cmd: Command,
-                             chat_map: Arc<HashMap<i64, Vec<String>>>,
-                             channel_map: ConfigMappedUserState| async move {
-                                match cmd {
-                                    Command::Ping => handle_ping(bot, msg).await,
-                                    Command::List => {
-                                        handle_list(bot, msg, chat_map, channel_map).await
-                                    }
-                                }
-                                .inspect_err(|e| log::error!("Handle command error: {e:?}"))
+                             chat_map: Arc<ChatMap>,
+                             config_hash: Arc<ConfigHashMap>,
+                             server_sender: Arc<ConfigMappedSender>,
+                             pending: Arc<PendingModeration>,
+                             access: Arc<AccessMap>| async move {
+                                handle_confirm(
+                                    bot,
+                                    query,
+                                    (chat_map, config_hash, access),
+                                    server_sender,
+                                    pending,
+                                )
+                                .await
+                                .inspect_err(|e| log::error!("Handle confirm error: {e:?}"))
                             },
                         ),
-                );
+                    )
+                    .branch(dptree::endpoint(
+                        |query: CallbackQuery,
+                         bot: BotType,
+                         chat_map: Arc<ChatMap>,
+                         config_hash: Arc<ConfigHashMap>,
+                         server_sender: Arc<ConfigMappedSender>,
+                         access: Arc<AccessMap>| async move {
+                            handle_lockdown(
+                                bot,
+                                query,
+                                (chat_map, config_hash, access),
+                                server_sender,
+                            )
+                            .await
+                            .inspect_err(|e| log::error!("Handle callback query error: {e:?}"))
+                        },
+                    ));
 
-                let dispatcher = Dispatcher::builder(bot, dptree::entry().branch(handle_message))
-                    .dependencies(dptree::deps![chat_map, channel_map])
-                    /* .default_handler(|update| async move {
-                        log::debug!("Unhandled message {:?}", update.from());
-                    }) */
-                    .default_handler(|_| async {});
+                let dispatcher = Dispatcher::builder(
+                    bot,
+                    dptree::entry()
+                        .branch(handle_message)
+                        .branch(handle_callback),
+                )
+                .dependencies(dptree::deps![
+                    chat_map,
+                    channel_map,
+                    config_hash,
+                    server_sender,
+                    tracker_map,
+                    bridge_map,
+                    Arc::new(PendingModeration::default()),
+                    access
+                ])
+                /* .default_handler(|update| async move {
+                    log::debug!("Unhandled message {:?}", update.from());
+                }) */
+                .default_handler(|_| async {});
 
                 //#[cfg(not(debug_assertions))]
                 //dispatcher.enable_ctrlc_handler().build().dispatch().await;
@@ -598,44 +1592,547 @@ mod thread {
         }
 
         pub async fn handle_ping(bot: BotType, msg: Message) -> anyhow::Result<()> {
-            bot.send_message(
+            bot.send_message(msg.chat.id, responder::ping(&msg.chat.id.0.to_string()))
+                .await?;
+            Ok(())
+        }
+
+        /// Configures of chat which user has `role` of, unauthorized attempt is audited to target
+        async fn authorize(
+            bot: &BotType,
+            (chat_id, user): (ChatId, &User),
+            (chat_map, access): (&ChatMap, &AccessMap),
+            (role, request): (Role, &str),
+        ) -> Vec<String> {
+            let Some(configs) = chat_map.get(&chat_id.0) else {
+                warn!("Deny unauthorized access chat {chat_id}");
+                return vec![];
+            };
+            let permitted = configs
+                .iter()
+                .filter(|config_id| {
+                    access
+                        .get(*config_id)
+                        .and_then(|(roles, _)| roles.role(user.id.0))
+                        .is_some_and(|user_role| user_role >= role)
+                })
+                .cloned()
+                .collect::<Vec<_>>();
+            if !permitted.is_empty() {
+                return permitted;
+            }
+            warn!(
+                "Deny {request:?} from telegram user {} in chat {chat_id}",
+                user.id
+            );
+            for config_id in configs {
+                let Some((_, target)) = access.get(config_id) else {
+                    continue;
+                };
+                send_html(
+                    bot,
+                    ChatId(*target),
+                    format!(
+                        "{config_id}\n#unauthorized <b>{}</b> tried <code>{}</code> in chat <code>{chat_id}</code>",
+                        escape_html(&invoker(user)),
+                        escape_html(request)
+                    ),
+                    None,
+                )
+                .await
+                .inspect_err(|e| error!("Unable send audit message to {target}: {e:?}"))
+                .ok();
+            }
+            permitted
+        }
+
+        /// Reply user in allowed chat which has no permission
+        async fn deny(bot: BotType, msg: Message, chat_map: Arc<ChatMap>) -> anyhow::Result<()> {
+            if chat_map.contains_key(&msg.chat.id.0) {
+                bot.send_message(msg.chat.id, "Permission denied").await?;
+            }
+            Ok(())
+        }
+
+        pub async fn handle_list(
+            bot: BotType,
+            msg: Message,
+            configs: Vec<String>,
+            channel_map: ConfigMappedUserState,
+        ) -> anyhow::Result<()> {
+            send_html(
+                &bot,
                 msg.chat.id,
-                format!(
-                    "Chat id: <code>{id}</code>\nVersion: {version}",
-                    id = msg.chat.id.0,
-                    version = env!("CARGO_PKG_VERSION")
-                ),
+                responder::list(&configs, &channel_map).await,
+                None,
             )
             .await?;
+
             Ok(())
         }
 
-        pub async fn handle_list(
+        /// Parse moderation command and ask requester to confirm
+        async fn handle_moderation(
             bot: BotType,
             msg: Message,
-            chat_map: Arc<HashMap<i64, Vec<String>>>,
-            channel_map: ConfigMappedUserState,
+            cmd: Command,
+            configs: Vec<String>,
+            pending: Arc<PendingModeration>,
         ) -> anyhow::Result<()> {
-            let Some(configs) = chat_map.get(&msg.chat.id.0) else {
-                warn!("Deny unauthorized access chat {}", msg.chat.id);
+            let (Some(user), Some((command, args))) = (msg.from.as_ref(), cmd.moderation()) else {
                 return Ok(());
             };
+            let action = match Action::parse(command, args) {
+                Ok(action) => action,
+                Err(usage) => {
+                    bot.send_message(msg.chat.id, escape_html(usage)).await?;
+                    return Ok(());
+                }
+            };
+            let text = format!("Confirm to {}?", escape_html(&action.to_string()));
+            let id = pending.insert(user.id.0, action);
+            send_html(
+                &bot,
+                msg.chat.id,
+                text,
+                Some(moderation_keyboard(id, &configs)),
+            )
+            .await?;
+            Ok(())
+        }
+
+        /// Manage presence subscriptions of requester on every permitted configure
+        async fn handle_watch(
+            bot: BotType,
+            msg: Message,
+            cmd: Command,
+            configs: Vec<String>,
+            server_sender: Arc<ConfigMappedSender>,
+        ) -> anyhow::Result<()> {
+            let (Some(user), Some(request)) = (msg.from.as_ref(), cmd.watch()) else {
+                return Ok(());
+            };
+            let request = match request {
+                Ok(request) => request,
+                Err(usage) => {
+                    bot.send_message(msg.chat.id, escape_html(usage)).await?;
+                    return Ok(());
+                }
+            };
+            let mut replies = vec![];
+            for config_id in &configs {
+                let Some(sender) = server_sender.get(config_id) else {
+                    continue;
+                };
+                let (s, r) = oneshot::channel();
+                let text = if sender
+                    .send(PrivateMessageRequest::Watch(user.id.0, request.clone(), s))
+                    .await
+                    .is_err()
+                {
+                    "Server connection is gone".to_string()
+                } else {
+                    match r.await {
+                        Ok(Ok(reply)) => reply,
+                        Ok(Err(e)) => e.to_string(),
+                        Err(_) => "Server connection is gone".to_string(),
+                    }
+                };
+                replies.push(format!(
+                    "<b>{}</b>\n{}",
+                    escape_html(config_id),
+                    escape_html(&text)
+                ));
+            }
+            send_html(&bot, msg.chat.id, replies.join("\n\n"), None).await?;
+            Ok(())
+        }
+
+        /// Execute or cancel moderation request by inline keyboard
+        async fn handle_confirm(
+            bot: BotType,
+            query: CallbackQuery,
+            (chat_map, config_hash, access): (Arc<ChatMap>, Arc<ConfigHashMap>, Arc<AccessMap>),
+            server_sender: Arc<ConfigMappedSender>,
+            pending: Arc<PendingModeration>,
+        ) -> anyhow::Result<()> {
+            let Some((id, hash)) = query
+                .data
+                .as_deref()
+                .and_then(|data| data.strip_prefix(MODERATE_PREFIX))
+                .and_then(|data| data.split_once(':'))
+                .and_then(|(id, hash)| Some((id.parse::<u32>().ok()?, hash)))
+            else {
+                return Ok(());
+            };
+            let Some(message) = &query.message else {
+                return Ok(());
+            };
+            let chat_id = message.chat().id;
+
+            let action = match pending.take(id, query.from.id.0) {
+                Ok(action) => action,
+                Err(reason) => {
+                    bot.answer_callback_query(query.id.clone())
+                        .text(reason)
+                        .await?;
+                    return Ok(());
+                }
+            };
+            let Some(config_id) = config_hash.0.get(hash) else {
+                bot.answer_callback_query(query.id.clone())
+                    .text("Cancelled")
+                    .await?;
+                bot.edit_message_text(
+                    chat_id,
+                    message.id(),
+                    format!("Cancelled: {}", escape_html(&action.to_string())),
+                )
+                .await?;
+                return Ok(());
+            };
+            if !authorize(
+                &bot,
+                (chat_id, &query.from),
+                (&chat_map, &access),
+                (action.role(), &action.to_string()),
+            )
+            .await
+            .contains(config_id)
+            {
+                bot.answer_callback_query(query.id.clone())
+                    .text("Unauthorized")
+                    .await?;
+                return Ok(());
+            }
+
+            let sender = server_sender
+                .get(config_id)
+                .ok_or_else(|| anyhow!("Sender of {config_id} not found"))?;
+            let (s, r) = oneshot::channel();
+            sender
+                .send(PrivateMessageRequest::Moderate(
+                    invoker(&query.from),
+                    action.clone(),
+                    s,
+                ))
+                .await
+                .map_err(|_| anyhow!("Unable send moderation request to {config_id}"))?;
+            let text = match r.await {
+                Ok(Ok(description)) => format!("Done: {}", escape_html(&description)),
+                Ok(Err(e)) => format!(
+                    "Unable {}: {}",
+                    escape_html(&action.to_string()),
+                    escape_html(&e.to_string())
+                ),
+                Err(_) => "Server connection is gone".to_string(),
+            };
+            bot.answer_callback_query(query.id.clone()).await?;
+            bot.edit_message_text(chat_id, message.id(), format!("{config_id}\n{text}"))
+                .await?;
+            Ok(())
+        }
+
+        /// Relay group message to TeamSpeak, messages from bots and commands are ignored
+        async fn handle_bridge(
+            msg: Message,
+            bridge_map: Arc<BridgeMap>,
+            server_sender: Arc<ConfigMappedSender>,
+        ) -> anyhow::Result<()> {
+            let (Some(text), Some(user)) = (msg.text(), msg.from.as_ref()) else {
+                return Ok(());
+            };
+            if user.is_bot || text.starts_with('/') {
+                return Ok(());
+            }
+            let Some(configs) = bridge_map.0.get(&msg.chat.id.0) else {
+                return Ok(());
+            };
+            let message = format!("[TG] {}: {text}", user.full_name())
+                .chars()
+                .take(TEAMSPEAK_MESSAGE_LIMIT)
+                .collect::<String>();
+            for config_id in configs {
+                let Some(sender) = server_sender.get(config_id) else {
+                    continue;
+                };
+                sender
+                    .send(PrivateMessageRequest::Bridge(message.clone()))
+                    .await
+                    .map_err(|_| anyhow!("Unable send bridge message to {config_id}"))?;
+            }
+            Ok(())
+        }
+
+        async fn handle_stats(
+            bot: BotType,
+            msg: Message,
+            cmd: Command,
+            configs: Vec<String>,
+            (channel_map, tracker_map): (ConfigMappedUserState, Arc<TrackerMap>),
+        ) -> anyhow::Result<()> {
+            if let Some(usage) = match &cmd {
+                Command::Stats(argument) if argument.trim().is_empty() => {
+                    Some("/stats &lt;uid&gt;")
+                }
+                Command::Seen(argument) if argument.trim().is_empty() => {
+                    Some("/seen &lt;nickname|uid&gt;")
+                }
+                Command::Names(argument) if argument.trim().is_empty() => {
+                    Some("/names &lt;uid&gt;")
+                }
+                _ => None,
+            } {
+                bot.send_message(msg.chat.id, format!("Usage: {usage}"))
+                    .await?;
+                return Ok(());
+            }
             let mut v = vec![];
-            for config in configs {
-                let Some(map) = channel_map.get(config).filter(|s| s.enabled()) else {
-                    //warn!("State is empty, skip");
+            for config in &configs {
+                let Some(database) = tracker_map.get(config) else {
                     continue;
                 };
-                v.push(format!("{config}\n{}", map.read().await.unwrap()));
+                let state = match channel_map.get(config) {
+                    Some(state) => state.read().await,
+                    None => None,
+                };
+                match query_tracker(database, &cmd, state.as_deref()).await {
+                    Ok(text) => v.push(format!("{config}\n{}", escape_html(&text))),
+                    Err(e) => {
+                        error!("[{config}] Unable query tracker database: {e:?}");
+                        v.push(format!("{config}\nQuery failed"));
+                    }
+                }
+            }
+            if v.is_empty() {
+                v.push("Tracker is not enabled".into());
             }
 
-            bot.send_message(msg.chat.id, v.join("\n\n")).await?;
+            for part in split_message(&v.join("\n\n"), MESSAGE_LIMIT) {
+                send_html(&bot, msg.chat.id, part, None).await?;
+            }
+            Ok(())
+        }
+
+        #[cfg(feature = "tracker")]
+        async fn query_tracker(
+            database: &str,
+            cmd: &Command,
+            state: Option<&UserState>,
+        ) -> anyhow::Result<String> {
+            use crate::plugins::tracker::stats;
+            /// Time range of statistics commands
+            const STATS_RANGE: i64 = 30 * 86400;
+            /// Maximum matches of `/seen`
+            const SEEN_LIMIT: usize = 5;
+
+            let mut conn = stats::open(database).await?;
+            let now = kstool::time::get_current_second() as i64;
+            let since = now - STATS_RANGE;
+            Ok(match cmd {
+                Command::Top => {
+                    let top = stats::top_online(&mut conn, since, now, 10).await?;
+                    if top.is_empty() {
+                        return Ok("No record found".into());
+                    }
+                    top.iter()
+                        .enumerate()
+                        .map(|(index, record)| format!("{}. {record}", index + 1))
+                        .collect::<Vec<_>>()
+                        .join("\n")
+                }
+                Command::Stats(unique_identifier) => {
+                    stats::user_stats(&mut conn, unique_identifier.trim(), since, now)
+                        .await?
+                        .map(|stats| stats.to_string())
+                        .unwrap_or_else(|| "Client not found".into())
+                }
+                Command::Peak => {
+                    let peak = stats::peak(&mut conn, since, now)
+                        .await?
+                        .map(|peak| peak.to_string())
+                        .unwrap_or_else(|| "No record found".into());
+                    let hours = stats::busiest_hours(&mut conn, since)
+                        .await?
+                        .iter()
+                        .take(3)
+                        .map(ToString::to_string)
+                        .collect::<Vec<_>>();
+                    if hours.is_empty() {
+                        peak
+                    } else {
+                        format!("{peak}\nBusiest hours: {}", hours.join(", "))
+                    }
+                }
+                Command::Seen(query) => {
+                    let mut result = stats::seen(&mut conn, query.trim(), SEEN_LIMIT).await?;
+                    if result.is_empty() {
+                        return Ok("Client not found".into());
+                    }
+                    if let Some(state) = state {
+                        for seen in &mut result {
+                            if let Some(name) = seen
+                                .channel()
+                                .and_then(|channel| state.channel_name(channel))
+                            {
+                                seen.set_channel_name(name.to_string());
+                            }
+                        }
+                    }
+                    result
+                        .iter()
+                        .map(ToString::to_string)
+                        .collect::<Vec<_>>()
+                        .join("\n\n")
+                }
+                Command::Names(unique_identifier) => {
+                    let names = stats::names(&mut conn, unique_identifier.trim()).await?;
+                    if names.is_empty() {
+                        return Ok("Client not found".into());
+                    }
+                    names
+                        .iter()
+                        .map(ToString::to_string)
+                        .collect::<Vec<_>>()
+                        .join("\n")
+                }
+                _ => unreachable!(),
+            })
+        }
+
+        #[cfg(not(feature = "tracker"))]
+        async fn query_tracker(
+            _database: &str,
+            _cmd: &Command,
+            _state: Option<&UserState>,
+        ) -> anyhow::Result<String> {
+            Err(anyhow!("Tracker feature is not enabled"))
+        }
 
+        pub async fn handle_lockdown(
+            bot: BotType,
+            query: CallbackQuery,
+            (chat_map, config_hash, access): (Arc<ChatMap>, Arc<ConfigHashMap>, Arc<AccessMap>),
+            server_sender: Arc<ConfigMappedSender>,
+        ) -> anyhow::Result<()> {
+            let Some(config_id) = query
+                .data
+                .as_deref()
+                .and_then(|data| data.strip_prefix(LOCKDOWN_PREFIX))
+                .and_then(|hash| config_hash.0.get(hash))
+            else {
+                return Ok(());
+            };
+            let Some(message) = &query.message else {
+                return Ok(());
+            };
+            let chat_id = message.chat().id;
+
+            if !authorize(
+                &bot,
+                (chat_id, &query.from),
+                (&chat_map, &access),
+                (Role::Moderator, "end lockdown"),
+            )
+            .await
+            .contains(config_id)
+            {
+                bot.answer_callback_query(query.id.clone())
+                    .text("Unauthorized")
+                    .await?;
+                return Ok(());
+            }
+
+            let sender = server_sender
+                .get(config_id)
+                .ok_or_else(|| anyhow!("Sender of {config_id} not found"))?;
+            let (s, r) = oneshot::channel();
+            sender
+                .send(PrivateMessageRequest::EndLockdown(s))
+                .await
+                .map_err(|_| anyhow!("Unable send end lockdown request to {config_id}"))?;
+            let ended = r.await.unwrap_or_default();
+
+            bot.answer_callback_query(query.id.clone())
+                .text(if ended {
+                    "Lockdown ended"
+                } else {
+                    "Server is not in lockdown"
+                })
+                .await?;
+            bot.edit_message_reply_markup(chat_id, message.id()).await?;
+            if ended {
+                info!(
+                    "[{config_id}] Lockdown ended by telegram user {}",
+                    query.from.id
+                );
+                bot.send_message(
+                    chat_id,
+                    format!(
+                        "{config_id}\nLockdown ended by {}",
+                        escape_html(&query.from.full_name())
+                    ),
+                )
+                .await?;
+            }
             Ok(())
         }
     }
 }
 
+pub use thread::telegram_bootstrap;
+pub use types::{BindTelegramHelper, TelegramHelper, escape_html, strip_html};
+
+#[cfg(test)]
+mod test {
+    use super::thread::{batch, digest};
+    use super::types::{MESSAGE_LIMIT, escape_html, split_message, strip_html};
+
+    #[test]
+    fn test_split() {
+        assert_eq!(split_message("a\nbb\nccc", 5), vec!["a\nbb", "ccc"]);
+        assert_eq!(split_message("abcdefg\nh", 3), vec!["abc", "def", "g\nh"]);
+        assert_eq!(split_message("", 3), vec![""]);
+
+        let queue = [
+            ("1".to_string(), "a".repeat(2000)),
+            ("1".to_string(), "b".repeat(2000)),
+            ("2".to_string(), "c".repeat(2000)),
+        ];
+        let (count, message) = batch(&queue);
+        assert_eq!(count, 2);
+        assert_eq!(message.len(), 2 + 4000 + 1);
+        // Batch of multiple events is never split, so only single event may be sent partially
+        assert_eq!(split_message(&message, MESSAGE_LIMIT).len(), 1);
+        assert_eq!(batch(&queue[2..]).0, 1);
+
+        let held = [
+            ("enter".to_string(), "a joined".to_string()),
+            ("leave".to_string(), "a left".to_string()),
+            ("enter".to_string(), "b joined".to_string()),
+        ];
+        assert_eq!(
+            digest(&held),
+            "<b>Quiet hours digest</b> (enter: 2, leave: 1)\na joined\na left\nb joined"
+        );
+    }
+
+    #[test]
+    fn test_html() {
+        let nickname = r#"<a href="x">Tom & Jerry</a>"#;
+        let escaped = escape_html(nickname);
+        assert_eq!(
+            escaped,
+            "&lt;a href=&quot;x&quot;&gt;Tom &amp; Jerry&lt;/a&gt;"
+        );
+        assert_eq!(
+            strip_html(&format!("<b>{escaped}</b> joined")),
+            format!("{nickname} joined")
+        );
+        assert_eq!(strip_html("<b>broken <i"), "broken <i");
+    }
+}
+
 /* #[cfg(test)]
 mod test {
     use std::collections::HashMap;
@@ -656,6 +2153,3 @@ mod test {
         assert_eq!(iter.len(), 13);
     }
 } */
-
-pub use thread::telegram_bootstrap;
-pub use types::{BindTelegramHelper, TelegramHelper};
@@ -0,0 +1,65 @@
+pub fn format_timestamp(timestamp: i64) -> String {
+    chrono::DateTime::from_timestamp(timestamp, 0)
+        .map(|time| {
+            time.with_timezone(&chrono::Local)
+                .format("%Y-%m-%d %H:%M:%S")
+                .to_string()
+        })
+        .unwrap_or_default()
+}
+
+pub fn format_duration(seconds: i64) -> String {
+    let (hours, minutes) = (seconds / 3600, seconds % 3600 / 60);
+    if hours > 0 {
+        format!("{hours}h {minutes}m")
+    } else {
+        format!("{minutes}m")
+    }
+}
+
+/// Replace `{key}` placeholder in template, unknown placeholder will be kept
+pub fn render(template: &str, values: &[(&str, &str)]) -> String {
+    let mut output = String::with_capacity(template.len());
+    let mut rest = template;
+    while let Some(start) = rest.find('{') {
+        let Some(length) = rest[start..].find('}') else {
+            break;
+        };
+        output.push_str(&rest[..start]);
+        let placeholder = &rest[start..=start + length];
+        match values
+            .iter()
+            .find(|(key, _)| *key == &placeholder[1..length])
+        {
+            Some((_, value)) => output.push_str(value),
+            None => output.push_str(placeholder),
+        }
+        rest = &rest[start + length + 1..];
+    }
+    output.push_str(rest);
+    output
+}
+
+#[cfg(test)]
+mod test {
+    use super::render;
+
+    #[test]
+    fn test_render() {
+        assert_eq!(
+            render(
+                "Hi {nickname} from {country}, {online} online {unknown}",
+                &[
+                    ("nickname", "{country}"),
+                    ("country", "JP"),
+                    ("online", "3")
+                ]
+            ),
+            "Hi {country} from JP, 3 online {unknown}"
+        );
+        assert_eq!(
+            render("broken {nickname", &[("nickname", "a")]),
+            "broken {nickname"
+        );
+    }
+}
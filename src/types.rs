@@ -59,8 +59,10 @@ pub mod channel {
     pub struct Channel {
         #[serde(rename = "cid")]
         channel_id: i64,
-        /* pid: i64, */
-        /* channel_order: i64, */
+        #[serde(default)]
+        pid: i64,
+        #[serde(default)]
+        channel_order: i64,
         channel_name: String,
         /*total_clients: i64,
         channel_needed_subscribe_power: i64, */
@@ -70,12 +72,13 @@ pub mod channel {
         pub fn cid(&self) -> i64 {
             self.channel_id
         }
-        /* pub fn pid(&self) -> i64 {
+        pub fn pid(&self) -> i64 {
             self.pid
         }
+        /// Id of channel above this one in same parent, `0` means first
         pub fn channel_order(&self) -> i64 {
             self.channel_order
-        }*/
+        }
         pub fn channel_name(&self) -> &str {
             &self.channel_name
         }
@@ -124,6 +127,20 @@ mod client {
         client_database_id: i64,
         client_type: i64,
         client_nickname: String,
+        #[serde(default)]
+        client_unique_identifier: String,
+        #[serde(rename = "connection_client_ip", default)]
+        client_ip: String,
+        #[serde(default)]
+        client_country: String,
+        #[serde(rename = "client_servergroups", default)]
+        server_groups: String,
+        #[serde(default)]
+        client_away: bool,
+        #[serde(default)]
+        client_input_muted: bool,
+        #[serde(default)]
+        client_output_muted: bool,
     }
 
     impl Client {
@@ -142,9 +159,36 @@ mod client {
         pub fn client_nickname(&self) -> &str {
             &self.client_nickname
         }
+        pub fn client_unique_identifier(&self) -> &str {
+            &self.client_unique_identifier
+        }
+        pub fn client_ip(&self) -> &str {
+            &self.client_ip
+        }
+        pub fn client_country(&self) -> &str {
+            &self.client_country
+        }
         pub fn client_is_user(&self) -> bool {
             self.client_type == 0
         }
+        pub fn server_groups(&self) -> Vec<i64> {
+            self.server_groups
+                .split(',')
+                .filter_map(|group| group.trim().parse().ok())
+                .collect()
+        }
+        /// Icon of away or mute status, empty if client is active
+        pub fn status_icon(&self) -> &'static str {
+            if self.client_away {
+                "💤"
+            } else if self.client_output_muted {
+                "🔇"
+            } else if self.client_input_muted {
+                "🤐"
+            } else {
+                ""
+            }
+        }
     }
 
     impl FromQueryString for Client {}
@@ -164,7 +208,7 @@ mod client {
             assert_eq!(result.client_database_id(), 1);
             assert_eq!(result.client_nickname(), "serveradmin".to_string());
             assert_eq!(result.client_type(), 1);
-            //assert_eq!(result.client_unique_identifier(), "serveradmin".to_string());
+            assert_eq!(result.client_unique_identifier(), "serveradmin".to_string());
             //assert_eq!(result.client_database_id(), "1".to_string());
         }
     }
@@ -214,13 +258,11 @@ pub mod notifies {
         /*#[serde(rename = "reasonid", default)]
         reason_id: i64,
         #[serde(rename = "invokerid", default)]
-        invoker_id: i64,*/
-        #[cfg(feature = "tracker")]
+        invoker_id: i64,
         #[serde(rename = "invokeruid", default)]
         invoker_uid: String,
-        #[cfg(feature = "tracker")]
         #[serde(rename = "invokername", default)]
-        invoker_name: String,
+        invoker_name: String,*/
         #[serde(rename = "clid", default)]
         client_id: i64,
     }
@@ -234,15 +276,12 @@ pub mod notifies {
         }
         pub fn invoker_id(&self) -> i64 {
             self.invoker_id
-        }*/
-        #[cfg(feature = "tracker")]
         pub fn invoker_uid(&self) -> &str {
             &self.invoker_uid
         }
-        #[cfg(feature = "tracker")]
         pub fn invoker_name(&self) -> &str {
             &self.invoker_name
-        }
+        }*/
         pub fn client_id(&self) -> i64 {
             self.client_id
         }
@@ -254,15 +293,24 @@ pub mod notifies {
         client_id: i64,
         #[serde(rename = "ctid")]
         channel_id: i64,
+        #[serde(default)]
+        client_database_id: i64,
         client_nickname: String,
         client_unique_identifier: String,
         client_country: String,
+        #[serde(rename = "client_servergroups", default)]
+        server_groups: String,
+        #[serde(rename = "client_totalconnections", default)]
+        total_connections: i64,
     }
 
     impl NotifyClientEnterView {
         pub fn client_id(&self) -> i64 {
             self.client_id
         }
+        pub fn client_database_id(&self) -> i64 {
+            self.client_database_id
+        }
         pub fn client_nickname(&self) -> &str {
             &self.client_nickname
         }
@@ -276,6 +324,17 @@ pub mod notifies {
         pub fn channel_id(&self) -> i64 {
             self.channel_id
         }
+
+        pub fn total_connections(&self) -> i64 {
+            self.total_connections
+        }
+
+        pub fn server_groups(&self) -> Vec<i64> {
+            self.server_groups
+                .split(',')
+                .filter_map(|group| group.trim().parse().ok())
+                .collect()
+        }
     }
 
     fn default_left_reason_id() -> i64 {
@@ -286,6 +345,8 @@ pub mod notifies {
     pub struct NotifyClientLeftView {
         #[serde(rename = "clid")]
         client_id: i64,
+        #[serde(rename = "cfid", default)]
+        channel_id: i64,
         #[serde(rename = "reasonmsg", default)]
         reason: String,
         #[serde(rename = "reasonid", default = "default_left_reason_id")]
@@ -302,6 +363,10 @@ pub mod notifies {
         pub fn client_id(&self) -> i64 {
             self.client_id
         }
+        /// Channel which client left from
+        pub fn channel_id(&self) -> i64 {
+            self.channel_id
+        }
         pub fn reason(&self) -> &str {
             &self.reason
         }
@@ -318,8 +383,8 @@ pub mod notifies {
 
     #[derive(Clone, Debug, Deserialize)]
     pub struct NotifyTextMessage {
-        /*#[serde(rename = "targetmode", default)]
-        target_mode: i8,*/
+        #[serde(rename = "targetmode", default)]
+        target_mode: i8,
         msg: String,
         //target: i64,
         #[serde(rename = "invokerid", default)]
@@ -331,9 +396,10 @@ pub mod notifies {
     }
 
     impl NotifyTextMessage {
-        /*pub fn target_mode(&self) -> i8 {
+        /// 1 for private, 2 for channel and 3 for server message
+        pub fn target_mode(&self) -> i8 {
             self.target_mode
-        }*/
+        }
         pub fn msg(&self) -> &str {
             &self.msg
         }
@@ -351,10 +417,27 @@ pub mod notifies {
         }
     }
 
+    #[derive(Clone, Debug, Deserialize)]
+    pub struct NotifyClientUpdated {
+        #[serde(rename = "clid")]
+        client_id: i64,
+        client_nickname: Option<String>,
+    }
+
+    impl NotifyClientUpdated {
+        pub fn client_id(&self) -> i64 {
+            self.client_id
+        }
+        pub fn client_nickname(&self) -> Option<&str> {
+            self.client_nickname.as_deref()
+        }
+    }
+
     impl FromQueryString for NotifyClientMovedView {}
     impl FromQueryString for NotifyClientEnterView {}
     impl FromQueryString for NotifyClientLeftView {}
     impl FromQueryString for NotifyTextMessage {}
+    impl FromQueryString for NotifyClientUpdated {}
 }
 
 pub mod query_status {
@@ -439,17 +522,44 @@ pub mod server_info {
     pub struct ServerInfo {
         #[serde(rename = "virtualserver_unique_identifier")]
         virtual_server_unique_identifier: String,
+        #[serde(rename = "virtualserver_flag_password", default)]
+        flag_password: bool,
     }
 
     impl ServerInfo {
         pub fn virtual_server_unique_identifier(&self) -> &str {
             &self.virtual_server_unique_identifier
         }
+        pub fn has_password(&self) -> bool {
+            self.flag_password
+        }
     }
 
     impl FromQueryString for ServerInfo {}
 }
 
+pub mod channel_permission {
+    use super::FromQueryString;
+    use serde::Deserialize;
+
+    #[derive(Clone, Debug, Deserialize)]
+    pub struct ChannelPermission {
+        permsid: String,
+        permvalue: i64,
+    }
+
+    impl ChannelPermission {
+        pub fn permsid(&self) -> &str {
+            &self.permsid
+        }
+        pub fn permvalue(&self) -> i64 {
+            self.permvalue
+        }
+    }
+
+    impl FromQueryString for ChannelPermission {}
+}
+
 pub mod client_query_result {
 
     use super::FromQueryString;
@@ -532,6 +642,8 @@ pub mod ban_entry {
         }
     }
 
+    impl FromQueryString for BanEntry {}
+
     #[cfg(test)]
     mod test {
         use super::BanEntry;
@@ -548,6 +660,7 @@ pub mod ban_entry {
         reason=Spam enforcements=0"#;
 
         #[test]
+        #[allow(clippy::redundant_closure)]
         fn test() {
             TEST_STRING
                 .split('|')
@@ -555,8 +668,6 @@ pub mod ban_entry {
                 .for_each(|entry| drop(entry));
         }
     }
-
-    impl FromQueryString for BanEntry {}
 }
 
 mod status_result {
@@ -630,6 +741,8 @@ mod client_info {
         //client_unique_identifier: String,
         client_away: bool,
         client_idle_time: i64,
+        #[serde(default)]
+        connection_client_ip: String,
     }
 
     impl ClientInfo {
@@ -641,23 +754,156 @@ mod client_info {
                 || !self.client_input_hardware
                 || self.client_idle_time / 1000 > 300
         }
+
+        pub fn connection_client_ip(&self) -> &str {
+            &self.connection_client_ip
+        }
     }
 
     impl FromQueryString for ClientInfo {}
 }
 
+// Event fields are only consumed by tracker
+#[cfg_attr(not(feature = "tracker"), allow(dead_code))]
+mod tracker_event {
+    #[derive(Clone, Copy, Debug, PartialEq, Eq)]
+    pub enum EventType {
+        Enter,
+        Leave,
+        Move,
+        Kick,
+        Ban,
+    }
+
+    impl EventType {
+        /// Map `reasonid` of client left view to event type
+        pub fn from_left_reason(reason_id: i64) -> Self {
+            match reason_id {
+                5 => Self::Kick,
+                6 => Self::Ban,
+                _ => Self::Leave,
+            }
+        }
+
+        pub fn as_str(&self) -> &'static str {
+            match self {
+                Self::Enter => "enter",
+                Self::Leave => "leave",
+                Self::Move => "move",
+                Self::Kick => "kick",
+                Self::Ban => "ban",
+            }
+        }
+
+        pub fn is_leave(&self) -> bool {
+            matches!(self, Self::Leave | Self::Kick | Self::Ban)
+        }
+    }
+
+    #[derive(Clone, Debug)]
+    pub struct TrackerEvent {
+        timestamp: i64,
+        event_type: EventType,
+        client_id: i64,
+        database_id: Option<i64>,
+        unique_identifier: Option<String>,
+        nickname: Option<String>,
+        channel: Option<i64>,
+        ip: Option<String>,
+        country: Option<String>,
+        reason: Option<String>,
+    }
+
+    fn non_empty(s: &str) -> Option<String> {
+        (!s.is_empty()).then(|| s.to_string())
+    }
+
+    impl TrackerEvent {
+        pub fn new(event_type: EventType, client_id: i64) -> Self {
+            Self {
+                timestamp: kstool::time::get_current_second() as i64,
+                event_type,
+                client_id,
+                database_id: None,
+                unique_identifier: None,
+                nickname: None,
+                channel: None,
+                ip: None,
+                country: None,
+                reason: None,
+            }
+        }
+
+        pub fn with_client(
+            mut self,
+            database_id: i64,
+            unique_identifier: &str,
+            nickname: &str,
+        ) -> Self {
+            self.database_id = (database_id != 0).then_some(database_id);
+            self.unique_identifier = non_empty(unique_identifier);
+            self.nickname = non_empty(nickname);
+            self
+        }
+
+        pub fn with_channel(mut self, channel: i64) -> Self {
+            self.channel = Some(channel);
+            self
+        }
+
+        pub fn with_address(mut self, ip: &str, country: &str) -> Self {
+            self.ip = non_empty(ip);
+            self.country = non_empty(country);
+            self
+        }
+
+        pub fn with_reason(mut self, reason: &str) -> Self {
+            self.reason = non_empty(reason);
+            self
+        }
+
+        pub fn timestamp(&self) -> i64 {
+            self.timestamp
+        }
+        pub fn event_type(&self) -> EventType {
+            self.event_type
+        }
+        pub fn client_id(&self) -> i64 {
+            self.client_id
+        }
+        pub fn database_id(&self) -> Option<i64> {
+            self.database_id
+        }
+        pub fn unique_identifier(&self) -> Option<&str> {
+            self.unique_identifier.as_deref()
+        }
+        pub fn nickname(&self) -> Option<&str> {
+            self.nickname.as_deref()
+        }
+        pub fn channel(&self) -> Option<i64> {
+            self.channel
+        }
+        pub fn ip(&self) -> Option<&str> {
+            self.ip.as_deref()
+        }
+        pub fn country(&self) -> Option<&str> {
+            self.country.as_deref()
+        }
+        pub fn reason(&self) -> Option<&str> {
+            self.reason.as_deref()
+        }
+    }
+}
+
 mod pseudo_event_helper {
+    use super::TrackerEvent;
     use async_trait::async_trait;
 
     #[async_trait]
     pub trait EventHelperTrait {
-        async fn insert(
-            &self,
-            client_id: i32,
-            user_id: Option<String>,
-            nickname: Option<String>,
-            channel: Option<i32>,
-        ) -> Option<()>;
+        /// Whether events will be stored
+        fn enabled(&self) -> bool;
+        async fn insert(&self, event: TrackerEvent) -> Option<()>;
         async fn terminate(&self) -> Option<()>;
     }
 
@@ -679,13 +925,11 @@ mod pseudo_event_helper {
     #[cfg(not(feature = "tracker"))]
     #[async_trait]
     impl EventHelperTrait for PseudoEventHelper {
-        async fn insert(
-            &self,
-            _client_id: i32,
-            _user_id: Option<String>,
-            _nickname: Option<String>,
-            _channel: Option<i32>,
-        ) -> Option<()> {
+        fn enabled(&self) -> bool {
+            false
+        }
+
+        async fn insert(&self, _event: TrackerEvent) -> Option<()> {
             Some(())
         }
 
@@ -702,20 +946,30 @@ mod user_state {
     };
 
     use chrono::DateTime;
-    use tokio::sync::RwLock;
+    use tokio::sync::{RwLock, watch};
 
     use super::{Channel, Client, ToNameMap};
+    use crate::telegram::escape_html;
 
     static DEFAULT_NO_NAME_PLACEHOLDER: LazyLock<String> = LazyLock::new(|| "N/A".to_string());
 
+    /// Indent of each tree level, em space is not collapsed by HTML renderer
+    const INDENT: &str = "\u{2003}";
+
     #[derive(Clone, Debug, Default)]
     pub struct UserState {
         /// Channel name map
         channel: HashMap<i64, String>,
+        /// Channel id with depth in server order
+        tree: Vec<(i64, usize)>,
         /// Client name map
         client: HashMap<i64, String>,
+        /// Client id with away or mute status icon
+        status: HashMap<i64, &'static str>,
         /// Real map
         mapper: HashMap<i64, Vec<i64>>,
+        /// Show channels without clients
+        show_empty: bool,
         last_update: u64,
     }
 
@@ -728,6 +982,7 @@ mod user_state {
 
         pub fn update(&mut self, channels: Vec<Channel>, clients: Vec<Client>) -> bool {
             let mut obj = HashMap::new();
+            let mut status = HashMap::new();
             /* for channel in &channels {
                 if channel.total_clients() > 0 {
                     obj.mapper.insert(channel.cid(), Vec::new());
@@ -740,46 +995,155 @@ mod user_state {
                 obj.entry(client.channel_id())
                     .or_insert_with(Vec::new)
                     .push(client.client_id());
+                if !client.status_icon().is_empty() {
+                    status.insert(client.client_id(), client.status_icon());
+                }
             }
             self.last_update = kstool::time::get_current_second();
-            if obj.eq(&self.mapper) {
+            let tree = channel_tree(&channels);
+            let channel = channels.to_name_map();
+            let client = clients.to_name_map();
+            if obj.eq(&self.mapper)
+                && status.eq(&self.status)
+                && tree.eq(&self.tree)
+                && channel.eq(&self.channel)
+                && client.eq(&self.client)
+            {
                 return false;
             }
             self.mapper = obj;
-            self.channel = channels.to_name_map();
-            self.client = clients.to_name_map();
+            self.status = status;
+            self.tree = tree;
+            self.channel = channel;
+            self.client = client;
             true
         }
 
         pub fn last_update(&self) -> u64 {
             self.last_update
         }
+
+        /// Count of online clients
+        pub fn online(&self) -> usize {
+            self.mapper.values().map(Vec::len).sum()
+        }
+
+        #[cfg_attr(not(feature = "tracker"), allow(dead_code))]
+        pub fn channel_name(&self, channel: i64) -> Option<&str> {
+            self.channel.get(&channel).map(String::as_str)
+        }
+
+        /// Channels which should be rendered with depth, parents of non-empty channel are kept
+        fn visible_channels(&self) -> Vec<(i64, usize)> {
+            let mut visible = vec![];
+            // Channels shallower than this are parents of visible channels
+            let mut threshold = 0;
+            for &(channel, depth) in self.tree.iter().rev() {
+                // Visible channel or parent of visible channel
+                if !self.show_empty && !self.mapper.contains_key(&channel) && depth >= threshold {
+                    continue;
+                }
+                threshold = depth;
+                visible.push((channel, depth));
+            }
+            visible.reverse();
+            // Channel of clients may not in tree if channel list is outdated
+            let mut unknown = self
+                .mapper
+                .keys()
+                .filter(|channel| !self.tree.iter().any(|(cid, _)| cid == *channel))
+                .map(|channel| (*channel, 0))
+                .collect::<Vec<_>>();
+            unknown.sort();
+            visible.extend(unknown);
+            visible
+        }
+
+        fn name<'a>(map: &'a HashMap<i64, String>, id: &i64) -> &'a str {
+            map.get(id).unwrap_or(&DEFAULT_NO_NAME_PLACEHOLDER)
+        }
+    }
+
+    /// Sort channels in server order, `channel_order` is id of previous channel in same parent
+    fn channel_tree(channels: &[Channel]) -> Vec<(i64, usize)> {
+        fn visit(
+            parent: i64,
+            depth: usize,
+            children: &mut HashMap<i64, Vec<&Channel>>,
+            tree: &mut Vec<(i64, usize)>,
+        ) {
+            let Some(mut siblings) = children.remove(&parent) else {
+                return;
+            };
+            let mut sorted = Vec::with_capacity(siblings.len());
+            let mut previous = 0;
+            while let Some(index) = siblings
+                .iter()
+                .position(|channel| channel.channel_order() == previous)
+            {
+                let channel = siblings.swap_remove(index);
+                previous = channel.cid();
+                sorted.push(channel);
+            }
+            // Broken order chain, append rest by channel id
+            siblings.sort_by_key(|channel| channel.cid());
+            sorted.extend(siblings);
+            for channel in sorted {
+                tree.push((channel.cid(), depth));
+                visit(channel.cid(), depth + 1, children, tree);
+            }
+        }
+
+        let mut children: HashMap<i64, Vec<&Channel>> = HashMap::new();
+        for channel in channels {
+            children.entry(channel.pid()).or_default().push(channel);
+        }
+        let mut tree = vec![];
+        visit(0, 0, &mut children, &mut tree);
+        // Parent is not in list, treat as top level
+        let mut orphans = children.keys().copied().collect::<Vec<_>>();
+        orphans.sort();
+        for parent in orphans {
+            visit(parent, 0, &mut children, &mut tree);
+        }
+        tree
     }
 
     impl std::fmt::Display for UserState {
         fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
-            for (channel, clients) in &self.mapper {
-                write!(
+            for (channel, depth) in self.visible_channels() {
+                let indent = INDENT.repeat(depth);
+                writeln!(
                     f,
-                    "<b>{}</b>(<code>{channel}</code>): ",
-                    self.channel
-                        .get(channel)
-                        .unwrap_or(&DEFAULT_NO_NAME_PLACEHOLDER),
+                    "{indent}<b>{}</b>(<code>{channel}</code>)",
+                    escape_html(Self::name(&self.channel, &channel)),
                 )?;
-                for (index, client) in clients.iter().enumerate() {
+                let Some(clients) = self.mapper.get(&channel) else {
+                    continue;
+                };
+                let mut clients = clients
+                    .iter()
+                    .map(|client| (Self::name(&self.client, client), client))
+                    .collect::<Vec<_>>();
+                clients.sort_by_key(|(name, _)| name.to_lowercase());
+                for (name, client) in clients {
                     write!(
                         f,
-                        "{}(<code>{client}</code>)",
-                        self.client
-                            .get(client)
-                            .unwrap_or(&DEFAULT_NO_NAME_PLACEHOLDER),
+                        "{indent}{INDENT}• {}(<code>{client}</code>)",
+                        escape_html(name)
                     )?;
-                    if index != clients.len() - 1 {
-                        write!(f, ", ")?;
+                    if let Some(icon) = self.status.get(client) {
+                        write!(f, " {icon}")?;
                     }
+                    writeln!(f)?;
                 }
-                writeln!(f)?;
             }
+            writeln!(
+                f,
+                "Total: {} clients in {} channels",
+                self.online(),
+                self.mapper.len()
+            )?;
             let last_update: DateTime<chrono::prelude::Local> =
                 DateTime::from_timestamp(self.last_update() as i64, 0)
                     .unwrap()
@@ -795,17 +1159,27 @@ mod user_state {
     #[derive(Clone)]
     pub struct SafeUserState {
         inner: Option<Arc<RwLock<UserState>>>,
+        /// Notified when online clients changed
+        changed: Arc<watch::Sender<()>>,
     }
 
     impl SafeUserState {
         pub async fn update(&self, channels: Vec<Channel>, clients: Vec<Client>) -> bool {
             if let Some(ref inner) = self.inner {
                 let mut guard = inner.write().await;
-                return guard.update(channels, clients);
+                if guard.update(channels, clients) {
+                    self.changed.send_replace(());
+                    return true;
+                }
             }
             false
         }
 
+        /// Receiver which is notified when online clients changed
+        pub fn subscribe(&self) -> watch::Receiver<()> {
+            self.changed.subscribe()
+        }
+
         pub async fn read(&self) -> Option<tokio::sync::RwLockReadGuard<'_, UserState>> {
             if let Some(ref ret) = self.inner {
                 Some(ret.read().await)
@@ -830,12 +1204,19 @@ mod user_state {
         } */
 
         pub fn create_none() -> Self {
-            Self { inner: None }
+            Self {
+                inner: None,
+                changed: Arc::new(watch::channel(()).0),
+            }
         }
 
-        pub fn create() -> Self {
+        pub fn create(show_empty: bool) -> Self {
             Self {
-                inner: Some(Default::default()),
+                inner: Some(Arc::new(RwLock::new(UserState {
+                    show_empty,
+                    ..Default::default()
+                }))),
+                changed: Arc::new(watch::channel(()).0),
             }
         }
 
@@ -845,6 +1226,69 @@ mod user_state {
     }
 
     pub type ConfigMappedUserState = HashMap<String, SafeUserState>;
+
+    #[cfg(test)]
+    mod test {
+        use super::UserState;
+        use crate::types::{Channel, Client, FromQueryString};
+
+        #[test]
+        fn test_tree() {
+            let channels = [
+                "cid=3 pid=0 channel_order=1 channel_name=Gaming",
+                "cid=1 pid=0 channel_order=0 channel_name=Lobby",
+                "cid=5 pid=3 channel_order=4 channel_name=Team\\sB",
+                "cid=4 pid=3 channel_order=0 channel_name=Team\\sA",
+                "cid=6 pid=0 channel_order=3 channel_name=AFK",
+            ]
+            .into_iter()
+            .map(|s| Channel::from_query(s).unwrap())
+            .collect::<Vec<_>>();
+            let clients = [
+                "clid=8 cid=5 client_database_id=1 client_nickname=bob client_type=0 client_away=1",
+                "clid=9 cid=5 client_database_id=2 client_nickname=Alice client_type=0",
+                "clid=1 cid=1 client_database_id=3 client_nickname=serveradmin client_type=1",
+            ]
+            .into_iter()
+            .map(|s| Client::from_query(s).unwrap())
+            .collect::<Vec<_>>();
+
+            let mut state = UserState::default();
+            assert!(state.update(channels.clone(), clients.clone()));
+            assert!(!state.update(channels.clone(), clients.clone()));
+            assert_eq!(state.tree, vec![(1, 0), (3, 0), (4, 1), (5, 1), (6, 0)]);
+            assert!(state.to_string().starts_with(
+                "<b>Gaming</b>(<code>3</code>)\n\
+                 \u{2003}<b>Team B</b>(<code>5</code>)\n\
+                 \u{2003}\u{2003}• Alice(<code>9</code>)\n\
+                 \u{2003}\u{2003}• bob(<code>8</code>) 💤\n\
+                 Total: 2 clients in 1 channels\n"
+            ));
+
+            state.show_empty = true;
+            assert_eq!(state.visible_channels().len(), 5);
+
+            // Empty sibling before parent of nested occupied channel is hidden
+            let channels = [
+                "cid=10 pid=0 channel_order=0 channel_name=A",
+                "cid=11 pid=0 channel_order=10 channel_name=B",
+                "cid=12 pid=11 channel_order=0 channel_name=C",
+            ]
+            .into_iter()
+            .map(|s| Channel::from_query(s).unwrap())
+            .collect::<Vec<_>>();
+            let clients = [
+                "clid=8 cid=11 client_database_id=1 client_nickname=bob client_type=0",
+                "clid=9 cid=12 client_database_id=2 client_nickname=Alice client_type=0",
+            ]
+            .into_iter()
+            .map(|s| Client::from_query(s).unwrap())
+            .collect::<Vec<_>>();
+            let mut state = UserState::default();
+            state.update(channels, clients);
+            assert_eq!(state.visible_channels(), vec![(11, 0), (12, 1)]);
+        }
+    }
 }
 
 mod to_map {
@@ -914,12 +1358,20 @@ mod to_map {
 }
 
 mod arg {
+    use std::collections::HashMap;
     use std::sync::Arc;
 
-    use tokio::sync::{Barrier, Notify};
+    use tokio::sync::{Barrier, Notify, mpsc};
 
+    use crate::observer::PrivateMessageRequest;
+    use crate::plugins::KVMap;
     use crate::telegram::TelegramHelper;
 
+    use super::SafeUserState;
+
+    /// Configure id with observer request sender relationship
+    pub type ConfigMappedSender = HashMap<String, mpsc::Sender<PrivateMessageRequest>>;
+
     //use super::UserState;
 
     /* pub struct ArgPass2AutoChannel {
@@ -952,20 +1404,34 @@ mod arg {
             }
         }
     }
+
+    /// Resources owned by single configure thread
+    pub struct ArgPass2Thread {
+        pub kv_map: Box<dyn KVMap>,
+        pub observer_kv_map: Box<dyn KVMap>,
+        pub user_map: SafeUserState,
+        pub private_message: (
+            mpsc::Sender<PrivateMessageRequest>,
+            mpsc::Receiver<PrivateMessageRequest>,
+        ),
+    }
 }
 
 pub use ban_entry::BanEntry;
 pub use channel::Channel;
+pub use channel_permission::ChannelPermission;
 pub use client::Client;
 pub use client_info::ClientInfo;
 pub use client_query_result::DatabaseId;
 pub use create_channel::CreateChannel;
 pub use notifies::{
-    NotifyClientEnterView, NotifyClientLeftView, NotifyClientMovedView, NotifyTextMessage,
+    NotifyClientEnterView, NotifyClientLeftView, NotifyClientMovedView, NotifyClientUpdated,
+    NotifyTextMessage,
 };
 pub use pseudo_event_helper::EventHelperTrait;
+pub use tracker_event::{EventType, TrackerEvent};
 
-pub use arg::ArgPass2Controller;
+pub use arg::{ArgPass2Controller, ArgPass2Thread, ConfigMappedSender};
 #[cfg(not(feature = "tracker"))]
 pub use pseudo_event_helper::PseudoEventHelper;
 pub use query_status::QueryStatus;
@@ -973,5 +1439,5 @@ use serde::Deserialize;
 pub use server_info::ServerInfo;
 pub use status_result::{QueryError, QueryResult};
 pub use to_map::ToNameMap;
-pub use user_state::{ConfigMappedUserState, SafeUserState};
+pub use user_state::{ConfigMappedUserState, SafeUserState, UserState};
 pub use whoami::WhoAmI;
@@ -0,0 +1,195 @@
+use crate::moderation::Target;
+use crate::plugins::KVMap;
+use serde::Serialize;
+use serde::de::DeserializeOwned;
+
+/// Maximum clients which one telegram user can watch on one server
+const MAX_WATCHES: usize = 20;
+
+/// Presence subscription request from chat bot
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum WatchRequest {
+    Watch(Target),
+    Unwatch(Target),
+    List,
+}
+
+impl WatchRequest {
+    /// Parse arguments of command, return usage if arguments are invalid
+    pub fn parse(command: &str, args: &str) -> Result<Self, &'static str> {
+        match command {
+            "watch" => Target::from_args(args)
+                .map(Self::Watch)
+                .ok_or("Usage: /watch <uid|nickname>"),
+            "unwatch" => Target::from_args(args)
+                .map(Self::Unwatch)
+                .ok_or("Usage: /unwatch <uid|nickname>"),
+            _ => Ok(Self::List),
+        }
+    }
+}
+
+/// Telegram user ids which watch client
+fn build_watchers_key(server_id: &str, unique_identifier: &str) -> String {
+    format!("ts_watchers_{server_id}_{unique_identifier}")
+}
+
+/// `(uid, nickname)` of clients which telegram user watches
+fn build_watches_key(server_id: &str, user_id: u64) -> String {
+    format!("ts_watches_{server_id}_{user_id}")
+}
+
+fn build_notified_key(server_id: &str, unique_identifier: &str) -> String {
+    format!("ts_watch_notified_{server_id}_{unique_identifier}")
+}
+
+async fn load<T: DeserializeOwned + Default>(
+    kv_map: &mut Box<dyn KVMap>,
+    key: String,
+) -> anyhow::Result<T> {
+    Ok(match kv_map.get(key).await? {
+        Some(value) => serde_json::from_str(&value)?,
+        None => T::default(),
+    })
+}
+
+/// Save value as JSON, key is deleted if list is empty
+async fn save<T: Serialize>(
+    kv_map: &mut Box<dyn KVMap>,
+    key: String,
+    value: &[T],
+) -> anyhow::Result<()> {
+    if value.is_empty() {
+        return kv_map.delete(key).await;
+    }
+    kv_map
+        .set(key, serde_json::to_string(value)?)
+        .await
+        .map(|_| ())
+}
+
+/// Handle subscription request of telegram user, `clients` are `(client id, nickname, uid)` of
+/// online clients, reply is shown to user
+pub async fn handle<'a>(
+    kv_map: &mut Box<dyn KVMap>,
+    server_id: &str,
+    clients: impl Iterator<Item = (i64, &'a str, &'a str)> + Clone,
+    user_id: u64,
+    request: &WatchRequest,
+) -> anyhow::Result<String> {
+    let mut watches: Vec<(String, String)> =
+        load(kv_map, build_watches_key(server_id, user_id)).await?;
+    let (unique_identifier, nickname) = match request {
+        WatchRequest::List => {
+            if watches.is_empty() {
+                return Ok("No watched client".into());
+            }
+            return Ok(watches
+                .iter()
+                .map(|(uid, nickname)| format!("{nickname} ({uid})"))
+                .collect::<Vec<_>>()
+                .join("\n"));
+        }
+        WatchRequest::Watch(Target::Uid(uid)) => {
+            let nickname = clients
+                .clone()
+                .find(|(_, _, client_uid)| client_uid == uid)
+                .map_or(uid.as_str(), |(_, nickname, _)| nickname);
+            (uid.clone(), nickname.to_string())
+        }
+        WatchRequest::Watch(target) => {
+            let client_id = target
+                .resolve(clients.clone())
+                .map_err(|e| anyhow::anyhow!("{e}, use UID if client is offline"))?;
+            let (_, nickname, uid) = clients.clone().find(|(id, ..)| *id == client_id).unwrap();
+            (uid.to_string(), nickname.to_string())
+        }
+        WatchRequest::Unwatch(target) => {
+            let index = watches.iter().position(|(uid, nickname)| match target {
+                Target::Uid(target) => uid == target,
+                Target::Nickname(target) => nickname.to_lowercase() == target.to_lowercase(),
+                Target::ClientId(client_id) => clients
+                    .clone()
+                    .any(|(id, _, client_uid)| id == *client_id && client_uid == uid),
+            });
+            let Some(index) = index else {
+                return Err(anyhow::anyhow!("Client {target} is not watched"));
+            };
+            let (uid, nickname) = watches.remove(index);
+            save(kv_map, build_watches_key(server_id, user_id), &watches).await?;
+            let mut watchers: Vec<u64> = load(kv_map, build_watchers_key(server_id, &uid)).await?;
+            watchers.retain(|watcher| *watcher != user_id);
+            save(kv_map, build_watchers_key(server_id, &uid), &watchers).await?;
+            return Ok(format!("Unwatched {nickname} ({uid})"));
+        }
+    };
+    if watches.iter().any(|(uid, _)| *uid == unique_identifier) {
+        return Ok(format!("Already watching {nickname} ({unique_identifier})"));
+    }
+    if watches.len() >= MAX_WATCHES {
+        return Err(anyhow::anyhow!("Watch at most {MAX_WATCHES} clients"));
+    }
+    watches.push((unique_identifier.clone(), nickname.clone()));
+    save(kv_map, build_watches_key(server_id, user_id), &watches).await?;
+    let mut watchers: Vec<u64> =
+        load(kv_map, build_watchers_key(server_id, &unique_identifier)).await?;
+    watchers.push(user_id);
+    save(
+        kv_map,
+        build_watchers_key(server_id, &unique_identifier),
+        &watchers,
+    )
+    .await?;
+    Ok(format!("Watching {nickname} ({unique_identifier})"))
+}
+
+/// Telegram user ids which should be notified when client enters, empty if client is notified
+/// in `cooldown` seconds
+pub async fn watchers(
+    kv_map: &mut Box<dyn KVMap>,
+    server_id: &str,
+    unique_identifier: &str,
+    cooldown: u64,
+) -> anyhow::Result<Vec<u64>> {
+    let watchers: Vec<u64> = load(kv_map, build_watchers_key(server_id, unique_identifier)).await?;
+    if watchers.is_empty() {
+        return Ok(watchers);
+    }
+    let now = kstool::time::get_current_second();
+    let notified: Option<u64> = kv_map
+        .get(build_notified_key(server_id, unique_identifier))
+        .await?
+        .and_then(|value| value.parse().ok());
+    if notified.is_some_and(|notified| notified + cooldown > now) {
+        return Ok(vec![]);
+    }
+    kv_map
+        .set(
+            build_notified_key(server_id, unique_identifier),
+            now.to_string(),
+        )
+        .await?;
+    Ok(watchers)
+}
+
+#[cfg(test)]
+mod test {
+    use super::WatchRequest;
+    use crate::moderation::Target;
+
+    #[test]
+    fn test_parse() {
+        assert_eq!(
+            WatchRequest::parse("watch", "\"Alice Bob\""),
+            Ok(WatchRequest::Watch(Target::Nickname("Alice Bob".into())))
+        );
+        assert_eq!(
+            WatchRequest::parse("unwatch", "QuietTeamspeak0000000000000="),
+            Ok(WatchRequest::Unwatch(Target::Uid(
+                "QuietTeamspeak0000000000000=".into()
+            )))
+        );
+        assert_eq!(WatchRequest::parse("watches", ""), Ok(WatchRequest::List));
+        assert!(WatchRequest::parse("watch", " ").is_err());
+    }
+}
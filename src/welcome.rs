@@ -0,0 +1,55 @@
+use crate::plugins::KVMap;
+
+fn build_last_seen_key(server_id: &str, unique_identifier: &str) -> String {
+    format!("ts_last_seen_{server_id}_{unique_identifier}")
+}
+
+/// Get last seen timestamp of client, `None` if client never seen before
+pub async fn last_seen(
+    kv_map: &mut Box<dyn KVMap>,
+    server_id: &str,
+    unique_identifier: &str,
+) -> anyhow::Result<Option<i64>> {
+    Ok(kv_map
+        .get(build_last_seen_key(server_id, unique_identifier))
+        .await?
+        .and_then(|v| v.parse().ok()))
+}
+
+pub async fn update_last_seen(
+    kv_map: &mut Box<dyn KVMap>,
+    server_id: &str,
+    unique_identifier: &str,
+    timestamp: i64,
+) -> anyhow::Result<()> {
+    kv_map
+        .set(
+            build_last_seen_key(server_id, unique_identifier),
+            timestamp.to_string(),
+        )
+        .await
+        .map(|_| ())
+}
+
+#[cfg(test)]
+mod test {
+    use crate::configure::config::Welcome;
+
+    #[test]
+    fn test_template() {
+        let welcome: Welcome = toml::from_str(
+            r#"
+enable = true
+first = "first"
+returning = "returning"
+[[group]]
+group-id = 6
+returning = "admin"
+"#,
+        )
+        .unwrap();
+        assert_eq!(welcome.template(&[8], true), Some("first"));
+        assert_eq!(welcome.template(&[8, 6], false), Some("admin"));
+        assert_eq!(welcome.template(&[6], true), Some("first"));
+    }
+}